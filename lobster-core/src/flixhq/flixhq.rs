@@ -0,0 +1,513 @@
+use crate::{
+    flixhq::{
+        html::FlixHQHTML,
+        ids::{MediaId, ServerId},
+    },
+    http::HttpClient,
+    providers::{
+        vidcloud::{Source, Track, VidCloud},
+        VideoExtractor,
+    },
+    redact::redact,
+    MediaType, Provider, BASE_URL,
+};
+use anyhow::anyhow;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// The genres FlixHQ has a `/genre/<slug>` page for, by name and slug.
+pub const GENRES: [(&str, &str); 28] = [
+    ("Action", "action"),
+    ("Action & Adventure", "action-adventure"),
+    ("Adventure", "adventure"),
+    ("Animation", "animation"),
+    ("Biography", "biography"),
+    ("Comedy", "comedy"),
+    ("Crime", "crime"),
+    ("Documentary", "documentary"),
+    ("Drama", "drama"),
+    ("Family", "family"),
+    ("Fantasy", "fantasy"),
+    ("History", "history"),
+    ("Horror", "horror"),
+    ("Kids", "kids"),
+    ("Music", "music"),
+    ("Mystery", "mystery"),
+    ("News", "news"),
+    ("Reality", "reality"),
+    ("Romance", "romance"),
+    ("Sci-Fi & Fantasy", "sci-fi-fantasy"),
+    ("Science Fiction", "science-fiction"),
+    ("Soap", "soap"),
+    ("Talk", "talk"),
+    ("Thriller", "thriller"),
+    ("TV Movie", "tv-movie"),
+    ("War", "war"),
+    ("War & Politics", "war-politics"),
+    ("Western", "western"),
+];
+
+#[derive(Debug)]
+pub enum FlixHQInfo {
+    Tv(FlixHQShow),
+    Movie(FlixHQMovie),
+}
+
+#[derive(Debug)]
+pub struct FlixHQMovie {
+    pub title: String,
+    pub year: String,
+    pub media_type: MediaType,
+    pub duration: String,
+    pub image: String,
+    pub id: String,
+}
+
+#[derive(Debug)]
+pub struct FlixHQShow {
+    pub title: String,
+    pub media_type: MediaType,
+    pub image: String,
+    pub id: String,
+    pub seasons: FlixHQSeason,
+    /// Episodes in the latest season, as listed on search and home pages. Shows from
+    /// [`FlixHQ::info`] leave this at 0 since their episodes are fetched lazily.
+    pub episodes: usize,
+}
+
+/// The seasons of a show. Shows from [`FlixHQ::info`] know their season ids and fetch the
+/// episodes of a season the first time they are asked for with [`FlixHQSeason::episodes`].
+#[derive(Debug, Clone)]
+pub struct FlixHQSeason {
+    pub total_seasons: usize,
+    pub season_ids: Vec<String>,
+    episodes: Vec<OnceLock<Vec<FlixHQEpisode>>>,
+}
+
+impl FlixHQSeason {
+    /// Seasons as listed on search and home pages, where only their number is known.
+    pub(super) fn from_count(total_seasons: usize) -> Self {
+        Self {
+            total_seasons,
+            season_ids: vec![],
+            episodes: vec![],
+        }
+    }
+
+    pub(super) fn from_ids(season_ids: Vec<String>) -> Self {
+        Self {
+            total_seasons: season_ids.len(),
+            episodes: season_ids.iter().map(|_| OnceLock::new()).collect(),
+            season_ids,
+        }
+    }
+
+    /// The episodes of season `season_number` (starting at 1), fetched on first use and
+    /// cached afterwards.
+    pub async fn episodes<C: HttpClient>(
+        &self,
+        flixhq: &FlixHQ<C>,
+        season_number: usize,
+    ) -> anyhow::Result<&[FlixHQEpisode]> {
+        let index = season_number
+            .checked_sub(1)
+            .filter(|index| *index < self.season_ids.len())
+            .ok_or_else(|| anyhow!("Season {} not found", season_number))?;
+
+        if let Some(episodes) = self.episodes[index].get() {
+            return Ok(episodes);
+        }
+
+        let episodes = flixhq.season_episodes(&self.season_ids[index]).await?;
+
+        Ok(self.episodes[index].get_or_init(|| episodes))
+    }
+}
+
+#[derive(Debug)]
+pub struct FlixHQResult {
+    pub id: String,
+    pub title: String,
+    pub year: String,
+    pub image: String,
+    pub duration: String,
+    pub media_type: Option<MediaType>,
+}
+
+/// What the page of a movie or show says about it, beyond what search results show.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FlixHQDetails {
+    pub title: String,
+    pub description: String,
+    /// As on the page, e.g. `2005-03-24`
+    pub released: String,
+    pub genres: Vec<String>,
+    pub casts: Vec<String>,
+    pub country: Vec<String>,
+    pub duration: String,
+    /// The IMDb rating, e.g. `8.9`
+    pub rating: Option<String>,
+    /// The YouTube id of the trailer, e.g. `qtRKdVHc-cE`
+    pub trailer: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlixHQEpisode {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug)]
+pub struct FlixHQServers {
+    pub servers: Vec<FlixHQServer>,
+}
+
+#[derive(Debug)]
+pub struct FlixHQServer {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FlixHQServerInfo {
+    link: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct FlixHQSources {
+    pub subtitles: FlixHQSubtitles,
+    pub sources: FlixHQSourceType,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum FlixHQSourceType {
+    VidCloud(Vec<Source>),
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum FlixHQSubtitles {
+    VidCloud(Vec<Track>),
+}
+
+pub struct FlixHQ<C> {
+    client: C,
+}
+
+impl<C: HttpClient> FlixHQ<C> {
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+
+    pub async fn search(&self, query: &str) -> anyhow::Result<Vec<FlixHQInfo>> {
+        self.search_page(query, 1).await
+    }
+
+    /// A page of the search results, counting from 1.
+    pub async fn search_page(&self, query: &str, page: usize) -> anyhow::Result<Vec<FlixHQInfo>> {
+        debug!("Starting search for query: {} (page {})", query, page);
+        let parsed_query = query.replace(" ", "-");
+
+        debug!("Formatted query: {}", parsed_query);
+
+        let url = if page > 1 {
+            format!("{}/search/{}?page={}", BASE_URL, parsed_query, page)
+        } else {
+            format!("{}/search/{}", BASE_URL, parsed_query)
+        };
+
+        let page_html = self.client.get(&url).await?;
+
+        debug!("Received HTML for search results");
+        let results = self.parse_search(&page_html);
+
+        debug!("Search completed with {} results", results.len());
+        Ok(results)
+    }
+
+    pub async fn info(&self, media_id: &str) -> anyhow::Result<FlixHQInfo> {
+        debug!("Fetching info for media_id: {}", media_id);
+        let info_html = self
+            .client
+            .get(&format!("{}/{}", BASE_URL, media_id))
+            .await?;
+
+        debug!("Received HTML for media info");
+        let search_result = self.single_page(&info_html, media_id);
+        let parsed_id: MediaId = search_result.id.parse()?;
+
+        match &search_result.media_type {
+            Some(MediaType::Tv) => {
+                debug!("Media type is Tv. Processing seasons and episodes");
+                let id = parsed_id.numeric_id().to_owned();
+
+                let season_html = self
+                    .client
+                    .get(&format!("{}/ajax/v2/tv/seasons/{}", BASE_URL, id))
+                    .await?;
+
+                let season_ids = self.season_info(&season_html);
+
+                debug!("Fetched {} seasons", season_ids.len());
+
+                return Ok(FlixHQInfo::Tv(FlixHQShow {
+                    episodes: 0,
+                    seasons: FlixHQSeason::from_ids(season_ids),
+                    id,
+                    title: search_result.title,
+                    image: search_result.image,
+                    media_type: MediaType::Tv,
+                }));
+            }
+
+            Some(MediaType::Movie) => {
+                debug!("Media type is Movie");
+                return Ok(FlixHQInfo::Movie(FlixHQMovie {
+                    id: parsed_id.numeric_id().to_owned(),
+                    title: search_result.title,
+                    image: search_result.image,
+                    year: search_result
+                        .year
+                        .split('-')
+                        .nth(0)
+                        .unwrap_or_default()
+                        .to_owned(),
+                    duration: search_result.duration,
+                    media_type: MediaType::Movie,
+                }));
+            }
+            None => {
+                error!("No results found for media_id: {}", media_id);
+                return Err(anyhow!("No results found"));
+            }
+        }
+    }
+
+    /// The synopsis, genres, cast and rating from the page of a movie or show.
+    pub async fn details(&self, media_id: &str) -> anyhow::Result<FlixHQDetails> {
+        debug!("Fetching details for media_id: {}", media_id);
+        let info_html = self
+            .client
+            .get(&format!("{}/{}", BASE_URL, media_id))
+            .await?;
+
+        let details = self.details_page(&info_html);
+
+        if details.title.is_empty() {
+            return Err(anyhow!("No details found for {}", media_id));
+        }
+
+        Ok(details)
+    }
+
+    async fn season_episodes(&self, season_id: &str) -> anyhow::Result<Vec<FlixHQEpisode>> {
+        debug!("Fetching episodes for season_id: {}", season_id);
+        let episode_html = self
+            .client
+            .get(&format!(
+                "{}/ajax/v2/season/episodes/{}",
+                BASE_URL, season_id
+            ))
+            .await?;
+
+        let episodes = self.episode_info(&episode_html);
+
+        debug!("Fetched {} episodes", episodes.len());
+        Ok(episodes)
+    }
+
+    pub async fn servers(&self, episode_id: &str, media_id: &str) -> anyhow::Result<FlixHQServers> {
+        debug!(
+            "Fetching servers for episode_id: {} and media_id: {}",
+            episode_id, media_id
+        );
+        let parsed_id: MediaId = media_id.parse()?;
+
+        let episode_id = format!(
+            "{}/ajax/{}",
+            BASE_URL,
+            if !episode_id.starts_with(&format!("{}/ajax", BASE_URL)) && parsed_id.is_tv() {
+                format!("v2/episode/servers/{}", episode_id)
+            } else {
+                format!("movie/episodes/{}", episode_id)
+            }
+        );
+
+        let server_html = self.client.get(&episode_id).await?;
+
+        debug!("Received HTML for servers");
+        let servers = self.info_server(server_html, media_id);
+
+        debug!("Found {} servers", servers.len());
+        Ok(FlixHQServers { servers })
+    }
+
+    pub async fn sources(
+        &self,
+        episode_id: &str,
+        media_id: &str,
+        server: Provider,
+    ) -> anyhow::Result<FlixHQSources> {
+        debug!(
+            "Fetching sources for episode_id: {}, media_id: {}, server: {}",
+            episode_id, media_id, server
+        );
+        let servers = self.servers(episode_id, media_id).await?;
+
+        let server_url = match servers
+            .servers
+            .iter()
+            .find(|s| s.name == server.to_string())
+        {
+            Some(server) => &server.url,
+            None => {
+                error!("Server {} not found!", server);
+                return Err(anyhow!("Server {} not found", server));
+            }
+        };
+
+        debug!("Selected server URL: {}", redact(server_url));
+        let server_id = ServerId::from_url(server_url)?;
+
+        let server_json = self
+            .client
+            .get(&format!("{}/ajax/episode/sources/{}", BASE_URL, server_id))
+            .await?;
+
+        let server_info: FlixHQServerInfo = serde_json::from_str(&server_json)?;
+
+        match server {
+            Provider::Vidcloud | Provider::Upcloud => {
+                debug!("Processing VidCloud or UpCloud sources");
+                let mut vidcloud = VidCloud::new();
+                vidcloud.extract(&self.client, &server_info.link).await?;
+
+                debug!("Sources and subtitles extracted successfully");
+                return Ok(FlixHQSources {
+                    sources: FlixHQSourceType::VidCloud(vidcloud.sources),
+                    subtitles: FlixHQSubtitles::VidCloud(vidcloud.tracks),
+                });
+            }
+        }
+    }
+
+    pub async fn recent_movies(&self) -> anyhow::Result<Vec<FlixHQInfo>> {
+        let recent_html = self.client.get(&format!("{}/home", BASE_URL)).await?;
+
+        let results = self.parse_recent_movies(&recent_html);
+
+        Ok(results)
+    }
+
+    pub async fn recent_shows(&self) -> anyhow::Result<Vec<FlixHQInfo>> {
+        let recent_html = self.client.get(&format!("{}/home", BASE_URL)).await?;
+
+        let results = self.parse_recent_shows(&recent_html);
+
+        Ok(results)
+    }
+
+    pub async fn trending_movies(&self) -> anyhow::Result<Vec<FlixHQInfo>> {
+        let trending_html = self.client.get(&format!("{}/home", BASE_URL)).await?;
+
+        let results = self.parse_trending_movies(&trending_html);
+
+        Ok(results)
+    }
+
+    pub async fn trending_shows(&self) -> anyhow::Result<Vec<FlixHQInfo>> {
+        let trending_html = self.client.get(&format!("{}/home", BASE_URL)).await?;
+
+        let results = self.parse_trending_shows(&trending_html);
+
+        Ok(results)
+    }
+
+    /// A page of the movies and shows of a genre, counting from 1. `genre` is a slug from
+    /// [`GENRES`], e.g. `sci-fi-fantasy`.
+    pub async fn genre(&self, genre: &str, page: usize) -> anyhow::Result<Vec<FlixHQInfo>> {
+        debug!("Fetching genre {} (page {})", genre, page);
+
+        let url = if page > 1 {
+            format!("{}/genre/{}?page={}", BASE_URL, genre, page)
+        } else {
+            format!("{}/genre/{}", BASE_URL, genre)
+        };
+
+        let genre_html = self.client.get(&url).await?;
+
+        let results = self.parse_genre(&genre_html);
+
+        debug!("Genre page completed with {} results", results.len());
+        Ok(results)
+    }
+
+    /// A page of the movies and shows from a country, counting from 1. `country` is its
+    /// ISO 3166 code, e.g. `KR`.
+    pub async fn country(&self, country: &str, page: usize) -> anyhow::Result<Vec<FlixHQInfo>> {
+        debug!("Fetching country {} (page {})", country, page);
+
+        let url = if page > 1 {
+            format!("{}/country/{}?page={}", BASE_URL, country, page)
+        } else {
+            format!("{}/country/{}", BASE_URL, country)
+        };
+
+        let country_html = self.client.get(&url).await?;
+
+        let results = self.parse_country(&country_html);
+
+        debug!("Country page completed with {} results", results.len());
+        Ok(results)
+    }
+
+    /// A page of the movies and shows someone played in, counting from 1. `name` is turned
+    /// into the slug of their page, so `Tom Hanks` is `/cast/tom-hanks`.
+    pub async fn cast(&self, name: &str, page: usize) -> anyhow::Result<Vec<FlixHQInfo>> {
+        let slug = name
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join("-");
+
+        debug!("Fetching cast {} (page {})", slug, page);
+
+        let url = if page > 1 {
+            format!("{}/cast/{}?page={}", BASE_URL, slug, page)
+        } else {
+            format!("{}/cast/{}", BASE_URL, slug)
+        };
+
+        let cast_html = self.client.get(&url).await?;
+
+        let results = self.parse_cast(&cast_html);
+
+        debug!("Cast page completed with {} results", results.len());
+        Ok(results)
+    }
+
+    /// A page of the movies and shows released in `year`, counting from 1, newest first.
+    pub async fn year(&self, year: u32, page: usize) -> anyhow::Result<Vec<FlixHQInfo>> {
+        debug!("Fetching year {} (page {})", year, page);
+
+        let mut url = format!(
+            "{}/filter?type=all&quality=all&release_year={}&genre=all&country=all",
+            BASE_URL, year
+        );
+
+        if page > 1 {
+            url.push_str(&format!("&page={}", page));
+        }
+
+        let filter_html = self.client.get(&url).await?;
+
+        let results = self.parse_filter(&filter_html);
+
+        debug!("Year page completed with {} results", results.len());
+        Ok(results)
+    }
+}
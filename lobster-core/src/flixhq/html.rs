@@ -1,7 +1,8 @@
 use super::flixhq::{
-    FlixHQ, FlixHQEpisode, FlixHQInfo, FlixHQMovie, FlixHQResult, FlixHQSeason, FlixHQServer,
-    FlixHQShow,
+    FlixHQ, FlixHQDetails, FlixHQEpisode, FlixHQInfo, FlixHQMovie, FlixHQResult, FlixHQSeason,
+    FlixHQServer, FlixHQShow,
 };
+use super::ids::MediaId;
 use crate::{MediaType, BASE_URL};
 use log::{debug, warn};
 use visdom::types::Elements;
@@ -17,7 +18,12 @@ pub(super) trait FlixHQHTML {
     fn parse_trending_movies(&self, html: &str) -> Vec<FlixHQInfo>;
     fn parse_trending_shows(&self, html: &str) -> Vec<FlixHQInfo>;
     fn parse_search(&self, html: &str) -> Vec<FlixHQInfo>;
+    fn parse_genre(&self, html: &str) -> Vec<FlixHQInfo>;
+    fn parse_country(&self, html: &str) -> Vec<FlixHQInfo>;
+    fn parse_cast(&self, html: &str) -> Vec<FlixHQInfo>;
+    fn parse_filter(&self, html: &str) -> Vec<FlixHQInfo>;
     fn single_page(&self, html: &str, id: &str) -> FlixHQResult;
+    fn details_page(&self, html: &str) -> FlixHQDetails;
     fn season_info(&self, html: &str) -> Vec<String>;
     fn episode_info(&self, html: &str) -> Vec<FlixHQEpisode>;
     fn info_server(&self, html: String, media_id: &str) -> Vec<FlixHQServer>;
@@ -173,7 +179,7 @@ fn recent_shows<'a>(
     )
 }
 
-impl FlixHQHTML for FlixHQ {
+impl<C> FlixHQHTML for FlixHQ<C> {
     fn parse_recent_shows(&self, html: &str) -> Vec<FlixHQInfo> {
         let recent_parser = Recent::new(html);
 
@@ -190,10 +196,7 @@ impl FlixHQHTML for FlixHQ {
                 id,
                 title,
                 image,
-                seasons: FlixHQSeason {
-                    total_seasons: season.replace("SS ", "").parse().unwrap_or(0),
-                    episodes: vec![],
-                },
+                seasons: FlixHQSeason::from_count(season.replace("SS ", "").parse().unwrap_or(0)),
                 episodes: episode.replace("EPS ", "").parse().unwrap_or(0),
                 media_type: MediaType::Tv,
             }));
@@ -268,10 +271,7 @@ impl FlixHQHTML for FlixHQ {
                 id,
                 title,
                 image,
-                seasons: FlixHQSeason {
-                    total_seasons: season.replace("SS ", "").parse().unwrap_or(0),
-                    episodes: vec![],
-                },
+                seasons: FlixHQSeason::from_count(season.replace("SS ", "").parse().unwrap_or(0)),
                 episodes: episode.replace("EPS ", "").parse().unwrap_or(0),
                 media_type: MediaType::Tv,
             }));
@@ -303,10 +303,9 @@ impl FlixHQHTML for FlixHQ {
                         id,
                         title,
                         image,
-                        seasons: FlixHQSeason {
-                            total_seasons: release_date.replace("SS ", "").parse().unwrap_or(0),
-                            episodes: vec![],
-                        },
+                        seasons: FlixHQSeason::from_count(
+                            release_date.replace("SS ", "").parse().unwrap_or(0),
+                        ),
                         episodes: episode.replace("EPS ", "").parse().unwrap_or(0),
                         media_type: MediaType::Tv,
                     }));
@@ -330,6 +329,27 @@ impl FlixHQHTML for FlixHQ {
         results
     }
 
+    fn parse_genre(&self, html: &str) -> Vec<FlixHQInfo> {
+        // Genre pages list their movies and shows with the same cards as the search
+        debug!("Parsing genre results from HTML.");
+        self.parse_search(html)
+    }
+
+    fn parse_country(&self, html: &str) -> Vec<FlixHQInfo> {
+        debug!("Parsing country results from HTML.");
+        self.parse_search(html)
+    }
+
+    fn parse_cast(&self, html: &str) -> Vec<FlixHQInfo> {
+        debug!("Parsing cast results from HTML.");
+        self.parse_search(html)
+    }
+
+    fn parse_filter(&self, html: &str) -> Vec<FlixHQInfo> {
+        debug!("Parsing filter results from HTML.");
+        self.parse_search(html)
+    }
+
     fn single_page(&self, html: &str, id: &str) -> FlixHQResult {
         debug!("Parsing single page for ID = {}", id);
         let elements = create_html_fragment(html);
@@ -341,7 +361,7 @@ impl FlixHQHTML for FlixHQ {
             image: search_parser.image(),
             year: info_parser.label(3, "Released:").join(""),
             duration: info_parser.duration(),
-            media_type: Some(MediaType::Tv),
+            media_type: id.parse::<MediaId>().ok().map(|id| id.media_type()),
             id: id.to_string(),
         };
 
@@ -349,6 +369,25 @@ impl FlixHQHTML for FlixHQ {
         result
     }
 
+    fn details_page(&self, html: &str) -> FlixHQDetails {
+        debug!("Parsing details page");
+        let elements = create_html_fragment(html);
+        let search_parser = Search::new(&elements);
+        let info_parser = Info::new(&elements);
+
+        FlixHQDetails {
+            title: search_parser.title(),
+            description: info_parser.description(),
+            released: info_parser.row_line("Released:").join(", "),
+            genres: info_parser.row_line("Genre:"),
+            casts: info_parser.row_line("Casts:"),
+            country: info_parser.row_line("Country:"),
+            duration: info_parser.row_line("Duration:").join(", "),
+            rating: info_parser.rating(),
+            trailer: info_parser.trailer(),
+        }
+    }
+
     fn season_info(&self, html: &str) -> Vec<String> {
         debug!("Extracting season information.");
         let season_parser = Season::new(html);
@@ -440,11 +479,7 @@ impl<'a> Page<'a> {
     }
 
     fn media_type(&self, id: &str) -> Option<MediaType> {
-        match id.split('/').next() {
-            Some("tv") => Some(MediaType::Tv),
-            Some("movie") => Some(MediaType::Movie),
-            _ => None,
-        }
+        id.parse::<MediaId>().ok().map(|id| id.media_type())
     }
 }
 
@@ -514,6 +549,60 @@ impl<'b> Info<'b> {
             .trim()
             .to_owned()
     }
+
+    fn description(&self) -> String {
+        self.elements
+            .find("div.m_i-d-content > div.description")
+            .text()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The values of a line like `Genre: Drama, Comedy` under the description, found by
+    /// its label rather than its position since not every page has all of them.
+    fn row_line(&self, label: &str) -> Vec<String> {
+        self.elements
+            .find("div.m_i-d-content > div.elements div.row-line")
+            .map(|_, element| element.text())
+            .into_iter()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .find_map(|line| line.strip_prefix(label).map(str::to_owned))
+            .map(|values| {
+                values
+                    .split(',')
+                    .map(|value| value.trim().to_owned())
+                    .filter(|value| !value.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn rating(&self) -> Option<String> {
+        self.elements
+            .find("span.item")
+            .map(|_, element| element.text())
+            .into_iter()
+            .find_map(|item| {
+                item.trim()
+                    .strip_prefix("IMDB:")
+                    .map(|rating| rating.trim().to_owned())
+            })
+            .filter(|rating| !rating.is_empty() && rating != "N/A")
+    }
+
+    /// The YouTube id from the embed address of the trailer, which the page only loads once
+    /// the trailer button is clicked.
+    fn trailer(&self) -> Option<String> {
+        let embed = self.elements.find("#iframe-trailer").attr("data-src")?.to_string();
+
+        embed
+            .split("/embed/")
+            .nth(1)
+            .and_then(|id| id.split(['?', '&', '/']).next())
+            .filter(|id| !id.is_empty())
+            .map(str::to_owned)
+    }
 }
 
 struct Season<'a> {
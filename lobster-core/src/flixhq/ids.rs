@@ -0,0 +1,109 @@
+use crate::MediaType;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum IdError {
+    #[error("Invalid media id: {0}")]
+    InvalidMediaId(String),
+    #[error("Invalid server url: {0}")]
+    InvalidServerUrl(String),
+}
+
+fn is_numeric(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A FlixHQ media id, e.g. `movie/watch-fight-club-19745` or `tv/watch-the-office-39383`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaId {
+    id: String,
+    media_type: MediaType,
+}
+
+impl MediaId {
+    pub fn media_type(&self) -> MediaType {
+        self.media_type.clone()
+    }
+
+    pub fn is_tv(&self) -> bool {
+        self.media_type == MediaType::Tv
+    }
+
+    /// The numeric suffix FlixHQ uses in its ajax endpoints, e.g. `19745`.
+    pub fn numeric_id(&self) -> &str {
+        self.id.rsplit('-').next().unwrap_or_default()
+    }
+}
+
+impl FromStr for MediaId {
+    type Err = IdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s.trim().trim_start_matches('/');
+
+        let media_type = match id.split_once('/') {
+            Some(("tv", _)) => MediaType::Tv,
+            Some(("movie", _)) => MediaType::Movie,
+            _ => return Err(IdError::InvalidMediaId(s.to_string())),
+        };
+
+        match id.rsplit_once('-') {
+            Some((_, numeric_id)) if is_numeric(numeric_id) => Ok(Self {
+                id: id.to_string(),
+                media_type,
+            }),
+            _ => Err(IdError::InvalidMediaId(s.to_string())),
+        }
+    }
+}
+
+impl Display for MediaId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+/// The id FlixHQ uses to look up servers. For movies this is the numeric part of the media id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeId(String);
+
+impl EpisodeId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&MediaId> for EpisodeId {
+    fn from(media_id: &MediaId) -> Self {
+        Self(media_id.numeric_id().to_string())
+    }
+}
+
+impl Display for EpisodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The id of a single server, taken from the end of its url, e.g. `4829542` in
+/// `https://flixhq.to/watch-tv/watch-the-office-39383.4829542`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerId(String);
+
+impl ServerId {
+    pub fn from_url(url: &str) -> Result<Self, IdError> {
+        match url.rsplit_once('.') {
+            Some((_, id)) if is_numeric(id) => Ok(Self(id.to_string())),
+            _ => Err(IdError::InvalidServerUrl(url.to_string())),
+        }
+    }
+}
+
+impl Display for ServerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
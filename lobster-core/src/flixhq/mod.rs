@@ -1,2 +1,3 @@
 pub mod flixhq;
 pub(super) mod html;
+pub mod ids;
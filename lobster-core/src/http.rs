@@ -0,0 +1,20 @@
+/// The HTTP layer used by the scrapers.
+///
+/// Futures are not required to be `Send` since browsers run them on a single thread.
+#[allow(async_fn_in_trait)]
+pub trait HttpClient {
+    async fn get(&self, url: &str) -> anyhow::Result<String>;
+}
+
+impl<T: HttpClient> HttpClient for &T {
+    async fn get(&self, url: &str) -> anyhow::Result<String> {
+        (**self).get(url).await
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpClient for reqwest::Client {
+    async fn get(&self, url: &str) -> anyhow::Result<String> {
+        Ok(reqwest::Client::get(self, url).send().await?.text().await?)
+    }
+}
@@ -0,0 +1,52 @@
+//! Platform independent scraping and parsing logic shared by lobster-rs.
+//!
+//! Nothing in here may depend on tokio, spawn processes or touch the filesystem so
+//! the crate keeps compiling to `wasm32-unknown-unknown`. All network access goes
+//! through [`http::HttpClient`], which frontends implement with whatever HTTP layer
+//! their platform offers.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+pub mod flixhq;
+pub mod http;
+pub mod playlist;
+pub mod providers;
+pub mod redact;
+pub mod self_test;
+
+pub static BASE_URL: &str = "https://flixhq.to";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "clap", clap(rename_all = "kebab-case"))]
+pub enum MediaType {
+    Tv,
+    Movie,
+}
+
+impl Display for MediaType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaType::Tv => write!(f, "tv"),
+            MediaType::Movie => write!(f, "movie"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "clap", clap(rename_all = "PascalCase"))]
+pub enum Provider {
+    Vidcloud,
+    Upcloud,
+}
+
+impl Display for Provider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Vidcloud => write!(f, "Vidcloud"),
+            Provider::Upcloud => write!(f, "Upcloud"),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use regex::Regex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub resolution: u32,
+    pub bandwidth: Option<u64>,
+    pub url: String,
+}
+
+impl Variant {
+    /// Estimated size in bytes of `duration` seconds of this variant.
+    pub fn estimated_size(&self, duration: f32) -> Option<u64> {
+        self.bandwidth
+            .map(|bandwidth| (bandwidth as f64 / 8.0 * duration as f64) as u64)
+    }
+}
+
+/// Parses the variant streams of an HLS master playlist, highest resolution first.
+pub fn variants(master: &str) -> Vec<Variant> {
+    let res_re = Regex::new(r"RESOLUTION=(\d+)x(\d+)").unwrap();
+    let bandwidth_re = Regex::new(r"[:,]BANDWIDTH=(\d+)").unwrap();
+
+    let mut variants = vec![];
+    let mut lines = master.lines().map(str::trim);
+
+    while let Some(line) = lines.next() {
+        if !line.starts_with("#EXT-X-STREAM-INF:") {
+            continue;
+        }
+
+        let Some(resolution) = res_re
+            .captures(line)
+            .and_then(|captures| captures[2].parse().ok())
+        else {
+            continue;
+        };
+
+        let bandwidth = bandwidth_re
+            .captures(line)
+            .and_then(|captures| captures[1].parse().ok());
+
+        if let Some(url) = lines.find(|line| !line.is_empty() && !line.starts_with('#')) {
+            if url.starts_with("https://") {
+                variants.push(Variant {
+                    resolution,
+                    bandwidth,
+                    url: url.to_string(),
+                });
+            }
+        }
+    }
+
+    variants.sort_by_key(|variant| std::cmp::Reverse(variant.resolution));
+
+    variants
+}
+
+/// Sums the `#EXTINF` segment durations of an HLS media playlist, in seconds.
+pub fn total_duration(playlist: &str) -> f32 {
+    let re = Regex::new(r#"#EXTINF:([0-9]*\.?[0-9]+),"#).unwrap();
+
+    re.captures_iter(playlist)
+        .filter_map(|capture| capture[1].parse::<f32>().ok())
+        .sum()
+}
@@ -0,0 +1,8 @@
+use crate::http::HttpClient;
+
+pub mod vidcloud;
+
+#[allow(async_fn_in_trait)]
+pub trait VideoExtractor {
+    async fn extract<C: HttpClient>(&mut self, client: &C, video_url: &str) -> anyhow::Result<()>;
+}
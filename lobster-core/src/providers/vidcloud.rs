@@ -1,13 +1,15 @@
-use crate::{providers::VideoExtractor, CLIENT};
+use crate::{http::HttpClient, providers::VideoExtractor, redact::redact};
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Source {
     pub file: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Track {
     pub file: String,
     pub label: String,
@@ -33,26 +35,20 @@ impl VidCloud {
 }
 
 impl VideoExtractor for VidCloud {
-    async fn extract(&mut self, server_url: &str) -> anyhow::Result<()> {
+    async fn extract<C: HttpClient>(&mut self, client: &C, server_url: &str) -> anyhow::Result<()> {
         let request_url = format!("https://dec.eatmynerds.live?url={}", server_url);
 
-        debug!("Starting extraction process for URL: {}", server_url);
-        debug!("Constructed request URL: {}", request_url);
+        debug!("Starting extraction process for URL: {}", redact(server_url));
+        debug!("Constructed request URL: {}", redact(&request_url));
 
-        let response = match CLIENT.get(&request_url).send().await {
-            Ok(resp) => {
+        let response = match client.get(&request_url).await {
+            Ok(text) => {
                 debug!("Received response from server.");
-                match resp.text().await {
-                    Ok(text) => text,
-                    Err(e) => {
-                        error!("Failed to read response text: {}", e);
-                        return Err(e.into());
-                    }
-                }
+                text
             }
             Err(e) => {
                 error!("HTTP request failed: {}", e);
-                return Err(e.into());
+                return Err(e);
             }
         };
 
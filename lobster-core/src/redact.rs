@@ -0,0 +1,59 @@
+//! Masks the parts of URLs that tend to carry tokens (query strings, fragments and long
+//! opaque path segments) before they end up in debug output.
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SHOW_SECRETS: AtomicBool = AtomicBool::new(false);
+
+/// Path segments at least this long are assumed to be tokens rather than names.
+const TOKEN_LENGTH: usize = 32;
+
+lazy_static! {
+    static ref URL_RE: Regex = Regex::new(r#"https?://[^\s"'<>]+"#).unwrap();
+    static ref TOKEN_RE: Regex =
+        Regex::new(&format!(r"/[A-Za-z0-9_\-.=]{{{},}}", TOKEN_LENGTH)).unwrap();
+}
+
+/// Turns redaction off for [`redact`], for debugging issues that need the full URLs.
+pub fn show_secrets(show: bool) {
+    SHOW_SECRETS.store(show, Ordering::Relaxed);
+}
+
+/// Redacts every URL in `text`, regardless of [`show_secrets`].
+pub fn redact_always(text: &str) -> String {
+    URL_RE
+        .replace_all(text, |captures: &Captures| {
+            let url = &captures[0];
+
+            let (url, secret) = match url.find(['?', '#']) {
+                Some(i) => (&url[..i], true),
+                None => (url, false),
+            };
+
+            // Leave the scheme and host alone, only the path can hold tokens
+            let host_start = url.find("://").map_or(0, |i| i + 3);
+            let path_start = url[host_start..]
+                .find('/')
+                .map_or(url.len(), |i| i + host_start);
+            let (host, path) = url.split_at(path_start);
+            let path = TOKEN_RE.replace_all(path, "/<redacted>");
+
+            if secret {
+                format!("{}{}?<redacted>", host, path)
+            } else {
+                format!("{}{}", host, path)
+            }
+        })
+        .to_string()
+}
+
+/// Redacts every URL in `text`, unless secrets were allowed with [`show_secrets`].
+pub fn redact(text: &str) -> String {
+    if SHOW_SECRETS.load(Ordering::Relaxed) {
+        return text.to_string();
+    }
+
+    redact_always(text)
+}
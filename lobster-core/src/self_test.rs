@@ -0,0 +1,362 @@
+//! Offline checks behind `lobster-rs --self-test`. The scrapers run against canned
+//! responses instead of the network, so packagers can validate a build in a sandbox.
+
+use crate::{
+    flixhq::{
+        flixhq::{FlixHQ, FlixHQInfo},
+        ids::{EpisodeId, MediaId, ServerId},
+    },
+    http::HttpClient,
+    playlist,
+    redact::redact_always,
+    BASE_URL,
+};
+use anyhow::{anyhow, ensure};
+
+const SEARCH_HTML: &str = r#"<div class="film_list-wrap">
+<div class="flw-item">
+  <div class="film-poster">
+    <img data-src="https://img.example/fight-club.jpg" class="film-poster-img">
+    <a href="/movie/watch-fight-club-19745" class="film-poster-ahref"></a>
+  </div>
+  <div class="film-detail">
+    <h2 class="film-name"><a href="/movie/watch-fight-club-19745" title="Fight Club">Fight Club</a></h2>
+    <div class="fd-infor"><span class="fdi-item">1999</span><span class="dot"></span><span class="fdi-item fdi-duration">139m</span></div>
+  </div>
+</div>
+<div class="flw-item">
+  <div class="film-poster">
+    <img data-src="https://img.example/the-office.jpg" class="film-poster-img">
+    <a href="/tv/watch-the-office-39383" class="film-poster-ahref"></a>
+  </div>
+  <div class="film-detail">
+    <h2 class="film-name"><a href="/tv/watch-the-office-39383" title="The Office">The Office</a></h2>
+    <div class="fd-infor"><span class="fdi-item">SS 9</span><span class="dot"></span><span class="fdi-item">EPS 23</span></div>
+  </div>
+</div>
+</div>"#;
+
+const MOVIE_HTML: &str = r#"<div id="main-wrapper">
+<div class="movie_information">
+  <div class="container">
+    <div class="m_i-detail">
+      <div class="m_i-d-poster"><div class="film-poster"><img src="https://img.example/fight-club.jpg"></div></div>
+      <div class="m_i-d-content">
+        <h2 class="heading-name">Fight Club</h2>
+        <div class="stats"><span class="item">HD</span><span class="item"><button class="btn-imdb">IMDB: 8.4</button></span><span class="item">139 min</span></div>
+        <div class="description">
+          A ticking-time-bomb insomniac and a slippery soap salesman
+          channel primal male aggression.
+        </div>
+        <div class="elements">
+          <div class="row-line">Genre: Drama</div>
+          <div class="row-line">Casts: Brad Pitt, Edward Norton</div>
+          <div class="row-line">Released: 1999-10-15</div>
+        </div>
+      </div>
+    </div>
+  </div>
+</div>
+<iframe id="iframe-trailer" data-src="https://www.youtube.com/embed/qtRKdVHc-cE?autoplay=1"></iframe>
+</div>"#;
+
+const SHOW_HTML: &str = r#"<div id="main-wrapper">
+<div class="movie_information">
+  <div class="container">
+    <div class="m_i-detail">
+      <div class="m_i-d-poster"><div class="film-poster"><img src="https://img.example/the-office.jpg"></div></div>
+      <div class="m_i-d-content"><h2 class="heading-name">The Office</h2></div>
+    </div>
+  </div>
+</div>
+</div>"#;
+
+const SEASONS_HTML: &str = r#"<div class="dropdown-menu">
+<a data-id="701" class="dropdown-item">Season 1</a>
+<a data-id="702" class="dropdown-item">Season 2</a>
+</div>"#;
+
+const SEASON_1_HTML: &str = r#"<ul class="nav">
+<li class="nav-item"><a data-id="1001" title="Eps 1: Pilot" class="nav-link">Pilot</a></li>
+<li class="nav-item"><a data-id="1002" title="Eps 2: Diversity Day" class="nav-link">Diversity Day</a></li>
+</ul>"#;
+
+const SEASON_2_HTML: &str = r#"<ul class="nav">
+<li class="nav-item"><a data-id="2001" title="Eps 1: The Dundies" class="nav-link">The Dundies</a></li>
+</ul>"#;
+
+const SERVERS_HTML: &str = r#"<ul class="nav">
+<li class="nav-item"><a id="watch-4829542" title="Server Vidcloud" class="nav-link">Vidcloud</a></li>
+<li class="nav-item"><a id="watch-4829543" title="Server Upcloud" class="nav-link">Upcloud</a></li>
+</ul>"#;
+
+const MASTER_PLAYLIST: &str = "#EXTM3U
+#EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=800000,RESOLUTION=640x360
+https://cdn.example/360/index.m3u8
+#EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=5000000,RESOLUTION=1920x1080
+https://cdn.example/1080/index.m3u8
+#EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=2000000,RESOLUTION=1280x720
+https://cdn.example/720/index.m3u8
+";
+
+const MEDIA_PLAYLIST: &str = "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXTINF:10.0,
+https://cdn.example/1080/seg-1.ts
+#EXTINF:10.0,
+https://cdn.example/1080/seg-2.ts
+#EXTINF:4.5,
+https://cdn.example/1080/seg-3.ts
+#EXT-X-ENDLIST
+";
+
+/// Answers requests from the fixtures above and fails on anything else, so a check can
+/// never reach the network.
+struct Fixtures;
+
+impl HttpClient for Fixtures {
+    async fn get(&self, url: &str) -> anyhow::Result<String> {
+        let path = url
+            .strip_prefix(BASE_URL)
+            .ok_or_else(|| anyhow!("No fixture for {}", url))?;
+
+        let html = match path {
+            "/search/fight-club" => SEARCH_HTML,
+            // Genre pages use the same cards as the search
+            "/genre/drama?page=2" => SEARCH_HTML,
+            "/country/KR" => SEARCH_HTML,
+            "/cast/brad-pitt" => SEARCH_HTML,
+            "/filter?type=all&quality=all&release_year=1999&genre=all&country=all" => SEARCH_HTML,
+            "/movie/watch-fight-club-19745" => MOVIE_HTML,
+            "/tv/watch-the-office-39383" => SHOW_HTML,
+            "/ajax/v2/tv/seasons/39383" => SEASONS_HTML,
+            "/ajax/v2/season/episodes/701" => SEASON_1_HTML,
+            "/ajax/v2/season/episodes/702" => SEASON_2_HTML,
+            "/ajax/v2/episode/servers/1001" => SERVERS_HTML,
+            _ => return Err(anyhow!("No fixture for {}", url)),
+        };
+
+        Ok(html.to_string())
+    }
+}
+
+fn ids() -> anyhow::Result<()> {
+    let movie: MediaId = "movie/watch-fight-club-19745".parse()?;
+    ensure!(!movie.is_tv(), "movie id parsed as a show");
+    ensure!(movie.numeric_id() == "19745", "wrong numeric id {}", movie.numeric_id());
+    ensure!(EpisodeId::from(&movie).as_str() == "19745", "wrong movie episode id");
+
+    let show: MediaId = "/tv/watch-the-office-39383".parse()?;
+    ensure!(show.is_tv(), "show id parsed as a movie");
+    ensure!(show.to_string() == "tv/watch-the-office-39383", "leading slash kept");
+
+    for invalid in ["watch-fight-club-19745", "movie/watch-fight-club", "anime/watch-x-1"] {
+        ensure!(
+            invalid.parse::<MediaId>().is_err(),
+            "accepted invalid media id {}",
+            invalid
+        );
+    }
+
+    let server = ServerId::from_url("https://flixhq.to/watch-tv/watch-the-office-39383.4829542")?;
+    ensure!(server.to_string() == "4829542", "wrong server id {}", server);
+    ensure!(
+        ServerId::from_url("https://flixhq.to/watch-tv/watch-the-office-39383").is_err(),
+        "accepted a server url without id"
+    );
+
+    Ok(())
+}
+
+fn playlists() -> anyhow::Result<()> {
+    let variants = playlist::variants(MASTER_PLAYLIST);
+    let resolutions: Vec<u32> = variants.iter().map(|variant| variant.resolution).collect();
+    ensure!(
+        resolutions == [1080, 720, 360],
+        "wrong variant order {:?}",
+        resolutions
+    );
+    ensure!(
+        variants[0].url == "https://cdn.example/1080/index.m3u8",
+        "wrong variant url {}",
+        variants[0].url
+    );
+    ensure!(
+        variants[0].estimated_size(8.0) == Some(5_000_000),
+        "wrong size estimate"
+    );
+
+    let duration = playlist::total_duration(MEDIA_PLAYLIST);
+    ensure!(duration == 24.5, "wrong playlist duration {}", duration);
+
+    Ok(())
+}
+
+fn redaction() -> anyhow::Result<()> {
+    let token = "a".repeat(40);
+    let cases = [
+        (
+            format!("https://cdn.example/{}/index.m3u8?sig=1", token),
+            String::from("https://cdn.example/<redacted>/index.m3u8?<redacted>"),
+        ),
+        (String::from("http://?x"), String::from("http://?<redacted>")),
+        (String::from("http://a"), String::from("http://a")),
+        (
+            format!("http://ü.example/{}", token),
+            String::from("http://ü.example/<redacted>"),
+        ),
+    ];
+
+    for (url, expected) in cases {
+        let redacted = redact_always(&url);
+        ensure!(redacted == expected, "redacted {} to {}", url, redacted);
+    }
+
+    Ok(())
+}
+
+async fn search() -> anyhow::Result<()> {
+    let results = FlixHQ::new(Fixtures).search("fight club").await?;
+
+    match results.as_slice() {
+        [FlixHQInfo::Movie(movie), FlixHQInfo::Tv(show)] => {
+            ensure!(movie.title == "Fight Club", "wrong title {}", movie.title);
+            ensure!(movie.id == "movie/watch-fight-club-19745", "wrong id {}", movie.id);
+            ensure!(movie.year == "1999", "wrong year {}", movie.year);
+            ensure!(movie.duration == "139m", "wrong duration {}", movie.duration);
+            ensure!(
+                movie.image == "https://img.example/fight-club.jpg",
+                "wrong image {}",
+                movie.image
+            );
+            ensure!(show.title == "The Office", "wrong title {}", show.title);
+            ensure!(show.seasons.total_seasons == 9, "wrong season count");
+            ensure!(show.episodes == 23, "wrong episode count");
+            Ok(())
+        }
+        _ => Err(anyhow!("expected a movie and a show, got {:?}", results)),
+    }
+}
+
+async fn browse() -> anyhow::Result<()> {
+    let flixhq = FlixHQ::new(Fixtures);
+
+    let mut results = flixhq.genre("drama", 2).await?;
+    results.extend(flixhq.country("KR", 1).await?);
+    results.extend(flixhq.year(1999, 1).await?);
+    results.extend(flixhq.cast("Brad Pitt", 1).await?);
+
+    match results.as_slice() {
+        [FlixHQInfo::Movie(movie), FlixHQInfo::Tv(show), ..] if results.len() == 8 => {
+            ensure!(movie.id == "movie/watch-fight-club-19745", "wrong id {}", movie.id);
+            ensure!(show.id == "tv/watch-the-office-39383", "wrong id {}", show.id);
+            Ok(())
+        }
+        _ => Err(anyhow!("expected a movie and a show per page, got {:?}", results)),
+    }
+}
+
+async fn movie_info() -> anyhow::Result<()> {
+    let info = FlixHQ::new(Fixtures)
+        .info("movie/watch-fight-club-19745")
+        .await?;
+
+    let FlixHQInfo::Movie(movie) = info else {
+        return Err(anyhow!("expected a movie, got {:?}", info));
+    };
+
+    ensure!(movie.title == "Fight Club", "wrong title {}", movie.title);
+    ensure!(movie.id == "19745", "wrong id {}", movie.id);
+    ensure!(movie.year == "1999", "wrong year {}", movie.year);
+    ensure!(movie.duration == "139 min", "wrong duration {}", movie.duration);
+
+    Ok(())
+}
+
+async fn movie_details() -> anyhow::Result<()> {
+    let details = FlixHQ::new(Fixtures)
+        .details("movie/watch-fight-club-19745")
+        .await?;
+
+    ensure!(details.title == "Fight Club", "wrong title {}", details.title);
+    ensure!(
+        details.description
+            == "A ticking-time-bomb insomniac and a slippery soap salesman channel primal male aggression.",
+        "wrong description {}",
+        details.description
+    );
+    ensure!(details.released == "1999-10-15", "wrong release {}", details.released);
+    ensure!(details.genres == ["Drama"], "wrong genres {:?}", details.genres);
+    ensure!(
+        details.casts == ["Brad Pitt", "Edward Norton"],
+        "wrong cast {:?}",
+        details.casts
+    );
+    ensure!(
+        details.rating.as_deref() == Some("8.4"),
+        "wrong rating {:?}",
+        details.rating
+    );
+    ensure!(details.country.is_empty(), "found a country {:?}", details.country);
+    ensure!(
+        details.trailer.as_deref() == Some("qtRKdVHc-cE"),
+        "wrong trailer {:?}",
+        details.trailer
+    );
+
+    Ok(())
+}
+
+async fn show_info() -> anyhow::Result<()> {
+    let flixhq = FlixHQ::new(Fixtures);
+    let info = flixhq.info("tv/watch-the-office-39383").await?;
+
+    let FlixHQInfo::Tv(show) = info else {
+        return Err(anyhow!("expected a show, got {:?}", info));
+    };
+
+    ensure!(show.title == "The Office", "wrong title {}", show.title);
+    ensure!(show.seasons.total_seasons == 2, "wrong season count");
+
+    let second_season = show.seasons.episodes(&flixhq, 2).await?;
+    ensure!(second_season.len() == 1, "wrong episode count {}", second_season.len());
+    ensure!(
+        show.seasons.episodes(&flixhq, 3).await.is_err(),
+        "found a season that doesn't exist"
+    );
+
+    let first_season = show.seasons.episodes(&flixhq, 1).await?;
+    ensure!(first_season.len() == 2, "wrong episode count {}", first_season.len());
+
+    let pilot = &first_season[0];
+    ensure!(pilot.id == "1001", "wrong episode id {}", pilot.id);
+    ensure!(pilot.title == "Eps 1: Pilot", "wrong episode title {}", pilot.title);
+
+    let servers = flixhq
+        .servers(&pilot.id, "tv/watch-the-office-39383")
+        .await?
+        .servers;
+    let names: Vec<&str> = servers.iter().map(|server| server.name.as_str()).collect();
+    ensure!(names == ["Vidcloud", "Upcloud"], "wrong servers {:?}", names);
+    ensure!(
+        servers[0].url == format!("{}/watch-tv/watch-the-office-39383.4829542", BASE_URL),
+        "wrong server url {}",
+        servers[0].url
+    );
+
+    Ok(())
+}
+
+/// Runs every check, returning its name and outcome.
+pub async fn run() -> Vec<(&'static str, anyhow::Result<()>)> {
+    vec![
+        ("id parsing", ids()),
+        ("playlist parsing", playlists()),
+        ("url redaction", redaction()),
+        ("search parsing", search().await),
+        ("genre, country, year and cast parsing", browse().await),
+        ("movie info parsing", movie_info().await),
+        ("movie details parsing", movie_details().await),
+        ("show info parsing", show_info().await),
+    ]
+}
@@ -1,26 +1,37 @@
-use crate::flixhq::flixhq::{FlixHQ, FlixHQInfo};
+use crate::flixhq::{
+    flixhq::{FlixHQ, FlixHQInfo, FlixHQSeason, GENRES},
+    ids::{EpisodeId, MediaId},
+};
+use crate::providers::tmdb::{Tmdb, TmdbEpisode};
+use crate::utils::history::{episode_number, mark_watched, show_progress, Store};
 use crate::utils::image_preview::remove_desktop_and_tmp;
 use crate::utils::{
+    batch::{BatchAction, BatchEntry},
     config::Config,
+    details::{details_preview, season_details},
+    last_selection::{last_selection, LastSelection},
     {
         fzf::FzfArgs,
-        rofi::{Rofi, RofiArgs, RofiSpawn},
+        menu::{menu_backend, menu_name},
+        rofi::RofiArgs,
+        search_filters::{matching_result, SearchFilters},
+        shuffle::{random_episode, random_index},
+        title::{ellipsize_title, split_episode, title_width, EpisodeNumber},
     },
 };
-use crate::{handle_servers, launcher};
-use crate::{Args, MediaType};
+use crate::{handle_servers, launcher, play_trailer};
+use crate::{
+    Args, MediaOptions, MediaType, PlayOptions, Provider, Quality, SearchOutput, SCRAPER_CLIENT,
+};
 use anyhow::anyhow;
-use log::{debug, error, info};
-use std::{io, io::Write, sync::Arc};
+use log::{debug, error, info, warn};
+use std::{io, io::Write, str::FromStr, sync::Arc};
 
 pub fn get_input(rofi: bool) -> anyhow::Result<String> {
     if rofi {
-        debug!("Using Rofi interface for input.");
-
-        let mut rofi = Rofi::new();
-        debug!("Initializing Rofi with arguments.");
+        debug!("Using {} for input.", menu_name());
 
-        let rofi_output = match rofi.spawn(&mut RofiArgs {
+        let rofi_output = match menu_backend().spawn(&mut RofiArgs {
             sort: true,
             dmenu: true,
             case_sensitive: true,
@@ -30,11 +41,11 @@ pub fn get_input(rofi: bool) -> anyhow::Result<String> {
             ..Default::default()
         }) {
             Ok(output) => {
-                debug!("Rofi command executed successfully.");
+                debug!("{} command executed successfully.", menu_name());
                 output
             }
             Err(e) => {
-                error!("Failed to execute Rofi command: {}", e);
+                error!("Failed to execute {} command: {}", menu_name(), e);
                 return Err(e.into());
             }
         };
@@ -43,14 +54,15 @@ pub fn get_input(rofi: bool) -> anyhow::Result<String> {
             .trim()
             .to_string();
 
-        debug!("Rofi returned input: {}", result);
+        debug!("{} returned input: {}", menu_name(), result);
         Ok(result)
     } else {
         debug!("Using terminal input for input.");
 
-        print!("Search Movie/TV Show: ");
-        if let Err(e) = io::stdout().flush() {
-            error!("Failed to flush stdout: {}", e);
+        // On stderr, so it doesn't end up in the output of --json-only or --print-link
+        eprint!("Search Movie/TV Show: ");
+        if let Err(e) = io::stderr().flush() {
+            error!("Failed to flush stderr: {}", e);
             return Err(e.into());
         }
 
@@ -73,98 +85,709 @@ pub fn get_input(rofi: bool) -> anyhow::Result<String> {
     }
 }
 
-pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()> {
-    if settings.clear_history {
-        let history_file = dirs::data_local_dir()
-            .expect("Failed to find local dir")
-            .join("lobster-rs/lobster_history.txt");
+/// Plays or downloads the episodes marked together in the episode menu one after the
+/// other, through the same queue as `--batch`.
+async fn queue_episodes(
+    settings: Arc<Args>,
+    config: Arc<Config>,
+    (media_id, media_title): (&str, &str),
+    season: usize,
+    episode_numbers: &[usize],
+) -> anyhow::Result<()> {
+    let action = if settings.play.print_link {
+        BatchAction::Link
+    } else if settings.download.is_some() {
+        BatchAction::Download
+    } else {
+        let choices = ["Play them in order", "Download them"];
+
+        let action_choice = launcher(
+            &vec![],
+            settings.media.rofi,
+            &mut RofiArgs {
+                process_stdin: Some(choices.join("\n")),
+                mesg: Some(format!("{} episodes marked", episode_numbers.len())),
+                dmenu: true,
+                case_sensitive: true,
+                entry_prompt: Some("".to_string()),
+                ..Default::default()
+            },
+            &mut FzfArgs {
+                process_stdin: Some(choices.join("\n")),
+                reverse: true,
+                header: Some(format!("{} episodes marked", episode_numbers.len())),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        if action_choice == choices[1] {
+            BatchAction::Download
+        } else {
+            BatchAction::Play
+        }
+    };
+
+    let entries = episode_numbers
+        .iter()
+        .map(|episode| BatchEntry {
+            query: format!("{} s{:02}e{:02}", media_title, season, episode + 1),
+            id: Some(media_id.to_string()),
+            season: Some(season),
+            episode: Some(episode + 1),
+            action,
+            quality: None,
+            dir: settings.download.clone().flatten(),
+        })
+        .collect();
+
+    let settings = Arc::new(Args {
+        queued: true,
+        ..(*settings).clone()
+    });
 
-        if history_file.exists() {
-            std::fs::remove_file(history_file)?;
+    batch(entries, settings, config).await
+}
+
+/// Keys that act on the highlighted entry of the fzf menus, passed to `--expect`.
+const MENU_KEYS: &str = "ctrl-d,ctrl-s,ctrl-p";
+
+/// Plays the trailer of the highlighted movie or show in the search menu.
+const TRAILER_KEY: &str = "ctrl-t";
+
+/// The line of the fzf header listing `MENU_KEYS`, with what they are set to now.
+fn menu_keys_header(settings: &Args) -> String {
+    format!(
+        "ctrl-d: download | ctrl-s: subtitles {} | ctrl-p: provider {}",
+        if settings.media.no_subs { "off" } else { "on" },
+        settings.media.provider.unwrap_or(Provider::Vidcloud)
+    )
+}
+
+/// Applies one of `MENU_KEYS` pressed in a menu. Returns whether the highlighted entry is
+/// taken, otherwise the menu is shown again with the changed settings.
+fn menu_key(settings: &mut Arc<Args>, config: &Config, key: &str) -> bool {
+    let mut changed = (**settings).clone();
+
+    let take = match key {
+        "ctrl-d" => {
+            info!("Downloading to {}", config.download);
+            changed.download = Some(Some(config.download.clone()));
+            true
+        }
+        "ctrl-s" => {
+            changed.media.no_subs = !changed.media.no_subs;
+            changed.media.subs = !changed.media.no_subs;
+            false
         }
+        "ctrl-p" => {
+            let providers = <Provider as clap::ValueEnum>::value_variants();
+            let current = providers
+                .iter()
+                .position(|provider| Some(*provider) == changed.media.provider)
+                .unwrap_or(0);
 
-        info!("History file deleted! Exiting...");
+            changed.media.provider = Some(providers[(current + 1) % providers.len()]);
+            false
+        }
+        _ => true,
+    };
 
-        std::process::exit(0);
+    debug!("{} pressed in the menu", key);
+    *settings = Arc::new(changed);
+
+    take
+}
+
+/// What is listed in pages: the results of a search or the movies and shows of a genre.
+enum Listing<'a> {
+    Search(&'a str),
+    Genre(&'a str),
+    Country(&'a str),
+    Cast(&'a str),
+    Year(u32),
+}
+
+impl Listing<'_> {
+    async fn page(&self, page: usize) -> anyhow::Result<Vec<FlixHQInfo>> {
+        let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+        match self {
+            Listing::Search(query) => flixhq.search_page(query, page).await,
+            Listing::Genre(genre) => flixhq.genre(genre, page).await,
+            Listing::Country(country) => flixhq.country(country, page).await,
+            Listing::Cast(name) => flixhq.cast(name, page).await,
+            Listing::Year(year) => flixhq.year(*year, page).await,
+        }
     }
+}
 
-    if settings.r#continue {
-        let history_file = dirs::data_local_dir()
-            .expect("Failed to find local dir")
-            .join("lobster-rs/lobster_history.txt");
+/// The results of the first `pages` pages of a search or genre. Pages past the last one add
+/// nothing new, which ends it early.
+async fn search_pages(listing: Listing<'_>, pages: u32) -> anyhow::Result<Vec<FlixHQInfo>> {
+    let mut results = listing.page(1).await?;
 
-        if !history_file.exists() {
-            error!("History file not found!");
-            std::process::exit(1)
+    let id = |result: &FlixHQInfo| match result {
+        FlixHQInfo::Movie(movie) => movie.id.clone(),
+        FlixHQInfo::Tv(tv) => tv.id.clone(),
+    };
+
+    let mut seen: Vec<String> = results.iter().map(id).collect();
+
+    for page in 2..=pages as usize {
+        let mut new_results = vec![];
+
+        for result in listing.page(page).await? {
+            if !seen.contains(&id(&result)) {
+                seen.push(id(&result));
+                new_results.push(result);
+            }
         }
 
-        let history_text = std::fs::read_to_string(history_file).unwrap();
+        if new_results.is_empty() {
+            debug!("Search page {} has nothing new, stopping", page);
+            break;
+        }
 
-        let mut history_choices: Vec<String> = vec![];
-        let mut history_image_files: Vec<(String, String, String)> = vec![];
-        let history_entries = history_text.split("\n").collect::<Vec<&str>>();
-        for (i, history_entry) in history_entries.iter().enumerate() {
-            if i == history_entries.len() - 1 {
-                break;
+        debug!("Search page {} added {} results", page, new_results.len());
+        results.extend(new_results);
+    }
+
+    Ok(results)
+}
+
+/// Finds the movie or show with the `--imdb` or `--tmdb` id by searching FlixHQ for the title
+/// and year TMDB has for it.
+async fn find_by_id(
+    filters: &SearchFilters,
+    tmdb_api_key: Option<&str>,
+) -> anyhow::Result<FlixHQInfo> {
+    let tmdb = Tmdb::new(
+        tmdb_api_key
+            .ok_or_else(|| anyhow!("--imdb and --tmdb need `tmdb_api_key` in the config"))?,
+    );
+
+    let entry = match (&filters.imdb, filters.tmdb) {
+        (Some(imdb_id), _) => tmdb
+            .by_imdb_id(imdb_id)
+            .await?
+            .ok_or_else(|| anyhow!("TMDB doesn't know {}", imdb_id))?,
+        (None, Some(id)) => {
+            let media_type = filters.media_type.clone().unwrap_or(MediaType::Movie);
+            tmdb.by_id(id, media_type).await?
+        }
+        (None, None) => return Err(anyhow!("Needs --imdb or --tmdb")),
+    };
+
+    debug!(
+        "Looking for {} ({}) on FlixHQ",
+        entry.title, entry.release_date
+    );
+
+    let results = search_pages(Listing::Search(&entry.title), filters.pages.unwrap_or(1)).await?;
+
+    matching_result(results, &entry).ok_or_else(|| {
+        anyhow!(
+            "Couldn't find {} ({}) on FlixHQ",
+            entry.title,
+            entry.release_date.get(..4).unwrap_or("?")
+        )
+    })
+}
+
+/// Asks for a genre, returning its slug.
+async fn choose_genre(rofi: bool) -> anyhow::Result<String> {
+    let names: Vec<&str> = GENRES.iter().map(|(name, _)| *name).collect();
+
+    let genre_choice = launcher(
+        &vec![],
+        rofi,
+        &mut RofiArgs {
+            process_stdin: Some(names.join("\n")),
+            mesg: Some("Choose a genre".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(names.join("\n")),
+            reverse: true,
+            header: Some("Choose a genre".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    GENRES
+        .iter()
+        .find(|(name, _)| *name == genre_choice)
+        .map(|(_, slug)| slug.to_string())
+        .ok_or_else(|| anyhow!("No genre chosen"))
+}
+
+/// Searches for `query`, asking for it when there is none, or lists the recent or trending
+/// movies or shows, or those of a genre, country, actor or year, instead. An episode like
+/// `s03e07` at the end of the query is taken off and returned with the results, which are
+/// narrowed down and sorted by `filters`.
+pub async fn find_media(
+    query: Option<&str>,
+    recent: Option<&MediaType>,
+    trending: Option<&MediaType>,
+    genre: Option<&Option<String>>,
+    filters: &SearchFilters,
+    rofi: bool,
+    tmdb_api_key: Option<&str>,
+) -> anyhow::Result<(Vec<FlixHQInfo>, Option<EpisodeNumber>)> {
+    if filters.has_id() {
+        return Ok((vec![find_by_id(filters, tmdb_api_key).await?], None));
+    }
+
+    let mut episode = None;
+
+    let results = if let Some(recent) = recent {
+        match recent {
+            MediaType::Movie => FlixHQ::new(&*SCRAPER_CLIENT).recent_movies().await?,
+            MediaType::Tv => FlixHQ::new(&*SCRAPER_CLIENT).recent_shows().await?,
+        }
+    } else if let Some(trending) = trending {
+        match trending {
+            MediaType::Movie => FlixHQ::new(&*SCRAPER_CLIENT).trending_movies().await?,
+            MediaType::Tv => FlixHQ::new(&*SCRAPER_CLIENT).trending_shows().await?,
+        }
+    } else if let Some(genre) = genre {
+        let genre = match genre {
+            Some(genre) => genre.clone(),
+            None => choose_genre(rofi).await?,
+        };
+
+        search_pages(Listing::Genre(&genre), filters.pages.unwrap_or(1)).await?
+    } else if let Some(country) = &filters.country {
+        search_pages(Listing::Country(country), filters.pages.unwrap_or(1)).await?
+    } else if let Some(actor) = &filters.actor {
+        search_pages(Listing::Cast(actor), filters.pages.unwrap_or(1)).await?
+    } else if let Some(year) = filters
+        .year
+        .and_then(|year| year.single())
+        .filter(|_| query.is_none())
+    {
+        search_pages(Listing::Year(year), filters.pages.unwrap_or(1)).await?
+    } else {
+        let query = match query {
+            Some(query) => query.to_string(),
+            None => get_input(rofi)?,
+        };
+
+        let (query, query_episode) = split_episode(&query);
+        episode = query_episode;
+
+        search_pages(Listing::Search(&query), filters.pages.unwrap_or(1)).await?
+    };
+
+    if results.is_empty() {
+        return Err(anyhow!("No results found"));
+    }
+
+    let results = filters.apply(results);
+
+    if results.is_empty() {
+        return Err(anyhow!("None of the results match --type and --year"));
+    }
+
+    Ok((results, episode))
+}
+
+/// A random movie or show for `--random`, from the trending ones or those of `genre`, after
+/// `--type` and `--year` narrowed them down.
+async fn random_media(genre: Option<&str>, filters: &SearchFilters) -> anyhow::Result<FlixHQInfo> {
+    let results = match genre {
+        Some(genre) => search_pages(Listing::Genre(genre), filters.pages.unwrap_or(1)).await?,
+        None => {
+            let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+            let mut trending = vec![];
+
+            if filters.media_type != Some(MediaType::Tv) {
+                trending.extend(flixhq.trending_movies().await?);
             }
 
-            let entries = history_entry.split("\t").collect::<Vec<&str>>();
-            let title = entries[0];
-            let media_type = entries[2].split('/').collect::<Vec<&str>>()[0];
-            match media_type {
-                "tv" => {
-                    let temp_episode = entries[5].replace(":", "");
+            if filters.media_type != Some(MediaType::Movie) {
+                trending.extend(flixhq.trending_shows().await?);
+            }
 
-                    let episode_number = temp_episode
-                        .split_whitespace()
-                        .nth(1)
-                        .expect("Failed to parse episode number from history!");
+            trending
+        }
+    };
+
+    let mut results = filters.apply(results);
+
+    if results.is_empty() {
+        return Err(anyhow!("Nothing to pick a random title from"));
+    }
+
+    let result = results.swap_remove(random_index(results.len()));
+
+    match &result {
+        FlixHQInfo::Movie(movie) => info!("Picked {} ({})", movie.title, movie.year),
+        FlixHQInfo::Tv(tv) => info!("Picked {}", tv.title),
+    }
+
+    Ok(result)
+}
+
+/// What the episode menu shows after a title when TMDB knows the episode, e.g.
+/// ` [42m] [2005-03-24]`.
+fn episode_facts(episode: &TmdbEpisode) -> String {
+    [
+        episode.runtime.map(|runtime| format!(" [{}m]", runtime)),
+        episode
+            .air_date
+            .as_ref()
+            .map(|air_date| format!(" [{}]", air_date)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// The entry of the season menu that plays a random episode, like `--shuffle`.
+const RANDOM_EPISODE: &str = "Random episode";
+
+/// Plays a random episode of a show, for `--shuffle` and the "Random episode" entry of the
+/// season menu.
+async fn play_random_episode(
+    config: Arc<Config>,
+    settings: Arc<Args>,
+    seasons: &FlixHQSeason,
+    (media_id, media_title, media_image): (&str, &str, &str),
+) -> anyhow::Result<()> {
+    let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+    let (season_number, episode_number) = random_episode(&flixhq, seasons).await?;
+    let episode_info = &seasons.episodes(&flixhq, season_number).await?[episode_number];
+
+    info!(
+        "Shuffled to S{:02}E{:02} of {}",
+        season_number,
+        episode_number + 1,
+        media_title
+    );
+
+    handle_servers(
+        config,
+        settings,
+        None,
+        (
+            Some(episode_info.title.clone()),
+            &episode_info.id,
+            media_id,
+            media_title,
+            media_image,
+        ),
+        Some((season_number, episode_number, seasons.clone())),
+    )
+    .await
+}
+
+/// Prints the results for `lobster-rs search`, one per line with the id after a tab, or as
+/// JSON.
+pub async fn search(
+    query: Option<&str>,
+    recent: Option<&MediaType>,
+    trending: Option<&MediaType>,
+    genre: Option<&Option<String>>,
+    filters: &SearchFilters,
+    json: bool,
+    tmdb_api_key: Option<&str>,
+) -> anyhow::Result<()> {
+    let (results, _) =
+        find_media(query, recent, trending, genre, filters, false, tmdb_api_key).await?;
+
+    if json {
+        let output: Vec<SearchOutput> = results.iter().map(SearchOutput::from).collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    for result in results {
+        match result {
+            FlixHQInfo::Movie(movie) => println!(
+                "{} (movie) [{}] [{}]\t{}",
+                movie.title, movie.year, movie.duration, movie.id
+            ),
+            FlixHQInfo::Tv(tv) => println!(
+                "{} (tv) [SZNS {}] [EPS {}]\t{}",
+                tv.title, tv.seasons.total_seasons, tv.episodes, tv.id
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Plays, prints or downloads a single `--batch` entry, taking the first search result.
+async fn batch_entry(
+    entry: &BatchEntry,
+    settings: &Args,
+    config: Arc<Config>,
+) -> anyhow::Result<()> {
+    let (query, query_episode) = split_episode(&entry.query);
+
+    let episode = match (entry.season, entry.episode) {
+        (Some(season), Some(episode)) if season > 0 && episode > 0 => {
+            Some(EpisodeNumber { season, episode })
+        }
+        (None, None) => query_episode,
+        _ => {
+            return Err(anyhow!(
+                "`season` and `episode` need to be given together, from 1"
+            ))
+        }
+    };
+
+    let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+    let media_id = match &entry.id {
+        Some(id) => id.clone(),
+        None if query.is_empty() => return Err(anyhow!("Needs a `query` or an `id`")),
+        None => match flixhq.search(&query).await?.into_iter().next() {
+            Some(FlixHQInfo::Movie(movie)) => movie.id,
+            Some(FlixHQInfo::Tv(tv)) => tv.id,
+            None => return Err(anyhow!("No results found for {}", query)),
+        },
+    };
+
+    let quality = match entry.quality {
+        Some(quality) => Some(Quality::from_str(&quality.to_string())?),
+        None => settings.media.quality,
+    };
+
+    let download = (entry.action == BatchAction::Download)
+        .then(|| Some(entry.dir.clone().unwrap_or_else(|| config.download.clone())));
+
+    let settings = Arc::new(Args {
+        download,
+        media: MediaOptions {
+            quality,
+            ..settings.media.clone()
+        },
+        play: PlayOptions {
+            print_link: entry.action == BatchAction::Link,
+            ..settings.play.clone()
+        },
+        ..settings.clone()
+    });
+
+    match flixhq.info(&media_id).await? {
+        FlixHQInfo::Tv(tv) => {
+            let episode =
+                episode.ok_or_else(|| anyhow!("{} is a show, it needs an episode", tv.title))?;
+
+            let episode_info = tv
+                .seasons
+                .episodes(&flixhq, episode.season)
+                .await?
+                .get(episode.episode - 1)
+                .ok_or_else(|| anyhow!("{} has no episode {}", tv.title, episode))?;
+
+            handle_servers(
+                config,
+                settings,
+                None,
+                (
+                    Some(episode_info.title.clone()),
+                    &episode_info.id,
+                    &media_id,
+                    &tv.title,
+                    &tv.image,
+                ),
+                Some((episode.season, episode.episode - 1, tv.seasons.clone())),
+            )
+            .await
+        }
+        FlixHQInfo::Movie(movie) => {
+            if let Some(episode) = episode {
+                warn!("{} is a movie, ignoring {}", movie.title, episode);
+            }
+
+            let episode_id = EpisodeId::from(&media_id.parse::<MediaId>()?);
+
+            handle_servers(
+                config,
+                settings,
+                None,
+                (
+                    None,
+                    episode_id.as_str(),
+                    &media_id,
+                    &movie.title,
+                    &movie.image,
+                ),
+                None,
+            )
+            .await
+        }
+    }
+}
+
+/// Works through the entries of a `--batch` file one after the other. An entry that fails
+/// is logged and skipped, so one broken entry doesn't hold up the rest of the queue.
+pub async fn batch(
+    entries: Vec<BatchEntry>,
+    settings: Arc<Args>,
+    config: Arc<Config>,
+) -> anyhow::Result<()> {
+    let total = entries.len();
+    let mut failed = 0;
 
-                    if settings.image_preview {
+    for (i, entry) in entries.iter().enumerate() {
+        let name = match &entry.id {
+            Some(id) if entry.query.is_empty() => id,
+            _ => &entry.query,
+        };
+
+        info!("[{}/{}] {}", i + 1, total, name);
+
+        if let Err(e) = batch_entry(entry, &settings, config.clone()).await {
+            error!("[{}/{}] {}", i + 1, total, e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} of {} entries failed", failed, total));
+    }
+
+    info!("Done with all {} entries", total);
+
+    Ok(())
+}
+
+/// Plays the last picked movie or episode again, from the same server and in the same
+/// quality unless another one is given with `-q`.
+async fn repeat_last(
+    settings: Arc<Args>,
+    config: Arc<Config>,
+    last: LastSelection,
+) -> anyhow::Result<()> {
+    info!("{}", last.label());
+
+    let quality = settings.media.quality.or_else(|| {
+        last.quality
+            .and_then(|quality| Quality::from_str(&quality.to_string()).ok())
+    });
+
+    let settings = Arc::new(Args {
+        media: MediaOptions {
+            provider: Some(last.provider),
+            quality,
+            ..settings.media.clone()
+        },
+        ..(*settings).clone()
+    });
+
+    match last.episode {
+        Some((season, episode)) => {
+            let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+            let FlixHQInfo::Tv(tv) = flixhq.info(&last.media_id).await? else {
+                return Err(anyhow!("{} isn't a show", last.title));
+            };
+
+            let episode_info = tv
+                .seasons
+                .episodes(&flixhq, season)
+                .await?
+                .get(episode)
+                .cloned()
+                .ok_or_else(|| anyhow!("{} has no episode {} anymore", last.title, episode + 1))?;
+
+            handle_servers(
+                config,
+                settings,
+                None,
+                (
+                    Some(episode_info.title),
+                    &episode_info.id,
+                    &last.media_id,
+                    &last.title,
+                    &last.image,
+                ),
+                Some((season, episode, tv.seasons.clone())),
+            )
+            .await
+        }
+        None => {
+            let episode_id = EpisodeId::from(&last.media_id.parse::<MediaId>()?);
+
+            handle_servers(
+                config,
+                settings,
+                None,
+                (
+                    None,
+                    episode_id.as_str(),
+                    &last.media_id,
+                    &last.title,
+                    &last.image,
+                ),
+                None,
+            )
+            .await
+        }
+    }
+}
+
+pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()> {
+    if settings.play.last {
+        let last = last_selection().ok_or_else(|| anyhow!("Nothing was played yet"))?;
+        return repeat_last(settings, config, last).await;
+    }
+
+    if settings.play.r#continue {
+        let store = Store::load()?;
+
+        if store.entries().is_empty() {
+            error!("History is empty!");
+            std::process::exit(1)
+        }
+
+        let mut history_choices: Vec<String> = vec![];
+        let mut history_image_files: Vec<(String, String, String)> = vec![];
+
+        for entry in store.entries() {
+            let display = match &entry.episode {
+                Some(episode) => {
+                    if settings.media.image_preview {
                         history_image_files.push((
-                            format!("{} {} {}", title, entries[4], entries[5]),
-                            entries[6].to_string(),
-                            entries[3].to_string(),
+                            format!("{} {} {}", entry.title, episode.season, episode.title),
+                            entry.image.clone(),
+                            episode.id.clone(),
                         ))
                     }
 
-                    history_choices.push(format!(
-                        "{} (tv) Season {} {}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
-                        title,
-                        entries[4],
-                        entries[5],
-                        entries[3],
-                        entries[2],
-                        entries[6],
-                        entries[4],
-                        episode_number,
-                        title,
-                        entries[5],
-                    ))
+                    format!(
+                        "{} (tv) Season {} {}",
+                        entry.title, episode.season, episode.title
+                    )
                 }
-                "movie" => {
-                    let episode_id = entries[2].rsplit("-").collect::<Vec<&str>>()[0];
-
-                    if settings.image_preview {
+                None => {
+                    if settings.media.image_preview {
                         history_image_files.push((
-                            title.to_string(),
-                            entries[3].to_string(),
-                            entries[2].to_string(),
+                            entry.title.clone(),
+                            entry.image.clone(),
+                            entry.media_id.clone(),
                         ))
                     }
 
-                    history_choices.push(format!(
-                        "{} (movie)\t{}\t{}\t{}",
-                        title, episode_id, entries[2], entries[3]
-                    ))
+                    format!("{} (movie)", entry.title)
                 }
-                _ => {}
-            }
+            };
+
+            history_choices.push(format!("{}\t{}", display, entry.media_id));
         }
 
         let history_choice = launcher(
             &history_image_files,
-            settings.rofi,
+            settings.media.rofi,
             &mut RofiArgs {
                 mesg: Some("Choose an entry: ".to_string()),
                 process_stdin: Some(history_choices.join("\n")),
@@ -185,72 +808,117 @@ pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()>
         )
         .await;
 
-        let entry = history_choice.split("\t").collect::<Vec<&str>>();
-        let media_type = entry[2].split('/').collect::<Vec<&str>>()[0];
-        match media_type {
-            "tv" => {
-                let show_info = FlixHQ.info(entry[2]).await?;
+        let entry = history_choice
+            .split('\t')
+            .nth(1)
+            .and_then(|media_id| store.get(media_id))
+            .ok_or_else(|| anyhow!("No history entry for {}", history_choice))?;
+
+        let media_id: MediaId = entry.media_id.parse()?;
+
+        match (media_id.media_type(), &entry.episode) {
+            (MediaType::Tv, Some(episode)) => {
+                let show_info = FlixHQ::new(&*SCRAPER_CLIENT).info(&entry.media_id).await?;
                 if let FlixHQInfo::Tv(tv) = show_info {
-                    let season_number = entry[4]
-                        .parse::<usize>()
-                        .expect("Failed to parse season number!");
-                    let episode_number = entry[5]
-                        .parse::<usize>()
-                        .expect("Failed to parse episode number!");
+                    let episode_number = episode_number(&episode.title)
+                        .expect("Failed to parse episode number from history!");
+
+                    // Stepping back from the 1-based episode number lands on the saved episode
                     handle_servers(
                         config.clone(),
                         settings.clone(),
                         Some(false),
-                        (Some(entry[7].to_string()), entry[1], entry[2], entry[6], entry[3]),
-                        Some((season_number, episode_number, tv.seasons.episodes)),
+                        (
+                            Some(episode.title.clone()),
+                            &episode.id,
+                            &entry.media_id,
+                            &entry.title,
+                            &entry.image,
+                        ),
+                        Some((episode.season, episode_number, tv.seasons)),
                     )
                     .await?;
                 }
             }
-            "movie" => {
+            _ => {
+                let episode_id = EpisodeId::from(&media_id);
+
                 handle_servers(
                     config.clone(),
                     settings.clone(),
                     Some(false),
-                    (None, entry[1], entry[2], entry[0], entry[3]),
+                    (
+                        None,
+                        episode_id.as_str(),
+                        &entry.media_id,
+                        &entry.title,
+                        &entry.image,
+                    ),
                     None,
                 )
                 .await?
             }
-            _ => {}
         }
     }
 
-    let results = if let Some(recent) = &settings.recent {
-        match recent {
-            MediaType::Movie => FlixHQ.recent_movies().await?,
-            MediaType::Tv => FlixHQ.recent_shows().await?,
-        }
-    } else if let Some(trending) = &settings.trending {
-        match trending {
-            MediaType::Movie => FlixHQ.trending_movies().await?,
-            MediaType::Tv => FlixHQ.trending_shows().await?,
+    let (results, query_episode) = match &settings.media.random {
+        Some(genre) => (
+            vec![random_media(genre.as_deref(), &settings.media.filters).await?],
+            None,
+        ),
+        None => {
+            find_media(
+                settings.query.as_deref(),
+                settings.media.recent.as_ref(),
+                settings.media.trending.as_ref(),
+                settings.media.genre.as_ref(),
+                &settings.media.filters,
+                settings.media.rofi,
+                config.tmdb_api_key.as_deref(),
+            )
+            .await?
         }
-    } else {
-        let query = match &settings.query {
-            Some(query) => query.to_string(),
-            None => get_input(settings.rofi)?,
-        };
-
-        FlixHQ.search(&query).await?
     };
 
-    if results.is_empty() {
-        return Err(anyhow!("No results found"));
-    }
+    let episode = settings.media.episode.or(query_episode);
+
+    let settings = if settings.play.json_only {
+        Arc::new(Args {
+            search_results: results.iter().map(SearchOutput::from).collect(),
+            ..(*settings).clone()
+        })
+    } else {
+        settings
+    };
 
     let mut search_results: Vec<String> = vec![];
     let mut image_preview_files: Vec<(String, String, String)> = vec![];
+    let mut truncated_titles = false;
+
+    // Offered above the results, with `last` in place of the id
+    let last = last_selection().filter(|_| !settings.unattended() && !settings.play.json_only);
+
+    if let Some(last) = &last {
+        search_results.push(format!(
+            "{}\tlast\t\t{}\t{}",
+            last.image,
+            last.label(),
+            last.title
+        ));
+    }
+
+    let history_progress = show_progress();
+
+    let max_width = title_width(
+        config.title_max_width,
+        settings.media.rofi,
+        settings.media.image_preview,
+    );
 
     for result in results {
         match result {
             FlixHQInfo::Movie(movie) => {
-                if settings.image_preview {
+                if settings.media.image_preview {
                     image_preview_files.push((
                         movie.title.to_string(),
                         movie.image.to_string(),
@@ -272,18 +940,17 @@ pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()>
                     }
                 };
 
+                let suffix = format!("[{}] [{}]", movie.year, formatted_duration);
+                let display_title = ellipsize_title(&movie.title, &suffix, max_width);
+                truncated_titles |= display_title != format!("{} {}", movie.title, suffix);
+
                 search_results.push(format!(
-                    "{}\t{}\t{}\t{} [{}] [{}]",
-                    movie.image,
-                    movie.id,
-                    movie.media_type,
-                    movie.title,
-                    movie.year,
-                    formatted_duration
+                    "{}\t{}\t{}\t{}\t{}",
+                    movie.image, movie.id, movie.media_type, display_title, movie.title
                 ));
             }
             FlixHQInfo::Tv(tv) => {
-                if settings.image_preview {
+                if settings.media.image_preview {
                     image_preview_files.push((
                         tv.title.to_string(),
                         tv.image.to_string(),
@@ -291,45 +958,105 @@ pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()>
                     ));
                 }
 
+                let mut suffix = format!(
+                    "[SZNS {}] [EPS {}]",
+                    tv.seasons.total_seasons, tv.episodes
+                );
+
+                if let Some((season_number, episode_title)) = history_progress.get(&tv.id) {
+                    match episode_number(episode_title) {
+                        Some(episode_number) => suffix.push_str(&format!(
+                            " [continue S{:02}E{:02}]",
+                            season_number, episode_number
+                        )),
+                        None => suffix.push_str(" [continue]"),
+                    }
+                }
+                let display_title = ellipsize_title(&tv.title, &suffix, max_width);
+                truncated_titles |= display_title != format!("{} {}", tv.title, suffix);
+
                 search_results.push(format!(
-                    "{}\t{}\t{}\t{} [SZNS {}] [EPS {}]",
-                    tv.image, tv.id, tv.media_type, tv.title, tv.seasons.total_seasons, tv.episodes
+                    "{}\t{}\t{}\t{}\t{}",
+                    tv.image, tv.id, tv.media_type, display_title, tv.title
                 ));
             }
         }
     }
 
-    let mut media_choice = launcher(
-        &image_preview_files,
-        settings.rofi,
-        &mut RofiArgs {
-            process_stdin: Some(search_results.join("\n")),
-            mesg: Some("Choose a movie or TV show".to_string()),
-            dmenu: true,
-            case_sensitive: true,
-            entry_prompt: Some("".to_string()),
-            display_columns: Some(4),
-            ..Default::default()
-        },
-        &mut FzfArgs {
+    let mut settings = settings;
+
+    let mut media_choice = loop {
+        // The id or the random pick leaves a single result, there's nothing to choose
+        if settings.media.filters.has_id() || settings.media.random.is_some() {
+            break search_results.last().cloned().unwrap_or_default();
+        }
+
+        let mut fzf_args = FzfArgs {
             process_stdin: Some(search_results.join("\n")),
             reverse: true,
-            with_nth: Some("4,5,6,7".to_string()),
+            with_nth: Some("4".to_string()),
             delimiter: Some("\t".to_string()),
-            header: Some("Choose a movie or TV show".to_string()),
+            header: Some(format!(
+                "Choose a movie or TV show\n{} | {}: trailer",
+                menu_keys_header(&settings),
+                TRAILER_KEY
+            )),
+            preview: if config.preview_details {
+                Some(details_preview())
+            } else {
+                truncated_titles.then(|| "echo {5}".to_string())
+            },
+            preview_window: if config.preview_details {
+                (!settings.media.image_preview).then(|| "right:50%:wrap".to_string())
+            } else {
+                (truncated_titles && !settings.media.image_preview).then(|| "up:3:wrap".to_string())
+            },
+            expect: Some(format!("{},{}", MENU_KEYS, TRAILER_KEY)),
             ..Default::default()
-        },
-    )
-    .await;
+        };
 
-    if settings.image_preview {
+        let media_choice = launcher(
+            &image_preview_files,
+            settings.media.rofi,
+            &mut RofiArgs {
+                process_stdin: Some(search_results.join("\n")),
+                mesg: Some("Choose a movie or TV show".to_string()),
+                dmenu: true,
+                case_sensitive: true,
+                entry_prompt: Some("".to_string()),
+                display_columns: Some(4),
+                ..Default::default()
+            },
+            &mut fzf_args,
+        )
+        .await;
+
+        match fzf_args.pressed {
+            Some(key) if key == TRAILER_KEY => {
+                let media_id = media_choice.split('\t').nth(1).unwrap_or_default();
+
+                // The last selection has no details page of its own
+                if media_id != "last" {
+                    if let Err(e) = play_trailer(&settings, &config, media_id).await {
+                        error!("Failed to play the trailer: {}", e);
+                    }
+                }
+
+                continue;
+            }
+            Some(key) if !menu_key(&mut settings, &config, &key) => continue,
+            _ => break media_choice,
+        }
+    };
+
+    if settings.media.image_preview {
         for (_, _, media_id) in &image_preview_files {
             remove_desktop_and_tmp(media_id.to_string())
                 .expect("Failed to remove old .desktop files & tmp images");
         }
     }
 
-    if settings.rofi {
+    if settings.media.rofi {
         for result in search_results {
             if result.contains(&media_choice) {
                 media_choice = result;
@@ -341,14 +1068,128 @@ pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()>
     let media_info = media_choice.split("\t").collect::<Vec<&str>>();
     let media_image = media_info[0];
     let media_id = media_info[1];
-    let media_type = media_info[2];
-    let media_title = media_info[3].split('[').next().unwrap_or("").trim();
+    let media_title = media_info[4];
+
+    if let Some(last) = last.filter(|_| media_id == "last") {
+        return repeat_last(settings, config, last).await;
+    }
+
+    let parsed_id: MediaId = media_id.parse()?;
 
-    if media_type == "tv" {
-        let show_info = FlixHQ.info(&media_id).await?;
+    if parsed_id.is_tv() {
+        let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+        let show_info = flixhq.info(&media_id).await?;
 
         if let FlixHQInfo::Tv(tv) = show_info {
-            let mut seasons: Vec<String> = vec![];
+            if let Some(episode) = episode {
+                let episode_info = tv
+                    .seasons
+                    .episodes(&flixhq, episode.season)
+                    .await?
+                    .get(episode.episode - 1)
+                    .ok_or_else(|| anyhow!("{} has no episode {}", media_title, episode))?;
+
+                debug!("Going straight to {} of {}", episode, media_title);
+
+                handle_servers(
+                    config,
+                    settings,
+                    None,
+                    (
+                        Some(episode_info.title.clone()),
+                        &episode_info.id,
+                        media_id,
+                        media_title,
+                        media_image,
+                    ),
+                    Some((episode.season, episode.episode - 1, tv.seasons.clone())),
+                )
+                .await?;
+
+                return Ok(());
+            }
+
+            if settings.media.shuffle {
+                return play_random_episode(
+                    config,
+                    settings,
+                    &tv.seasons,
+                    (media_id, media_title, media_image),
+                )
+                .await;
+            }
+
+            let mut resume_from = None;
+
+            if let Some((season_number, episode_title)) = history_progress.get(media_id) {
+                if let Ok(episodes) = tv.seasons.episodes(&flixhq, *season_number).await {
+                    resume_from = episodes
+                        .iter()
+                        .position(|episode| &episode.title == episode_title)
+                        .map(|episode_number| (*season_number, episode_number));
+                }
+            }
+
+            if let Some((season_number, episode_number)) = resume_from {
+                let continue_choice = format!(
+                    "Continue from S{:02}E{:02}",
+                    season_number,
+                    episode_number + 1
+                );
+
+                let resume = if settings.play.smart_resume {
+                    info!("{}", continue_choice);
+                    true
+                } else {
+                    let choices = [continue_choice.clone(), "Choose an episode".to_string()];
+
+                    let resume_choice = launcher(
+                        &vec![],
+                        settings.media.rofi,
+                        &mut RofiArgs {
+                            process_stdin: Some(choices.join("\n")),
+                            mesg: Some("Welcome back!".to_string()),
+                            dmenu: true,
+                            case_sensitive: true,
+                            entry_prompt: Some("".to_string()),
+                            ..Default::default()
+                        },
+                        &mut FzfArgs {
+                            process_stdin: Some(choices.join("\n")),
+                            reverse: true,
+                            header: Some("Welcome back!".to_string()),
+                            ..Default::default()
+                        },
+                    )
+                    .await;
+
+                    resume_choice == continue_choice
+                };
+
+                if resume {
+                    let episode_info =
+                        &tv.seasons.episodes(&flixhq, season_number).await?[episode_number];
+
+                    handle_servers(
+                        config,
+                        settings,
+                        None,
+                        (
+                            Some(episode_info.title.clone()),
+                            &episode_info.id,
+                            media_id,
+                            media_title,
+                            media_image,
+                        ),
+                        Some((season_number, episode_number, tv.seasons.clone())),
+                    )
+                    .await?;
+
+                    return Ok(());
+                }
+            }
+
+            let mut seasons: Vec<String> = vec![RANDOM_EPISODE.to_string()];
 
             for season in 0..tv.seasons.total_seasons {
                 seasons.push(format!("Season {}", season + 1));
@@ -356,7 +1197,7 @@ pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()>
 
             let season_choice = launcher(
                 &vec![],
-                settings.rofi,
+                settings.media.rofi,
                 &mut RofiArgs {
                     process_stdin: Some(seasons.join("\n")),
                     mesg: Some("Choose a season".to_string()),
@@ -375,64 +1216,137 @@ pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()>
             )
             .await;
 
+            if season_choice == RANDOM_EPISODE {
+                return play_random_episode(
+                    config,
+                    settings,
+                    &tv.seasons,
+                    (media_id, media_title, media_image),
+                )
+                .await;
+            }
+
             let season_number = season_choice.replace("Season ", "").parse::<usize>()?;
 
+            let episode_choices = tv.seasons.episodes(&flixhq, season_number).await?;
+
             let mut episodes: Vec<String> = vec![];
 
-            for episode in &tv.seasons.episodes[season_number - 1] {
+            for episode in episode_choices {
                 episodes.push(episode.title.to_string());
             }
 
-            let episode_choice = launcher(
-                &vec![],
-                settings.rofi,
-                &mut RofiArgs {
-                    process_stdin: Some(episodes.join("\n")),
-                    mesg: Some("Select an episode:".to_string()),
-                    dmenu: true,
-                    case_sensitive: true,
-                    entry_prompt: Some("".to_string()),
-                    ..Default::default()
-                },
-                &mut FzfArgs {
+            let tmdb_episodes =
+                season_details(media_id, season_number, config.tmdb_api_key.as_deref()).await;
+
+            // The overview goes after a tab, for the preview
+            let episodes: Vec<String> = mark_watched(media_id, season_number, &episodes)
+                .into_iter()
+                .zip(&episode_choices)
+                .enumerate()
+                .map(|(index, (label, episode))| {
+                    let number = episode_number(&episode.title).unwrap_or(index + 1);
+
+                    match tmdb_episodes.get(&number) {
+                        Some(tmdb) => format!(
+                            "{}{}\t{}",
+                            label,
+                            episode_facts(tmdb),
+                            tmdb.overview.replace(['\n', '\t'], " ")
+                        ),
+                        None => label,
+                    }
+                })
+                .collect();
+
+            let episode_choice = loop {
+                let mut fzf_args = FzfArgs {
                     process_stdin: Some(episodes.join("\n")),
                     reverse: true,
+                    with_nth: Some("1".to_string()),
                     delimiter: Some("\t".to_string()),
-                    header: Some("Select an episode:".to_string()),
+                    header: Some(format!(
+                        "Select an episode (Tab marks several):\n{}",
+                        menu_keys_header(&settings)
+                    )),
+                    preview: (!tmdb_episodes.is_empty()).then(|| "echo {2}".to_string()),
+                    preview_window: (!tmdb_episodes.is_empty()).then(|| "up:4:wrap".to_string()),
+                    multi: true,
+                    expect: Some(MENU_KEYS.to_string()),
                     ..Default::default()
-                },
-            )
-            .await;
+                };
 
-            let episode_choices = &tv.seasons.episodes[season_number - 1];
+                let episode_choice = launcher(
+                    &vec![],
+                    settings.media.rofi,
+                    &mut RofiArgs {
+                        process_stdin: Some(episodes.join("\n")),
+                        mesg: Some("Select an episode:".to_string()),
+                        dmenu: true,
+                        case_sensitive: true,
+                        entry_prompt: Some("".to_string()),
+                        display_columns: Some(1),
+                        ..Default::default()
+                    },
+                    &mut fzf_args,
+                )
+                .await;
 
-            let episode_number = episode_choices
-                .iter()
-                .position(|episode| episode.title == episode_choice)
-                .unwrap_or_else(|| {
-                    error!("Invalid episode choice: '{}'", episode_choice);
-                    std::process::exit(1);
-                });
+                match fzf_args.pressed {
+                    Some(key) if !menu_key(&mut settings, &config, &key) => continue,
+                    _ => break episode_choice,
+                }
+            };
 
-            let episode_info = &tv.seasons.episodes[season_number - 1][episode_number];
+            let episode_numbers: Vec<usize> = episode_choice
+                .lines()
+                .map(|choice| {
+                    episodes
+                        .iter()
+                        .position(|episode| episode == choice)
+                        .unwrap_or_else(|| {
+                            error!("Invalid episode choice: '{}'", choice);
+                            std::process::exit(1);
+                        })
+                })
+                .collect();
+
+            if episode_numbers.len() > 1 {
+                return queue_episodes(
+                    settings,
+                    config,
+                    (media_id, media_title),
+                    season_number,
+                    &episode_numbers,
+                )
+                .await;
+            }
+
+            let episode_number = episode_numbers[0];
+
+            let episode_info = &episode_choices[episode_number];
 
             handle_servers(
                 config,
                 settings,
                 None,
                 (Some(episode_info.title.clone()), &episode_info.id, media_id, media_title, media_image),
-                Some((season_number, episode_number, tv.seasons.episodes.clone())),
+                Some((season_number, episode_number, tv.seasons.clone())),
             )
             .await?;
         }
     } else {
-        let episode_id = &media_id.rsplit('-').collect::<Vec<&str>>()[0];
+        if let Some(episode) = episode {
+            warn!("{} is a movie, ignoring {}", media_title, episode);
+        }
+
+        let episode_id = EpisodeId::from(&parsed_id);
 
         handle_servers(
             config,
             settings,
             None,
-            (None, episode_id, media_id, media_title, media_image),
+            (None, episode_id.as_str(), media_id, media_title, media_image),
             None,
         )
         .await?;
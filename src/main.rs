@@ -1,66 +1,105 @@
 use anyhow::anyhow;
-use clap::{Parser, ValueEnum};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use crossterm::{
+    event::{self, Event},
+    terminal,
+};
 use futures::future::{BoxFuture, FutureExt};
-use futures::StreamExt;
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn, LevelFilter};
-use regex::Regex;
 use reqwest::Client;
 use self_update::cargo_crate_version;
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Debug, Display, Formatter},
+    io::{IsTerminal, Write},
     num::ParseIntError,
     process::Command,
     str::FromStr,
     sync::Arc,
+    time::Duration,
+};
+use utils::history::{
+    format_position, manage_history, playback_progress, reset_watchlater_dir, save_history,
+    prune_history, save_progress, saved_position, set_history_file,
 };
-use utils::history::{save_history, save_progress};
+use utils::history_sync::sync_history;
+use utils::i18n::{set_ui_language, Message};
 use utils::image_preview::remove_desktop_and_tmp;
-use utils::presence::discord_presence;
+use utils::presence::{discord_presence, download_presence, DownloadPresence};
 use utils::SpawnError;
 use serde_json::json;
 
 mod cli;
-use cli::run;
-mod flixhq;
-use flixhq::flixhq::{FlixHQ, FlixHQEpisode, FlixHQSourceType, FlixHQSubtitles};
+use cli::{batch, run, search};
+use lobster_core::{
+    flixhq, playlist,
+    providers::vidcloud::Source,
+    redact::{self, redact},
+};
+use flixhq::{
+    flixhq::{FlixHQ, FlixHQInfo, FlixHQSeason, FlixHQSourceType, FlixHQSources, FlixHQSubtitles},
+    ids::MediaId,
+};
 mod providers;
+mod tui;
+use providers::opensubtitles::OpenSubtitles;
+#[cfg(feature = "torrents")]
+use providers::torrents::TorrentIndexer;
 mod utils;
 use utils::{
-    config::Config,
+    anime_sync,
+    batch::load_batch,
+    config::{manage_config, set_profile, Config, SubtitlesMode},
+    config_check::report_config_problems,
+    details::{load_details, print_details, tmdb_poster},
+    downloads::{download_status, downloads_dir, format_size, register_download, DownloadJob},
     ffmpeg::{Ffmpeg, FfmpegArgs, FfmpegSpawn},
     fzf::{Fzf, FzfArgs, FzfSpawn},
-    image_preview::{generate_desktop, image_preview},
+    image_preview::{fzf_preview, fzf_preview_shell, generate_desktop, image_preview},
+    last_selection::{save_last_selection, LastSelection},
+    limits::{check_allowance, record_playback},
+    menu::{menu_backend, menu_name, set_menu, set_menu_command, supports_images},
+    network::LimitedClient,
+    nfo::write_nfo,
+    organize::{download_target, pack, update_checksums},
+    picker::pick,
     players::{
         celluloid::{Celluloid, CelluloidArgs, CelluloidPlay},
+        custom::{Custom, CustomArgs, CustomPlay},
+        dlna::{self, Renderer},
+        dry_run,
         iina::{Iina, IinaArgs, IinaPlay},
-        mpv::{Mpv, MpvArgs, MpvPlay},
-        vlc::{Vlc, VlcArgs, VlcPlay},
+        installed::find_player,
+        mpv::{track_position, Mpv, MpvArgs, MpvPlay},
+        set_dry_run,
+        vlc::{playback_status, Vlc, VlcArgs, VlcPlay},
     },
-    rofi::{Rofi, RofiArgs, RofiSpawn},
+    preferences::{save_subtitle_preference, subtitle_preference, SubtitlePreference},
+    report::generate_report,
+    rofi::RofiArgs,
+    search_filters::{parse_genre, SearchFilters},
+    self_test::self_test,
+    shuffle::random_episode,
+    sleep_timer,
+    stats::watch_stats,
+    stream_cache::{is_fresh, remember_stream},
+    subtitles::{convert_subtitles, filter_subtitles, LanguageTag, LanguageTagParser, Subtitle},
+    title::{parse_episode, EpisodeNumber},
+    whisper,
 };
 
-pub static BASE_URL: &'static str = "https://flixhq.to";
+pub use lobster_core::{MediaType, Provider, BASE_URL};
 
-lazy_static! {
-    static ref CLIENT: Client = Client::new();
-}
+/// Seconds to wait before `--autoplay` starts the next episode
+const AUTOPLAY_COUNTDOWN: u64 = 10;
 
-#[derive(ValueEnum, Debug, Clone, Serialize, Deserialize)]
-#[clap(rename_all = "kebab-case")]
-pub enum MediaType {
-    Tv,
-    Movie,
-}
+/// A player exiting with an error sooner than this is taken as a dead stream link
+const FAILED_PLAYBACK_SECS: u64 = 10;
 
-impl Display for MediaType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            MediaType::Tv => write!(f, "tv"),
-            MediaType::Movie => write!(f, "movie"),
-        }
-    }
+lazy_static! {
+    static ref CLIENT: Client = Client::new();
+    static ref SCRAPER_CLIENT: LimitedClient = LimitedClient::new(CLIENT.clone());
 }
 
 #[derive(Debug)]
@@ -71,22 +110,8 @@ pub enum Player {
     Celluloid,
     MpvAndroid,
     SyncPlay,
-}
-
-#[derive(ValueEnum, Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
-#[clap(rename_all = "PascalCase")]
-pub enum Provider {
-    Vidcloud,
-    Upcloud,
-}
-
-impl Display for Provider {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            Provider::Vidcloud => write!(f, "Vidcloud"),
-            Provider::Upcloud => write!(f, "Upcloud"),
-        }
-    }
+    Custom,
+    Dlna,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
@@ -103,6 +128,8 @@ pub enum Quality {
 pub enum StreamError {
     #[error("Failed to parse quality from string: {0}")]
     QualityParseError(#[from] ParseIntError),
+    #[error("The player exited right away, the stream is probably dead")]
+    PlaybackFailed,
 }
 
 impl FromStr for Quality {
@@ -163,33 +190,317 @@ impl Display for Languages {
     }
 }
 
+impl Languages {
+    /// ISO 639-1 code of the language
+    pub fn iso_code(&self) -> &'static str {
+        match self {
+            Languages::Arabic => "ar",
+            Languages::Turkish => "tr",
+            Languages::Danish => "da",
+            Languages::Dutch => "nl",
+            Languages::English => "en",
+            Languages::Finnish => "fi",
+            Languages::German => "de",
+            Languages::Italian => "it",
+            Languages::Russian => "ru",
+            Languages::Spanish => "es",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Commands {
+    /// Search for a movie or show and play it, the same as passing just a query
+    Play {
+        /// The search query or title to look for
+        query: Option<String>,
+
+        #[clap(flatten)]
+        media: MediaOptions,
+
+        #[clap(flatten)]
+        play: PlayOptions,
+    },
+    /// Print the search results without playing anything
+    Search {
+        /// The search query or title to look for
+        query: Option<String>,
+
+        /// List the most recent movies or TV shows instead
+        #[clap(long, value_enum, conflicts_with_all = ["query", "trending"])]
+        recent: Option<MediaType>,
+
+        /// List the most popular movies or TV shows instead
+        #[clap(long, value_enum, conflicts_with = "query")]
+        trending: Option<MediaType>,
+
+        /// List the movies and TV shows of a genre instead, chosen from a menu when not given
+        #[clap(long, value_name = "GENRE", value_parser = parse_genre, conflicts_with_all = ["query", "recent", "trending"])]
+        genre: Option<Option<String>>,
+
+        /// Print the results as JSON
+        #[clap(short, long)]
+        json: bool,
+
+        #[clap(flatten)]
+        filters: SearchFilters,
+    },
+    /// Search for a movie or episode and download it
+    Download {
+        /// The search query or title to look for
+        query: Option<String>,
+
+        /// Directory to download to (defaults to `download` from the config)
+        #[clap(short, long, value_name = "DIR")]
+        dir: Option<String>,
+
+        /// Run the download in the background so it keeps going after the terminal is closed
+        #[clap(long)]
+        detach: bool,
+
+        /// Start playing right away while the download runs in the background
+        #[clap(long)]
+        and_play: bool,
+
+        /// Transcribe the audio into an SRT with whisper.cpp when there are no subtitles in your language
+        #[clap(long, conflicts_with_all = ["detach", "and_play"])]
+        whisper: bool,
+
+        /// Quality to download with --and-play, if it should differ from --quality
+        #[clap(long, value_enum, requires = "and_play")]
+        download_quality: Option<Quality>,
+
+        /// Print the progress of downloads started with --detach
+        #[clap(long, exclusive = true)]
+        status: bool,
+
+        /// Write a sha256sums.txt for a download folder (e.g. "Show/Season 02") and pack it into a single zip
+        #[clap(long, value_name = "DIR", exclusive = true)]
+        pack: Option<std::path::PathBuf>,
+
+        #[clap(flatten)]
+        media: MediaOptions,
+    },
+    /// Manage the watch history
+    History {
+        #[clap(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Manage the config file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Update lobster-rs to the latest release
+    Update,
+    /// Print a completion script for a shell
+    Completions {
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Open the config file in `lobster_editor` from the config, or $EDITOR
+    Edit,
+    /// Print where the config file is
+    Path,
+    /// Print the config with the defaults filled in
+    Show,
+    /// Print the value of a setting, e.g. `provider` or `history_sync.remote`
+    Get { key: String },
+    /// Change a setting in the config file, keeping its comments, e.g. `player vlc`
+    Set { key: String, value: String },
+    /// Look for unknown keys, invalid values and missing paths in the config file
+    Check,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HistoryCommand {
+    /// List the movies and shows in the history
+    #[clap(visible_alias = "search")]
+    List {
+        /// Only list the entries whose title contains this
+        query: Option<String>,
+    },
+    /// Remove the entries with this title
+    Rm { title: String },
+    /// Remove every entry
+    Clear {
+        /// Only remove the shows whose last episode was watched to the end
+        #[clap(long)]
+        watched: bool,
+    },
+    /// Upgrade a history file written by an older release
+    Migrate,
+    /// Write the history to a file, or to stdout without one
+    Export {
+        #[clap(long, value_enum, default_value_t)]
+        format: HistoryFormat,
+        file: Option<std::path::PathBuf>,
+    },
+    /// Add the entries from an exported file, replacing the ones for the same titles
+    Import {
+        /// Taken from the file extension when left out
+        #[clap(long, value_enum)]
+        format: Option<HistoryFormat>,
+        file: std::path::PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum HistoryFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl Display for HistoryFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryFormat::Json => write!(f, "json"),
+            HistoryFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// `lobster-rs <query>` is a shortcut for `lobster-rs play <query>`, so the options of `play`
+/// are accepted without a subcommand too.
 #[derive(Parser, Debug, Clone, Default)]
 #[clap(author, version, about = "A media streaming CLI tool", long_about = None)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Commands>,
+
     /// The search query or title to look for
     #[clap(value_parser)]
     pub query: Option<String>,
 
-    /// Deletes the history file
+    #[clap(flatten)]
+    pub media: MediaOptions,
+
+    #[clap(flatten)]
+    pub play: PlayOptions,
+
+    /// Enable debug mode (prints debug info to stdout and saves it to $TEMPDIR/lobster.log)
+    #[clap(long, global = true)]
+    pub debug: bool,
+
+    /// Like --debug, but without masking tokens and query strings in the logged URLs
+    #[clap(long, global = true)]
+    pub debug_unsafe: bool,
+
+    /// Log more, -v for the debug logs and -vv for the trace logs of the libraries too
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only log warnings and errors
+    #[clap(long, global = true, conflicts_with_all = ["verbose", "debug", "debug_unsafe"])]
+    pub quiet: bool,
+
+    /// Don't color the output, which is also the case when NO_COLOR is set
+    #[clap(long, global = true)]
+    pub no_color: bool,
+
+    /// Use the settings of `[profile.NAME]` in the config over the others
+    #[clap(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Print a bug report with version info, sanitized config and the last debug log
     #[clap(long)]
-    pub clear_history: bool,
+    pub report: bool,
 
-    /// Continue watching from current history
-    #[clap(short, long)]
-    pub r#continue: bool,
+    /// Log in to the `anime_tracker` from the config to sync the progress of anime
+    #[clap(long)]
+    pub anime_login: bool,
 
-    /// Downloads movie or episode that is selected (defaults to current directory)
-    #[clap(short, long)]
+    /// Print watch statistics with a heatmap of the past year
+    #[clap(long)]
+    pub stats: bool,
+
+    /// Print the JSON Schema of everything --json outputs
+    #[clap(long)]
+    pub json_schema: bool,
+
+    /// Run offline checks of the config handling and parsers, for validating a build
+    #[clap(long)]
+    pub self_test: bool,
+
+    /// Print the man page, or write one for every subcommand to DIR, for packaging
+    #[clap(long, hide = true, value_name = "DIR")]
+    pub generate_man: Option<Option<std::path::PathBuf>>,
+
+    /// Print the details of a movie or show, for the fzf preview of `preview_details`
+    #[clap(long, hide = true, value_name = "ID")]
+    pub preview_details: Option<String>,
+
+    /// Play, print or download the entries of a JSON file one after the other without asking
+    /// anything, `-` reads it from stdin
+    #[clap(long, value_name = "FILE", conflicts_with = "query")]
+    pub batch: Option<std::path::PathBuf>,
+
+    /// Same as `download`
+    #[clap(short, long, hide = true)]
     pub download: Option<Option<String>>,
 
-    /// Enables discord rich presence (beta feature, works fine on Linux)
-    #[clap(short, long)]
-    pub rpc: bool,
+    /// Same as `download --detach`
+    #[clap(long, hide = true, requires = "download")]
+    pub detach: bool,
 
-    /// Edit config file using an editor defined with lobster_editor in the config ($EDITOR by default)
-    #[clap(short, long)]
+    /// Same as `download --and-play`
+    #[clap(long, hide = true, requires = "download")]
+    pub download_and_play: bool,
+
+    /// Same as `download --whisper`
+    #[clap(long, hide = true, requires = "download", conflicts_with_all = ["detach", "download_and_play"])]
+    pub whisper: bool,
+
+    /// Same as `download --download-quality`
+    #[clap(long, hide = true, value_enum, requires = "download_and_play")]
+    pub download_quality: Option<Quality>,
+
+    /// Same as `download --status`
+    #[clap(long, hide = true)]
+    pub download_status: bool,
+
+    /// Same as `download --pack`
+    #[clap(long, hide = true, value_name = "DIR")]
+    pub pack: Option<std::path::PathBuf>,
+
+    /// Same as `history clear`
+    #[clap(long, hide = true)]
+    pub clear_history: bool,
+
+    /// Same as `history migrate`
+    #[clap(long, hide = true)]
+    pub migrate_history: bool,
+
+    /// Same as `config edit`
+    #[clap(short, long, hide = true)]
     pub edit: bool,
 
+    /// Same as `update`
+    #[clap(short, long, hide = true)]
+    pub update: bool,
+
+    /// Language from `subs_fallback` used because none of the requested ones had subtitles
+    #[clap(skip)]
+    pub subtitle_fallback: Option<LanguageTag>,
+
+    /// The results of the search, kept for `--json-only`
+    #[clap(skip)]
+    pub search_results: Vec<SearchOutput>,
+
+    /// Set while working through episodes marked together in the menu
+    #[clap(skip)]
+    pub queued: bool,
+}
+
+/// Options for finding a movie or episode and picking its stream, for `play` and `download`.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct MediaOptions {
     /// Shows image previews during media selection
     #[clap(short, long)]
     pub image_preview: bool,
@@ -198,11 +509,17 @@ pub struct Args {
     #[clap(short, long)]
     pub json: bool,
 
-    /// Specify the subtitle language
-    #[clap(short, long, value_enum)]
-    pub language: Option<Languages>,
-
-    /// Use rofi instead of fzf
+    /// Specify the subtitle language by name or ISO 639 code (comma separated for a priority list, e.g. Spanish,pt,jpn)
+    #[clap(
+        short,
+        long,
+        value_delimiter = ',',
+        value_parser = LanguageTagParser,
+        hide_possible_values = true
+    )]
+    pub language: Vec<LanguageTag>,
+
+    /// Use rofi, or the `menu` from the config, instead of fzf
     #[clap(long)]
     pub rofi: bool,
 
@@ -218,43 +535,264 @@ pub struct Args {
     #[clap(long, value_enum)]
     pub recent: Option<MediaType>,
 
-    /// Use Syncplay to watch with friends
-    #[clap(short, long)]
-    pub syncplay: bool,
-
     /// Lets you select from the most popular movies or TV shows
     #[clap(short, long, value_enum)]
     pub trending: Option<MediaType>,
 
-    /// Update the script
-    #[clap(short, long)]
-    pub update: bool,
+    /// Lets you select from the movies and TV shows of a genre, chosen from a menu when not given
+    #[clap(long, value_name = "GENRE", value_parser = parse_genre, conflicts_with_all = ["query", "recent", "trending"])]
+    pub genre: Option<Option<String>>,
 
-    /// Enable debug mode (prints debug info to stdout and saves it to $TEMPDIR/lobster.log)
-    #[clap(long)]
-    pub debug: bool,
+    /// Play a random trending movie or show, or one from a genre when given
+    #[clap(long, value_name = "GENRE", value_parser = parse_genre, conflicts_with_all = ["query", "recent", "trending", "genre", "country", "actor", "imdb", "tmdb"])]
+    pub random: Option<Option<String>>,
+
+    #[clap(flatten)]
+    pub filters: SearchFilters,
+
+    /// Go straight to this episode of the chosen show, e.g. S03E07 (also read from the end of the query)
+    #[clap(long, value_name = "SxxEyy", value_parser = parse_episode)]
+    pub episode: Option<EpisodeNumber>,
+
+    /// Play a random episode of the chosen show, and another random one when autoplaying the next
+    #[clap(long, conflicts_with = "episode")]
+    pub shuffle: bool,
 
     /// Disable subtitles
     #[clap(short, long)]
     pub no_subs: bool,
+
+    /// Always play with subtitles, even when the config says `subtitles = "never"` or `"ask"`
+    #[clap(long, conflicts_with = "no_subs")]
+    pub subs: bool,
+
+    /// Cap the video quality to `max_quality_on_metered` (detected automatically with NetworkManager)
+    #[clap(long)]
+    pub metered: bool,
+
+    /// Choose subtitles from a menu of every available track
+    #[clap(long)]
+    pub choose_subs: bool,
+
+    /// Prefer subtitle tracks for the deaf and hard of hearing (SDH) when available
+    #[clap(long, conflicts_with = "no_sdh")]
+    pub sdh: bool,
+
+    /// Never pick SDH subtitle tracks
+    #[clap(long)]
+    pub no_sdh: bool,
+
+    /// Prefer forced subtitle tracks (only foreign dialogue and signs) when available
+    #[clap(long, conflicts_with = "no_forced")]
+    pub forced: bool,
+
+    /// Never pick forced subtitle tracks
+    #[clap(long)]
+    pub no_forced: bool,
+
+    /// Save the subtitles of the selection to the download directory (or print their URLs with --json) without playing
+    #[clap(long, conflicts_with = "no_subs")]
+    pub subs_only: bool,
+}
+
+/// Options that only matter while playing.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct PlayOptions {
+    /// Extra arguments passed on to the player, e.g. `lobster-rs deadpool -- --fs --volume=50`
+    #[clap(last = true)]
+    pub player_args: Vec<String>,
+
+    /// Continue watching from current history
+    #[clap(short, long)]
+    pub r#continue: bool,
+
+    /// Play the last picked movie or episode again, with the same provider and quality
+    #[clap(long, conflicts_with_all = ["continue", "query", "random"])]
+    pub last: bool,
+
+    /// Save watch history for this run, even if `history` is off in the config
+    #[clap(long, conflicts_with = "no_history")]
+    pub history: bool,
+
+    /// Don't save watch history for this run, even if `history` is on in the config
+    #[clap(long)]
+    pub no_history: bool,
+
+    /// Leave no trace of this session: no history, Discord presence or anime tracker sync
+    #[clap(long, conflicts_with_all = ["history", "rpc"])]
+    pub incognito: bool,
+
+    /// Play the next episode automatically after finishing one, instead of showing the menu
+    #[clap(long)]
+    pub autoplay: bool,
+
+    /// Go straight to the next unwatched episode of a show in the history, skipping the menus
+    #[clap(long)]
+    pub smart_resume: bool,
+
+    /// Start from the beginning instead of asking to resume at the position saved in the history
+    #[clap(long)]
+    pub from_start: bool,
+
+    /// Stops mpv after the given time and saves the progress, e.g. `45m` or `1h30m`
+    #[clap(long, value_name = "DURATION", value_parser = sleep_timer::parse_duration)]
+    pub sleep: Option<Duration>,
+
+    /// Runs this command instead of a player, with {url}, {title}, {subtitle} and {subtitles} filled in
+    #[clap(long, value_name = "COMMAND")]
+    pub open_with: Option<String>,
+
+    /// Print the stream URL in the chosen quality instead of playing it
+    #[clap(long, conflicts_with_all = ["json", "subs_only", "open_with"])]
+    pub print_link: bool,
+
+    /// Print the URLs of the selected subtitles after the stream URL, one per line
+    #[clap(long, requires = "print_link")]
+    pub print_subs: bool,
+
+    /// Print the player command with the server, quality and subtitles filled in instead of running it
+    #[clap(long, conflicts_with_all = ["print_link", "json_only"])]
+    pub dry_run: bool,
+
+    /// Print one JSON document with the search results, selection, sources and subtitles instead of playing, and nothing else
+    #[clap(long, conflicts_with_all = ["json", "print_link", "subs_only", "open_with"])]
+    pub json_only: bool,
+
+    /// Browse in a full-screen interface with search, episode and history panes instead of fzf
+    #[clap(long, conflicts_with_all = ["rofi", "json", "json_only", "print_link", "random"])]
+    pub tui: bool,
+
+    /// Starts the player in fullscreen
+    #[clap(long)]
+    pub fullscreen: bool,
+
+    /// Loops the video until the player is closed
+    #[clap(long)]
+    pub r#loop: bool,
+
+    /// Enables discord rich presence (beta feature, works fine on Linux)
+    #[clap(short, long)]
+    pub rpc: bool,
+
+    /// Cast to a DLNA renderer like a smart TV instead, the one whose name contains NAME when given
+    #[clap(long, value_name = "NAME", conflicts_with_all = ["syncplay", "open_with"])]
+    pub dlna: Option<Option<String>>,
+
+    /// Use Syncplay to watch with friends
+    #[clap(short, long)]
+    pub syncplay: bool,
+
+    /// Syncplay server to join, as host[:port]
+    #[clap(long, value_name = "HOST")]
+    pub syncplay_server: Option<String>,
+
+    /// Syncplay room to join
+    #[clap(long, value_name = "ROOM")]
+    pub syncplay_room: Option<String>,
+
+    /// Name to show to the others in the Syncplay room
+    #[clap(long, value_name = "NAME")]
+    pub syncplay_username: Option<String>,
+}
+
+impl Args {
+    /// Whether to go on without asking anything, for `--batch` and queued episodes.
+    pub fn unattended(&self) -> bool {
+        self.batch.is_some() || self.queued
+    }
+
+    /// Folds `play` and `download` into the options the rest of the program reads, and turns
+    /// the old flags for the other subcommands into those subcommands.
+    fn resolve_command(mut self) -> Self {
+        match self.command.take() {
+            Some(Commands::Play { query, media, play }) => {
+                self.query = query;
+                self.media = media;
+                self.play = play;
+            }
+            Some(Commands::Download {
+                query,
+                dir,
+                detach,
+                and_play,
+                whisper,
+                download_quality,
+                status,
+                pack,
+                media,
+            }) => {
+                self.query = query;
+                self.media = media;
+                self.download = Some(dir);
+                self.detach = detach;
+                self.download_and_play = and_play;
+                self.whisper = whisper;
+                self.download_quality = download_quality;
+                self.download_status = status;
+                self.pack = pack;
+            }
+            command => self.command = command,
+        }
+
+        if self.command.is_none() {
+            self.command = if self.update {
+                Some(Commands::Update)
+            } else if self.edit {
+                Some(Commands::Config {
+                    command: ConfigCommand::Edit,
+                })
+            } else if self.clear_history {
+                Some(Commands::History {
+                    command: HistoryCommand::Clear { watched: false },
+                })
+            } else if self.migrate_history {
+                Some(Commands::History {
+                    command: HistoryCommand::Migrate,
+                })
+            } else {
+                None
+            };
+        }
+
+        self
+    }
 }
 
 fn fzf_launcher<'a>(args: &'a mut FzfArgs) -> anyhow::Result<String> {
     debug!("Launching fzf with arguments: {:?}", args);
 
-    let mut fzf = Fzf::new();
+    let mut output = if fzf_installed() {
+        let mut fzf = Fzf::new();
 
-    let output = fzf
-        .spawn(args)
-        .map(|output| {
-            let result = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            debug!("fzf completed with result: {}", result);
-            result
-        })
-        .unwrap_or_else(|e| {
-            error!("Failed to launch fzf: {}", e.to_string());
+        fzf.spawn(args)
+            .map(|output| {
+                let result = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                debug!("fzf completed with result: {}", result);
+                result
+            })
+            .unwrap_or_else(|e| {
+                error!("Failed to launch fzf: {}", e.to_string());
+                std::process::exit(1)
+            })
+    } else {
+        pick(args).unwrap_or_else(|e| {
+            error!("Failed to launch the built-in picker: {}", e);
             std::process::exit(1)
-        });
+        })
+    };
+
+    // With --expect the key comes first, on a line of its own that is empty for Enter,
+    // which trimming takes away
+    if let Some(expect) = args.expect.clone() {
+        let key = expect
+            .split(',')
+            .find(|key| output == *key || output.starts_with(&format!("{}\n", key)));
+
+        if let Some(key) = key {
+            output = output[key.len()..].trim().to_string();
+            args.pressed = Some(key.to_string());
+        }
+    }
 
     if output.is_empty() {
         return Err(anyhow!("No selection made. Exiting..."));
@@ -263,20 +801,20 @@ fn fzf_launcher<'a>(args: &'a mut FzfArgs) -> anyhow::Result<String> {
     Ok(output)
 }
 
-fn rofi_launcher<'a>(args: &'a mut RofiArgs) -> anyhow::Result<String> {
-    debug!("Launching rofi with arguments: {:?}", args);
+fn menu_launcher<'a>(args: &'a mut RofiArgs) -> anyhow::Result<String> {
+    let menu = menu_name();
 
-    let mut rofi = Rofi::new();
+    debug!("Launching {} with arguments: {:?}", menu, args);
 
-    let output = rofi
+    let output = menu_backend()
         .spawn(args)
         .map(|output| {
             let result = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            debug!("rofi completed with result: {}", result);
+            debug!("{} completed with result: {}", menu, result);
             result
         })
         .unwrap_or_else(|e| {
-            error!("Failed to launch rofi: {}", e.to_string());
+            error!("Failed to launch {}: {}", menu, e.to_string());
             std::process::exit(1)
         });
 
@@ -287,14 +825,18 @@ fn rofi_launcher<'a>(args: &'a mut RofiArgs) -> anyhow::Result<String> {
     Ok(output)
 }
 
-async fn launcher(
+/// Like [`launcher`], but returns `None` when nothing was picked instead of exiting, for
+/// menus where backing out is a way to quit.
+async fn try_launcher(
     image_preview_files: &Vec<(String, String, String)>,
     rofi: bool,
     rofi_args: &mut RofiArgs,
     fzf_args: &mut FzfArgs,
-) -> String {
+) -> Option<String> {
     if image_preview_files.is_empty() {
         debug!("No image preview files provided.");
+    } else if rofi && !supports_images() {
+        warn!("Image previews only work with rofi, not {}.", menu_name());
     } else {
         debug!(
             "Generating image previews for {} files.",
@@ -323,13 +865,10 @@ async fn launcher(
                 Ok(_) => {
                     debug!("Setting up fzf preview script.");
 
-                    fzf_args.preview = Some(
-                        r#"
-    set -l selected (echo {} | cut -f2 | sed 's/\//-/g')
-    chafa -f sixels -s 80x40 "/tmp/images/$selected.jpg"
-    "#
-                        .to_string(),
-                    );
+                    let title_preview = fzf_args.preview.take().unwrap_or_default();
+
+                    fzf_args.preview = Some(fzf_preview(&title_preview));
+                    fzf_args.with_shell = fzf_preview_shell();
                 }
                 Err(_) => {
                     warn!("Chafa isn't installed. Cannot preview images with fzf.");
@@ -339,9 +878,9 @@ async fn launcher(
     }
 
     if rofi {
-        debug!("Using rofi launcher.");
-        match rofi_launcher(rofi_args) {
-            Ok(output) => output,
+        debug!("Using {} launcher.", menu_name());
+        match menu_launcher(rofi_args) {
+            Ok(output) => Some(output),
             Err(_) => {
                 if !image_preview_files.is_empty() {
                     for (_, _, media_id) in image_preview_files {
@@ -350,13 +889,13 @@ async fn launcher(
                     }
                 }
 
-                std::process::exit(1)
+                None
             }
         }
     } else {
         debug!("Using fzf launcher.");
         match fzf_launcher(fzf_args) {
-            Ok(output) => output,
+            Ok(output) => Some(output),
             Err(_) => {
                 if !image_preview_files.is_empty() {
                     for (_, _, media_id) in image_preview_files {
@@ -365,32 +904,226 @@ async fn launcher(
                     }
                 }
 
-                std::process::exit(1)
+                None
             }
         }
     }
 }
 
+async fn launcher(
+    image_preview_files: &Vec<(String, String, String)>,
+    rofi: bool,
+    rofi_args: &mut RofiArgs,
+    fzf_args: &mut FzfArgs,
+) -> String {
+    match try_launcher(image_preview_files, rofi, rofi_args, fzf_args).await {
+        Some(output) => output,
+        None => std::process::exit(1),
+    }
+}
+
+/// Lets the user pick a quality to download, showing the estimated file size of each one.
+async fn choose_download_quality(url: String, rofi: bool) -> anyhow::Result<String> {
+    let client = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let master = client.get(&url).send().await?.text().await?;
+    let variants = playlist::variants(&master);
+
+    let Some(first_variant) = variants.first() else {
+        return Ok(url);
+    };
+
+    let media_playlist = client
+        .get(&first_variant.url)
+        .send()
+        .await?
+        .text()
+        .await?;
+    let duration = playlist::total_duration(&media_playlist);
+
+    let choices: Vec<String> = variants
+        .iter()
+        .map(|variant| match variant.estimated_size(duration) {
+            Some(size) => format!("{}p ≈ {}", variant.resolution, format_size(size)),
+            None => format!("{}p", variant.resolution),
+        })
+        .collect();
+
+    let quality_choice = launcher(
+        &vec![],
+        rofi,
+        &mut RofiArgs {
+            process_stdin: Some(choices.join("\n")),
+            mesg: Some("Choose a quality to download".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(choices.join("\n")),
+            reverse: true,
+            header: Some("Choose a quality to download".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let variant = choices
+        .iter()
+        .position(|choice| *choice == quality_choice)
+        .map_or(first_variant, |i| &variants[i]);
+
+    debug!("Downloading {}p from {}", variant.resolution, redact(&variant.url));
+
+    Ok(variant.url.clone())
+}
+
 async fn download(
-    download_dir: String,
-    media_title: String,
+    (download_dir, media_title): (String, String),
     url: String,
+    settings: &Args,
+    config: &Config,
+    presence: Option<DownloadPresence>,
     subtitles: Option<Vec<String>>,
-    subtitle_language: Option<Languages>,
+    subtitle_language: Option<LanguageTag>,
 ) -> anyhow::Result<()> {
     info!("{}", format!(r#"Starting download for "{}""#, media_title));
 
-    let ffmpeg = Ffmpeg::new();
+    std::fs::create_dir_all(&download_dir)?;
 
-    ffmpeg.embed_video(FfmpegArgs {
+    let url = if settings.media.quality.is_some() || settings.unattended() {
+        url_quality(url, settings.media.quality).await?
+    } else {
+        choose_download_quality(url, settings.media.rofi).await?
+    };
+
+    let ffmpeg = Ffmpeg::new();
+    let ffmpeg_output = format!("{}/{}.mkv", download_dir, media_title);
+
+    let ffmpeg_args = FfmpegArgs {
         input_file: url,
         log_level: Some("error".to_string()),
-        stats: true,
-        output_file: format!("{}/{}.mkv", download_dir, media_title),
+        stats: !settings.detach,
+        output_file: ffmpeg_output.clone(),
         subtitle_files: subtitles.as_ref(),
-        subtitle_language: Some(subtitle_language.unwrap_or(Languages::English).to_string()),
+        subtitle_language: subtitle_language.map(|language| language.alpha3.to_string()),
         codec: Some("copy".to_string()),
-    })?;
+        progress: presence
+            .as_ref()
+            .filter(|_| !settings.detach)
+            .map(|_| "pipe:1".to_string()),
+    };
+
+    if settings.detach {
+        let mut job = DownloadJob::new(ffmpeg_args.output_file.clone());
+        std::fs::create_dir_all(downloads_dir())?;
+
+        job.pid =
+            ffmpeg.embed_video_detached(ffmpeg_args, &job.log_file(), &job.progress_file())?;
+        register_download(&job)?;
+
+        info!("Downloading in the background, check on it with `lobster-rs --download-status`");
+    } else if let Some(presence) = &presence {
+        let duration = match CLIENT.get(&ffmpeg_args.input_file).send().await {
+            Ok(response) => playlist::total_duration(&response.text().await.unwrap_or_default()),
+            Err(_) => 0.0,
+        };
+
+        let mut child = ffmpeg.spawn_video(ffmpeg_args)?;
+        download_presence(presence, &mut child, duration).await?;
+
+        if child.wait()?.code() != Some(0) {
+            return Err(anyhow!("Failed to download {:?}", media_title));
+        }
+
+        info!("Download completed.");
+    } else {
+        ffmpeg.embed_video(ffmpeg_args)?;
+        info!("Download completed.");
+    }
+
+    let no_subtitles = subtitles
+        .as_ref()
+        .is_none_or(|subtitles| subtitles.is_empty());
+
+    if settings.whisper && !settings.detach && !settings.media.no_subs && no_subtitles {
+        let language = subtitle_language
+            .or(settings.media.language.first().copied())
+            .unwrap_or(LanguageTag::from(Languages::English));
+
+        match whisper::transcribe(
+            std::path::Path::new(&ffmpeg_output),
+            language,
+            &config.whisper_command,
+            config.whisper_model.as_deref(),
+        ) {
+            Ok(subtitle_path) => info!("Saved the transcription to {}", subtitle_path.display()),
+            Err(e) => warn!("Failed to transcribe {:?}: {}", media_title, e),
+        }
+    }
+
+    if let Some(subtitles) = &subtitles {
+        save_subtitles(
+            &download_dir,
+            &media_title,
+            subtitles,
+            subtitle_language.unwrap_or(LanguageTag::from(Languages::English)),
+        )
+        .await?;
+    }
+
+    if config.organize_downloads && !settings.detach {
+        update_checksums(
+            std::path::Path::new(&download_dir),
+            &[format!("{}.mkv", media_title)],
+        )?;
+    }
+
+    Ok(())
+}
+
+async fn save_subtitles(
+    download_dir: &str,
+    media_title: &str,
+    subtitles: &[String],
+    subtitle_language: LanguageTag,
+) -> anyhow::Result<()> {
+    for (i, subtitle) in subtitles.iter().enumerate() {
+        let extension = subtitle
+            .rsplit('.')
+            .next()
+            .filter(|extension| ["vtt", "srt"].contains(extension))
+            .unwrap_or("vtt");
+
+        let language_suffix = if subtitles.len() > 1 {
+            format!("{}.{}", subtitle_language.code, i + 1)
+        } else {
+            subtitle_language.code.to_string()
+        };
+
+        let subtitle_path = format!(
+            "{}/{}.{}.{}",
+            download_dir, media_title, language_suffix, extension
+        );
+
+        debug!("Saving subtitle {} to {}", redact(subtitle), subtitle_path);
+
+        if subtitle.starts_with("http") {
+            let subtitle_bytes = CLIENT.get(subtitle).send().await?.bytes().await?;
+            std::fs::write(&subtitle_path, subtitle_bytes)?;
+        } else {
+            std::fs::copy(subtitle, &subtitle_path)?;
+        }
+    }
+
+    info!(
+        "Saved {} subtitle file(s) to {}",
+        subtitles.len(),
+        download_dir
+    );
 
     Ok(())
 }
@@ -430,54 +1163,41 @@ async fn url_quality(url: String, quality: Option<Quality>) -> anyhow::Result<St
 
     let input = client.get(url).send().await?.text().await?;
 
-    let url_re = Regex::new(r"https://[^\s]+m3u8").unwrap();
-    let res_re = Regex::new(r"RESOLUTION=(\d+)x(\d+)").unwrap();
-
-    let mut resolutions = Vec::new();
-    for cap in res_re.captures_iter(&input) {
-        resolutions.push(cap[2].to_string()); // Collect only height (e.g., "1080", "720", "360")
-    }
+    let variants = playlist::variants(&input);
 
     let url = if let Some(chosen_quality) = quality {
-        url_re
-            .captures_iter(&input)
-            .zip(res_re.captures_iter(&input))
-            .find_map(|(url_captures, res_captures)| {
-                let resolution = &res_captures[2];
-                let url = &url_captures[0];
-
-                if resolution == chosen_quality.to_string() {
-                    Some(url.to_string())
-                } else {
-                    None
-                }
-            })
+        variants
+            .iter()
+            .find(|variant| variant.resolution == chosen_quality.to_u32())
+            .map(|variant| variant.url.clone())
             .unwrap_or_else(|| {
-                info!("Quality {} not found, falling back to auto", chosen_quality);
-                input
-                    .lines()
-                    .find(|line| line.starts_with("https://"))
-                    .unwrap_or("")
-                    .to_string()
+                match variants
+                    .iter()
+                    .find(|variant| variant.resolution <= chosen_quality.to_u32())
+                {
+                    Some(variant) => {
+                        info!(
+                            "Quality {} not found, falling back to {}",
+                            chosen_quality, variant.resolution
+                        );
+                        variant.url.clone()
+                    }
+                    None => {
+                        info!("Quality {} not found, falling back to auto", chosen_quality);
+                        input
+                            .lines()
+                            .find(|line| line.starts_with("https://"))
+                            .unwrap_or("")
+                            .to_string()
+                    }
+                }
             })
     } else {
-        let mut urls_and_resolutions: Vec<(u32, String)> = url_re
-            .captures_iter(&input)
-            .zip(res_re.captures_iter(&input))
-            .filter_map(|(url_captures, res_captures)| {
-                let resolution: u32 = res_captures[2].parse().ok()?;
-                let url = url_captures[0].to_string();
-                Some((resolution, url))
-            })
-            .collect();
-
-        urls_and_resolutions.sort_by_key(|&(resolution, _)| std::cmp::Reverse(resolution));
-
-        let (_, url) = urls_and_resolutions
+        variants
             .first()
-            .expect("Failed to find best url quality!");
-
-        url.to_string()
+            .expect("Failed to find best url quality!")
+            .url
+            .clone()
     };
 
     Ok(url)
@@ -485,24 +1205,30 @@ async fn url_quality(url: String, quality: Option<Quality>) -> anyhow::Result<St
 
 async fn player_run_choice(
     media_info: (Option<String>, String, String, String, String),
-    episode_info: Option<(usize, usize, Vec<Vec<FlixHQEpisode>>)>,
+    episode_info: Option<(usize, usize, FlixHQSeason)>,
     config: Arc<Config>,
     settings: Arc<Args>,
     player: Player,
     download_dir: Option<String>,
     player_url: String,
     subtitles: Vec<String>,
-    subtitle_language: Option<Languages>,
+    subtitle_language: Option<LanguageTag>,
 ) -> anyhow::Result<()> {
-    let process_stdin = if media_info.2.starts_with("tv/") {
+    // The batch or queue goes on with its next entry instead, and the TUI is a menu of its own
+    if settings.unattended() || settings.play.tui {
+        return Ok(());
+    }
+
+    let process_stdin = if media_info.2.parse::<MediaId>()?.is_tv() {
         Some("Next Episode\nPrevious Episode\nReplay\nExit\nSearch".to_string())
     } else {
         Some("Replay\nExit\nSearch".to_string())
     };
 
-    let run_choice = launcher(
+    // Backing out of the menu quits like "Exit"
+    let run_choice = try_launcher(
         &vec![],
-        settings.rofi,
+        settings.media.rofi,
         &mut RofiArgs {
             mesg: Some("Select: ".to_string()),
             process_stdin: process_stdin.clone(),
@@ -517,7 +1243,8 @@ async fn player_run_choice(
             ..Default::default()
         },
     )
-    .await;
+    .await
+    .unwrap_or_else(|| String::from("Exit"));
 
     match run_choice.as_str() {
         "Next Episode" => {
@@ -555,6 +1282,24 @@ async fn player_run_choice(
         "Search" => {
             run(Arc::new(Args::default()), Arc::clone(&config)).await?;
         }
+        "Replay" if !is_fresh(&player_url).await => {
+            info!("The stream link has expired, fetching a new one.");
+
+            handle_servers(
+                config.clone(),
+                settings.clone(),
+                None,
+                (
+                    media_info.0,
+                    media_info.1.as_str(),
+                    media_info.2.as_str(),
+                    media_info.3.as_str(),
+                    media_info.4.as_str(),
+                ),
+                episode_info,
+            )
+            .await?;
+        }
         "Replay" => {
             handle_stream(
                 settings.clone(),
@@ -569,9 +1314,8 @@ async fn player_run_choice(
             )
             .await?;
         }
-        "Exit" => {
-            std::process::exit(0);
-        }
+        // Returned up the stack so the history is still synced on the way out
+        "Exit" => {}
         _ => {
             unreachable!("You shouldn't be here...")
         }
@@ -580,6 +1324,300 @@ async fn player_run_choice(
     Ok(())
 }
 
+/// Asks whether to keep the position of something that was stopped before
+/// `completion_threshold`, or count it as watched anyway.
+async fn ask_mark_finished(rofi: bool, progress: f32) -> bool {
+    let keep_position = format!("Keep position ({:.0}%)", progress);
+    let choices = [keep_position.as_str(), "Mark as finished"].join("\n");
+
+    let choice = launcher(
+        &vec![],
+        rofi,
+        &mut RofiArgs {
+            process_stdin: Some(choices.clone()),
+            mesg: Some("Save progress".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(choices),
+            reverse: true,
+            header: Some("Save progress".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    choice == "Mark as finished"
+}
+
+async fn ask_resume(rofi: bool, position: &str) -> bool {
+    let resume = format!("Resume from {}", position);
+    let choices = [resume.as_str(), "Start from beginning"].join("\n");
+
+    let choice = launcher(
+        &vec![],
+        rofi,
+        &mut RofiArgs {
+            process_stdin: Some(choices.clone()),
+            mesg: Some("Resume playback".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(choices),
+            reverse: true,
+            header: Some("Resume playback".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    choice == resume
+}
+
+/// Where to start playing, in seconds: the position saved in the history when resuming with
+/// `--continue`, or when picked over starting from the beginning.
+async fn start_position(
+    settings: &Args,
+    media_info: &(Option<String>, String, String, String, String),
+    episode_info: &Option<(usize, usize, FlixHQSeason)>,
+) -> Option<f32> {
+    if !settings.play.history || settings.play.from_start {
+        return None;
+    }
+
+    let seconds = saved_position(
+        &media_info.2,
+        episode_info.as_ref().map(|_| media_info.1.as_str()),
+    )?;
+
+    if settings.play.r#continue
+        || settings.unattended()
+        || ask_resume(settings.media.rofi, &format_position(seconds)).await
+    {
+        debug!("Resuming at {} seconds", seconds);
+        Some(seconds)
+    } else {
+        None
+    }
+}
+
+/// Saves the history after a player other than mpv exits. `progress` is the position and
+/// the percentage watched when the player could tell; without it the video is marked as
+/// watched, so resuming picks up the next episode.
+async fn save_player_history(
+    settings: &Args,
+    config: &Config,
+    media_info: &(Option<String>, String, String, String, String),
+    episode_info: &Option<(usize, usize, FlixHQSeason)>,
+    progress: Option<(String, f32)>,
+) -> anyhow::Result<()> {
+    let (position, progress, finished) = match progress {
+        Some((position, progress)) => {
+            let finished = progress >= config.completion_threshold
+                || (progress > 0.0
+                    && !settings.unattended()
+                    && ask_mark_finished(settings.media.rofi, progress).await);
+
+            (position, Some(progress), finished)
+        }
+        None => {
+            debug!("The player didn't report a position, marking as watched");
+            (format_position(0.0), None, true)
+        }
+    };
+
+    save_history(
+        media_info.clone(),
+        episode_info.clone(),
+        position,
+        progress,
+        finished,
+    )
+    .await?;
+
+    if finished {
+        anime_sync::sync_progress(config, &media_info.3, anime_episode(episode_info)).await;
+    }
+
+    Ok(())
+}
+
+/// The season and 1-based episode number an anime tracker counts.
+fn anime_episode(episode_info: &Option<(usize, usize, FlixHQSeason)>) -> Option<(usize, usize)> {
+    episode_info
+        .as_ref()
+        .map(|(season, episode, _)| (*season, episode + 1))
+}
+
+/// Whether there's an episode after `episode_info` for `--autoplay` to start, in this
+/// season or the next. With `--shuffle` any episode will do.
+async fn has_next_episode(
+    settings: &Args,
+    episode_info: &Option<(usize, usize, FlixHQSeason)>,
+) -> anyhow::Result<bool> {
+    let Some((season, episode, seasons)) = episode_info else {
+        return Ok(false);
+    };
+
+    if settings.media.shuffle || *season < seasons.total_seasons {
+        return Ok(true);
+    }
+
+    let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+    Ok(episode + 1 < seasons.episodes(&flixhq, *season).await?.len())
+}
+
+/// Counts down `seconds` before `--autoplay` starts the next episode. Returns false when a
+/// key was pressed to cancel. Without a terminal to read keys from it doesn't wait.
+fn autoplay_countdown(seconds: u64) -> anyhow::Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        info!("Playing the next episode.");
+        return Ok(true);
+    }
+
+    terminal::enable_raw_mode()?;
+
+    let countdown = || -> anyhow::Result<bool> {
+        for remaining in (1..=seconds).rev() {
+            print!(
+                "\rPlaying the next episode in {}s, press any key to cancel ",
+                remaining
+            );
+            std::io::stdout().flush()?;
+
+            if event::poll(Duration::from_secs(1))? {
+                if let Event::Key(_) = event::read()? {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    };
+
+    let play_next = countdown();
+
+    terminal::disable_raw_mode()?;
+    println!();
+
+    play_next
+}
+
+/// A yes/no question in the menu, answered with `default` when it's backed out of or when
+/// `skip_prompts` is set. The TUI hands the terminal over before playing, so it's asked in
+/// fzf there too.
+async fn confirm(rofi: bool, question: Message, default: bool, config: &Config) -> bool {
+    if config.skip_prompts {
+        return default;
+    }
+
+    let yes = Message::Yes.text();
+    let choices = if default {
+        [yes, Message::No.text()]
+    } else {
+        [Message::No.text(), yes]
+    }
+    .join("\n");
+
+    let choice = try_launcher(
+        &vec![],
+        rofi,
+        &mut RofiArgs {
+            process_stdin: Some(choices.clone()),
+            mesg: Some(question.to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(choices),
+            reverse: true,
+            header: Some(question.to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    match choice {
+        Some(choice) => choice == yes,
+        None => default,
+    }
+}
+
+/// The DLNA renderer to cast to: the one named with `--dlna` or `dlna_renderer`, the only
+/// one on the network, or the one picked from a menu.
+async fn choose_renderer(settings: &Args, config: &Config) -> anyhow::Result<Renderer> {
+    info!("Looking for DLNA renderers...");
+
+    let renderers = dlna::discover().await?;
+
+    let wanted = settings
+        .play
+        .dlna
+        .clone()
+        .flatten()
+        .or_else(|| config.dlna_renderer.clone());
+
+    if let Some(wanted) = wanted {
+        match renderers.iter().find(|renderer| {
+            renderer
+                .name
+                .to_lowercase()
+                .contains(&wanted.to_lowercase())
+        }) {
+            Some(renderer) => return Ok(renderer.clone()),
+            None => warn!("There's no DLNA renderer called {:?}", wanted),
+        }
+    }
+
+    if renderers.len() < 2 || settings.unattended() {
+        return renderers
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No DLNA renderers found on the network"));
+    }
+
+    let choices: Vec<String> = renderers
+        .iter()
+        .map(|renderer| renderer.name.clone())
+        .collect();
+
+    let choice = launcher(
+        &vec![],
+        settings.media.rofi,
+        &mut RofiArgs {
+            process_stdin: Some(choices.join("\n")),
+            mesg: Some("Cast to".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(choices.join("\n")),
+            reverse: true,
+            header: Some("Cast to".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let index = choices
+        .iter()
+        .position(|name| *name == choice)
+        .ok_or_else(|| anyhow!("No DLNA renderer picked"))?;
+
+    Ok(renderers[index].clone())
+}
+
 fn handle_stream(
     settings: Arc<Args>,
     config: Arc<Config>,
@@ -587,111 +1625,277 @@ fn handle_stream(
     download_dir: Option<String>,
     url: String,
     media_info: (Option<String>, String, String, String, String),
-    episode_info: Option<(usize, usize, Vec<Vec<FlixHQEpisode>>)>,
+    episode_info: Option<(usize, usize, FlixHQSeason)>,
     subtitles: Vec<String>,
-    subtitle_language: Option<Languages>,
+    subtitle_language: Option<LanguageTag>,
 ) -> BoxFuture<'static, anyhow::Result<()>> {
-    let subtitles_choice = settings.no_subs;
     let player_url = url.clone();
 
-    let subtitles_for_player = if subtitles_choice {
-        info!("Continuing without subtitles");
-        None
-    } else {
-        if subtitles.len() > 0 {
+    let download_presence = settings.play.rpc.then(|| DownloadPresence {
+        title: media_info.3.clone(),
+        season_and_episode_num: episode_info.as_ref().map(|(a, b, _)| (*a, *b)),
+        large_image: media_info.4.clone(),
+    });
+
+    let download_target = download_dir.as_ref().map(|download_dir| {
+        download_target(
+            download_dir,
+            &media_info.3,
+            episode_info
+                .as_ref()
+                .map(|(season, episode, _)| (*season, episode + 1)),
+            config.organize_downloads,
+        )
+    });
+
+    async move {
+        if download_target.is_none() || settings.download_and_play {
+            check_allowance(&config.limits, settings.media.rofi)?;
+        }
+
+        if let Some((download_dir, download_title)) = download_target
+            .as_ref()
+            .filter(|_| config.organize_downloads && !settings.play.dry_run)
+        {
+            let episode_title = media_info.0.as_deref().unwrap_or_default();
+            let episode = episode_info.as_ref().map(|(season, episode, _)| {
+                (
+                    *season,
+                    episode + 1,
+                    episode_title
+                        .split_once(": ")
+                        .map_or(episode_title, |(_, title)| title),
+                )
+            });
+
+            if let Err(e) = write_nfo(
+                download_dir,
+                download_title,
+                &media_info.2,
+                episode,
+                config.tmdb_api_key.as_deref(),
+            )
+            .await
+            {
+                warn!("Failed to write the .nfo file: {}", e);
+            }
+        }
+
+        let use_subtitles = if settings.media.no_subs {
+            false
+        } else if !settings.media.subs
+            && config.subtitles == SubtitlesMode::Ask
+            && !settings.unattended()
+            && !subtitles.is_empty()
+        {
+            confirm(settings.media.rofi, Message::PlayWithSubtitles, true, &config).await
+        } else {
+            true
+        };
+
+        let subtitles_for_player = if !use_subtitles {
+            info!("{}", Message::ContinuingWithoutSubtitles);
+            None
+        } else if !subtitles.is_empty() {
             Some(subtitles.clone())
         } else {
-            info!("No subtitles available!");
+            info!("{}", Message::NoSubtitlesAvailable);
             None
-        }
-    };
+        };
 
-    let subtitle_language = if subtitles_choice {
-        subtitle_language
-    } else {
-        None
-    };
+        let subtitle_language = if use_subtitles {
+            subtitle_language
+        } else {
+            None
+        };
+
+        let download_target = match download_target {
+            Some(target) if settings.download_and_play => {
+                let download_settings = Args {
+                    detach: true,
+                    media: MediaOptions {
+                        quality: settings.download_quality.or(settings.media.quality),
+                        ..settings.media.clone()
+                    },
+                    ..(*settings).clone()
+                };
+
+                download(
+                    target,
+                    url.clone(),
+                    &download_settings,
+                    &config,
+                    None,
+                    subtitles_for_player.clone(),
+                    subtitle_language,
+                )
+                .await?;
+
+                None
+            }
+            download_target => download_target,
+        };
 
-    async move {
         match player {
             Player::Celluloid => {
-                if let Some(download_dir) = download_dir {
+                if let Some((download_dir, download_title)) = download_target {
                     download(
-                        download_dir,
-                        media_info.3,
+                        (download_dir, download_title),
                         url,
+                        &settings,
+                        &config,
+                        download_presence,
                         subtitles_for_player,
                         subtitle_language,
                     )
                     .await?;
 
-                    info!("Download completed. Exiting...");
                     return Ok(());
                 }
 
-                let title = if let Some(title) = media_info.0 {
+                let url = if settings.media.quality.is_some() {
+                    url_quality(url, settings.media.quality).await?
+                } else {
+                    url
+                };
+
+                let mpv_start = start_position(&settings, &media_info, &episode_info)
+                    .await
+                    .map(format_position);
+
+                let title = if let Some(title) = &media_info.0 {
                     format!("{} - {}", media_info.3, title)
                 } else {
-                    media_info.3
+                    media_info.3.clone()
                 };
 
+                let watchlater_path = reset_watchlater_dir();
+                let progress_url = url.clone();
+
                 let celluloid = Celluloid::new();
+                let started = std::time::Instant::now();
 
                 celluloid.play(CelluloidArgs {
                     url,
+                    mpv_start,
                     mpv_sub_files: subtitles_for_player,
                     mpv_force_media_title: Some(title),
+                    mpv_sub_delay: config.sub_delay,
+                    mpv_sub_font_size: config.sub_font_size,
+                    mpv_sub_scale: config.sub_scale,
+                    mpv_fullscreen: settings.play.fullscreen,
+                    mpv_loop_file: settings.play.r#loop,
+                    mpv_save_position_on_quit: true,
+                    mpv_watch_later_dir: Some(watchlater_path),
+                    extra_args: settings.play.player_args.clone(),
                     ..Default::default()
                 })?;
+
+                if settings.play.dry_run {
+                    return Ok(());
+                }
+
+                record_playback(&config.limits, started)?;
+
+                if settings.play.history {
+                    let progress = save_progress(progress_url)
+                        .await
+                        .inspect_err(|e| debug!("No position from the player: {}", e))
+                        .ok();
+
+                    save_player_history(&settings, &config, &media_info, &episode_info, progress)
+                        .await?;
+                }
             }
             Player::Iina => {
-                if let Some(download_dir) = download_dir {
+                if let Some((download_dir, download_title)) = download_target {
                     download(
-                        download_dir,
-                        media_info.3,
+                        (download_dir, download_title),
                         url,
+                        &settings,
+                        &config,
+                        download_presence,
                         subtitles_for_player,
                         subtitle_language,
                     )
                     .await?;
 
-                    info!("Download completed. Exiting...");
                     return Ok(());
                 }
 
-                let title = if let Some(title) = media_info.0 {
+                let url = if settings.media.quality.is_some() {
+                    url_quality(url, settings.media.quality).await?
+                } else {
+                    url
+                };
+
+                let mpv_start = start_position(&settings, &media_info, &episode_info)
+                    .await
+                    .map(format_position);
+
+                let title = if let Some(title) = &media_info.0 {
                     format!("{} - {}", media_info.3, title)
                 } else {
-                    media_info.3
+                    media_info.3.clone()
                 };
 
+                let watchlater_path = reset_watchlater_dir();
+                let progress_url = url.clone();
+
                 let iina = Iina::new();
+                let started = std::time::Instant::now();
 
                 iina.play(IinaArgs {
                     url,
                     no_stdin: true,
                     keep_running: true,
+                    mpv_start,
                     mpv_sub_files: subtitles_for_player,
                     mpv_force_media_title: Some(title),
+                    mpv_sub_delay: config.sub_delay,
+                    mpv_sub_font_size: config.sub_font_size,
+                    mpv_sub_scale: config.sub_scale,
+                    mpv_fullscreen: settings.play.fullscreen,
+                    mpv_loop_file: settings.play.r#loop,
+                    mpv_save_position_on_quit: true,
+                    mpv_watch_later_dir: Some(watchlater_path),
+                    extra_args: settings.play.player_args.clone(),
                     ..Default::default()
                 })?;
+
+                if settings.play.dry_run {
+                    return Ok(());
+                }
+
+                record_playback(&config.limits, started)?;
+
+                if settings.play.history {
+                    let progress = save_progress(progress_url)
+                        .await
+                        .inspect_err(|e| debug!("No position from the player: {}", e))
+                        .ok();
+
+                    save_player_history(&settings, &config, &media_info, &episode_info, progress)
+                        .await?;
+                }
             }
             Player::Vlc => {
-                if let Some(download_dir) = download_dir {
+                if let Some((download_dir, download_title)) = download_target {
                     download(
-                        download_dir,
-                        media_info.3,
+                        (download_dir, download_title),
                         url,
+                        &settings,
+                        &config,
+                        download_presence,
                         subtitles_for_player,
                         subtitle_language,
                     )
                     .await?;
 
-                    info!("Download completed. Exiting...");
                     return Ok(());
                 }
 
-                let url = url_quality(url, settings.quality).await?;
+                let url = url_quality(url, settings.media.quality).await?;
 
                 let title: String = if let Some(title_part) = &media_info.0 {
                     format!("{} - {}", media_info.3, title_part)
@@ -699,15 +1903,70 @@ fn handle_stream(
                     media_info.3.to_string()
                 };
 
+                let start_time = start_position(&settings, &media_info, &episode_info).await;
+
+                let http_port = std::net::TcpListener::bind("127.0.0.1:0")?
+                    .local_addr()?
+                    .port();
+                // From the OS, so other local users can't guess it to read the position
+                let mut secret = [0u8; 16];
+                getrandom::getrandom(&mut secret)?;
+                let http_password: String =
+                    secret.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+                // VLC has no watch-later file, so its HTTP interface is polled for the position
+                let last_status = Arc::new(std::sync::Mutex::new(None));
+                let tracker = settings.play.history.then(|| {
+                    let last_status = Arc::clone(&last_status);
+                    let http_password = http_password.clone();
+
+                    tokio::spawn(async move {
+                        loop {
+                            tokio::time::sleep(Duration::from_secs(2)).await;
+
+                            if let Some(status) = playback_status(http_port, &http_password).await {
+                                *last_status.lock().unwrap() = Some(status);
+                            }
+                        }
+                    })
+                });
+
                 let vlc = Vlc::new();
+                let started = std::time::Instant::now();
 
-                vlc.play(VlcArgs {
-                    url,
-                    input_slave: subtitles_for_player,
-                    meta_title: Some(title),
-                    ..Default::default()
+                tokio::task::block_in_place(|| {
+                    vlc.play(VlcArgs {
+                        url,
+                        start_time,
+                        input_slave: subtitles_for_player,
+                        meta_title: Some(title),
+                        fullscreen: settings.play.fullscreen,
+                        repeat: settings.play.r#loop,
+                        http_port: settings.play.history.then_some(http_port),
+                        http_password: settings.play.history.then_some(http_password),
+                        extra_args: settings.play.player_args.clone(),
+                        ..Default::default()
+                    })
                 })?;
 
+                if settings.play.dry_run {
+                    return Ok(());
+                }
+
+                record_playback(&config.limits, started)?;
+
+                if let Some(tracker) = tracker {
+                    tracker.abort();
+
+                    let progress = last_status
+                        .lock()
+                        .unwrap()
+                        .map(|(time, length)| (format_position(time), time * 100.0 / length));
+
+                    save_player_history(&settings, &config, &media_info, &episode_info, progress)
+                        .await?;
+                }
+
                 player_run_choice(
                     media_info,
                     episode_info,
@@ -721,35 +1980,96 @@ fn handle_stream(
                 )
                 .await?;
             }
-            Player::Mpv => {
-                if let Some(download_dir) = download_dir {
+            Player::Custom => {
+                if let Some((download_dir, download_title)) = download_target {
                     download(
-                        download_dir,
-                        media_info.3,
+                        (download_dir, download_title),
                         url,
-                        subtitles_for_player.clone(),
+                        &settings,
+                        &config,
+                        download_presence,
+                        subtitles_for_player,
                         subtitle_language,
                     )
                     .await?;
 
-                    info!("Download completed. Exiting...");
                     return Ok(());
                 }
 
-                let watchlater_path =
-                    format!("{}/lobster-rs/watchlater", std::env::temp_dir().display());
+                let url = url_quality(url, settings.media.quality).await?;
+
+                let title: String = if let Some(title_part) = &media_info.0 {
+                    format!("{} - {}", media_info.3, title_part)
+                } else {
+                    media_info.3.to_string()
+                };
+
+                let command = settings
+                    .play
+                    .open_with
+                    .as_deref()
+                    .or(config.player_command.as_deref())
+                    .unwrap_or_default();
 
-                let watchlater_dir = std::path::PathBuf::new().join(&watchlater_path);
+                let custom = Custom::new(command);
+                let started = std::time::Instant::now();
+
+                custom.play(CustomArgs {
+                    url,
+                    title,
+                    sub_files: subtitles_for_player,
+                    extra_args: settings.play.player_args.clone(),
+                })?;
+
+                if settings.play.dry_run {
+                    return Ok(());
+                }
 
-                if watchlater_dir.exists() {
-                    std::fs::remove_dir_all(&watchlater_dir)
-                        .expect("Failed to remove watchlater directory!");
+                record_playback(&config.limits, started)?;
+
+                if settings.play.history {
+                    // There's no way to ask an arbitrary command how far it got, so only the
+                    // episode is recorded
+                    save_history(
+                        media_info.clone(),
+                        episode_info.clone(),
+                        format_position(0.0),
+                        None,
+                        false,
+                    )
+                    .await?;
                 }
 
-                std::fs::create_dir_all(&watchlater_dir)
-                    .expect("Failed to create watchlater directory!");
+                player_run_choice(
+                    media_info,
+                    episode_info,
+                    config,
+                    settings,
+                    player,
+                    download_dir,
+                    player_url,
+                    subtitles,
+                    subtitle_language,
+                )
+                .await?;
+            }
+            Player::Dlna => {
+                if let Some((download_dir, download_title)) = download_target {
+                    download(
+                        (download_dir, download_title),
+                        url,
+                        &settings,
+                        &config,
+                        download_presence,
+                        subtitles_for_player,
+                        subtitle_language,
+                    )
+                    .await?;
+
+                    return Ok(());
+                }
 
-                let url = url_quality(url, settings.quality).await?;
+                let url = url_quality(url, settings.media.quality).await?;
 
                 let title: String = if let Some(title_part) = &media_info.0 {
                     format!("{} - {}", media_info.3, title_part)
@@ -757,37 +2077,214 @@ fn handle_stream(
                     media_info.3.to_string()
                 };
 
-                let mpv = Mpv::new();
+                let renderer = choose_renderer(&settings, &config).await?;
 
-                let mut child = mpv.play(MpvArgs {
-                    url: url.clone(),
-                    sub_files: subtitles_for_player.clone(),
-                    force_media_title: Some(title),
-                    watch_later_dir: Some(watchlater_path),
-                    write_filename_in_watch_later_config: true,
-                    save_position_on_quit: true,
-                    ..Default::default()
-                })?;
+                if settings.play.dry_run {
+                    println!("Cast {} to {}", url, renderer.name);
+                    return Ok(());
+                }
+
+                if subtitles_for_player.is_some() {
+                    info!("DLNA renderers can't load subtitle files, casting without them");
+                }
+
+                let started = std::time::Instant::now();
+
+                renderer.play(&url, &title).await?;
+                info!("Casting {:?} to {}", title, renderer.name);
+
+                renderer.wait().await;
+                record_playback(&config.limits, started)?;
 
-                if settings.rpc {
-                    let season_and_episode_num = episode_info.as_ref().map(|(a, b, _)| (*a, *b));
+                if settings.play.history {
+                    // The renderer isn't asked how far it got, so only the episode is recorded
+                    save_history(
+                        media_info.clone(),
+                        episode_info.clone(),
+                        format_position(0.0),
+                        None,
+                        false,
+                    )
+                    .await?;
+                }
 
-                    discord_presence(
-                        &media_info.2.clone(),
-                        season_and_episode_num,
-                        child,
-                        &media_info.3,
+                player_run_choice(
+                    media_info,
+                    episode_info,
+                    config,
+                    settings,
+                    player,
+                    download_dir,
+                    player_url,
+                    subtitles,
+                    subtitle_language,
+                )
+                .await?;
+            }
+            Player::Mpv => {
+                if let Some((download_dir, download_title)) = download_target {
+                    download(
+                        (download_dir, download_title),
+                        url,
+                        &settings,
+                        &config,
+                        download_presence,
+                        subtitles_for_player.clone(),
+                        subtitle_language,
                     )
                     .await?;
+
+                    return Ok(());
+                }
+
+                let watchlater_path = reset_watchlater_dir();
+
+                let url = url_quality(url, settings.media.quality).await?;
+
+                let title: String = if let Some(title_part) = &media_info.0 {
+                    format!("{} - {}", media_info.3, title_part)
                 } else {
-                    child.wait()?;
+                    media_info.3.to_string()
+                };
+
+                let mpv = Mpv::new();
+
+                let mut start = start_position(&settings, &media_info, &episode_info)
+                    .await
+                    .map(format_position);
+                let mut restarts = 0;
+
+                let (position, failed) = loop {
+                    let started = std::time::Instant::now();
+
+                    let Some(mut child) = mpv.play(MpvArgs {
+                        url: url.clone(),
+                        sub_files: subtitles_for_player.clone(),
+                        force_media_title: Some(title.clone()),
+                        watch_later_dir: Some(watchlater_path.clone()),
+                        write_filename_in_watch_later_config: true,
+                        save_position_on_quit: true,
+                        input_ipc_server: settings.play.sleep.map(|_| sleep_timer::ipc_path()),
+                        start: start.clone(),
+                        sub_delay: config.sub_delay,
+                        sub_font_size: config.sub_font_size,
+                        sub_scale: config.sub_scale,
+                        fullscreen: settings.play.fullscreen,
+                        loop_file: settings.play.r#loop,
+                        subtitle_fallback: settings.subtitle_fallback.map(|tag| tag.to_string()),
+                        profile: config.mpv_profile.clone(),
+                        config_dir: config.mpv_config_dir.clone(),
+                        extra_args: settings.play.player_args.clone(),
+                        ..Default::default()
+                    })?
+                    else {
+                        return Ok(());
+                    };
+
+                    sleep_timer::watch();
+
+                    let position = if settings.play.rpc {
+                        let season_and_episode_num =
+                            episode_info.as_ref().map(|(a, b, _)| (*a, *b));
+
+                        discord_presence(
+                            &media_info.2.clone(),
+                            season_and_episode_num,
+                            &mut child,
+                            &media_info.3,
+                        )
+                        .await?
+                    } else {
+                        track_position(&mut child)?
+                    };
+
+                    let status = child.wait()?;
+                    record_playback(&config.limits, started)?;
+
+                    // A missing exit code means mpv was killed by a signal instead of quitting.
+                    if status.code().is_none() && restarts < config.player_restart_attempts {
+                        restarts += 1;
+                        warn!(
+                            "mpv crashed ({}), restarting at {} (attempt {}/{})",
+                            status,
+                            position.as_deref().unwrap_or("00:00:00"),
+                            restarts,
+                            config.player_restart_attempts
+                        );
+                        start = position;
+                        continue;
+                    }
+
+                    let failed = status.code().is_some_and(|code| code != 0)
+                        && started.elapsed() < Duration::from_secs(FAILED_PLAYBACK_SECS);
+
+                    break (position, failed);
+                };
+
+                if failed {
+                    error!("mpv exited right away, the stream link is probably dead.");
+                    return Err(StreamError::PlaybackFailed.into());
                 }
 
-                if config.history {
+                let play_next = match &position {
+                    _ if sleep_timer::expired() => false,
+                    Some(position)
+                        if settings.play.autoplay
+                            && episode_info.is_some()
+                            && !settings.unattended() =>
+                    {
+                        playback_progress(&url, position)
+                            .await
+                            .is_some_and(|progress| progress >= config.completion_threshold)
+                            && has_next_episode(&settings, &episode_info).await?
+                    }
+                    _ => false,
+                };
+
+                if settings.play.history {
                     let (position, progress) = save_progress(url).await?;
 
-                    save_history(media_info.clone(), episode_info.clone(), position, progress)
-                        .await?;
+                    let finished = progress >= config.completion_threshold
+                        || (progress > 0.0
+                            && !settings.unattended()
+                            && ask_mark_finished(settings.media.rofi, progress).await);
+
+                    save_history(
+                        media_info.clone(),
+                        episode_info.clone(),
+                        position,
+                        Some(progress),
+                        finished,
+                    )
+                    .await?;
+
+                    if finished {
+                        anime_sync::sync_progress(
+                            &config,
+                            &media_info.3,
+                            anime_episode(&episode_info),
+                        )
+                        .await;
+                    }
+                }
+
+                if play_next && autoplay_countdown(AUTOPLAY_COUNTDOWN)? {
+                    handle_servers(
+                        config,
+                        settings,
+                        Some(true),
+                        (
+                            media_info.0,
+                            media_info.1.as_str(),
+                            media_info.2.as_str(),
+                            media_info.3.as_str(),
+                            media_info.4.as_str(),
+                        ),
+                        episode_info,
+                    )
+                    .await?;
+
+                    return Ok(());
                 }
 
                 player_run_choice(
@@ -804,49 +2301,60 @@ fn handle_stream(
                 .await?;
             }
             Player::MpvAndroid => {
-                if let Some(download_dir) = download_dir {
+                if let Some((download_dir, download_title)) = download_target {
                     download(
-                        download_dir,
-                        media_info.2,
+                        (download_dir, download_title),
                         url,
+                        &settings,
+                        &config,
+                        download_presence,
                         subtitles_for_player,
                         subtitle_language,
                     )
                     .await?;
 
-                    info!("Download completed. Exiting...");
                     return Ok(());
                 }
 
+                let url = if settings.media.quality.is_some() {
+                    url_quality(url, settings.media.quality).await?
+                } else {
+                    url
+                };
+
                 let title: String = if let Some(title_part) = media_info.0 {
                     format!("{} - {}", media_info.3, title_part)
                 } else {
                     media_info.3.to_string()
                 };
 
-                Command::new("am")
-                    .args([
-                        "start",
-                        "--user",
-                        "0",
-                        "-a",
-                        "android.intent.action.VIEW",
-                        "-d",
-                        &url,
-                        "-n",
-                        "is.xyz.mpv/.MPVActivity",
-                        "-e",
-                        "title",
-                        &title,
-                    ])
-                    .spawn()
-                    .map_err(|e| {
-                        error!("Failed to start MPV for Android: {}", e);
-                        SpawnError::IOError(e)
-                    })?;
+                let mut command = Command::new("am");
+                command.args([
+                    "start",
+                    "--user",
+                    "0",
+                    "-a",
+                    "android.intent.action.VIEW",
+                    "-d",
+                    &url,
+                    "-n",
+                    "is.xyz.mpv/.MPVActivity",
+                    "-e",
+                    "title",
+                    &title,
+                ]);
+
+                if dry_run(&command) {
+                    return Ok(());
+                }
+
+                command.spawn().map_err(|e| {
+                    error!("Failed to start MPV for Android: {}", e);
+                    SpawnError::IOError(e)
+                })?;
             }
             Player::SyncPlay => {
-                let url = url_quality(url, settings.quality).await?;
+                let url = url_quality(url, settings.media.quality).await?;
 
                 let title: String = if let Some(title_part) = media_info.0 {
                     format!("{} - {}", media_info.3, title_part)
@@ -854,13 +2362,52 @@ fn handle_stream(
                     media_info.3.to_string()
                 };
 
-                Command::new("syncplay")
-                    .args([&url, "--", &format!("--force-media-title={}", title)])
-                    .spawn()
-                    .map_err(|e| {
-                        error!("Failed to start Syncplay: {}", e);
-                        SpawnError::IOError(e)
-                    })?;
+                let mut syncplay_args = vec![];
+
+                if let Some(server) = &settings.play.syncplay_server {
+                    syncplay_args.push(format!("--host={}", server));
+                }
+
+                if let Some(room) = &settings.play.syncplay_room {
+                    syncplay_args.push(format!("--room={}", room));
+                }
+
+                if let Some(username) = &settings.play.syncplay_username {
+                    syncplay_args.push(format!("--name={}", username));
+                }
+
+                syncplay_args.extend([
+                    url,
+                    String::from("--"),
+                    format!("--force-media-title={}", title),
+                ]);
+
+                if let Some(sub_files) = subtitles_for_player {
+                    let sub_files = sub_files
+                        .iter()
+                        .map(|sub_file| sub_file.replace(":", r#"\:"#))
+                        .collect::<Vec<_>>()
+                        .join(":");
+
+                    syncplay_args.push(format!("--sub-files={}", sub_files));
+                }
+
+                debug!(
+                    "Executing syncplay command: {}",
+                    redact(&format!("{:?}", syncplay_args))
+                );
+
+                let mut command = Command::new("syncplay");
+                command.args(syncplay_args).args(&settings.play.player_args);
+
+                if dry_run(&command) {
+                    return Ok(());
+                }
+
+                command.spawn().map_err(|e| {
+                    error!("Failed to start Syncplay: {}", e);
+                    SpawnError::IOError(e)
+                })?;
             }
         }
 
@@ -869,12 +2416,293 @@ fn handle_stream(
     .boxed()
 }
 
+/// What `--subs-only --json` prints.
+#[derive(Serialize, schemars::JsonSchema)]
+struct SubtitlesOutput<'a> {
+    title: &'a str,
+    /// ISO 639-1 code of the subtitle language
+    language: &'a str,
+    /// Whether `language` comes from `subs_fallback`, as none of the requested ones had subtitles
+    fallback: bool,
+    /// URLs of the subtitle files
+    subtitles: Vec<String>,
+}
+
+/// What `search --json` prints for every result.
+#[derive(Serialize, schemars::JsonSchema, Debug, Clone)]
+pub struct SearchOutput {
+    id: String,
+    title: String,
+    /// `movie` or `tv`
+    media_type: String,
+    image: String,
+    /// Release year, movies only
+    year: Option<String>,
+    /// Seasons, shows only
+    seasons: Option<usize>,
+}
+
+impl From<&FlixHQInfo> for SearchOutput {
+    fn from(info: &FlixHQInfo) -> Self {
+        match info {
+            FlixHQInfo::Movie(movie) => SearchOutput {
+                id: movie.id.clone(),
+                title: movie.title.clone(),
+                media_type: movie.media_type.to_string(),
+                image: movie.image.clone(),
+                year: Some(movie.year.clone()),
+                seasons: None,
+            },
+            FlixHQInfo::Tv(tv) => SearchOutput {
+                id: tv.id.clone(),
+                title: tv.title.clone(),
+                media_type: tv.media_type.to_string(),
+                image: tv.image.clone(),
+                year: None,
+                seasons: Some(tv.seasons.total_seasons),
+            },
+        }
+    }
+}
+
+/// The movie or episode that was picked, for `--json-only`.
+#[derive(Serialize, schemars::JsonSchema)]
+struct SelectionOutput<'a> {
+    media_id: &'a str,
+    title: &'a str,
+    image: &'a str,
+    /// Shows only
+    season: Option<usize>,
+    /// 1-based, shows only
+    episode: Option<usize>,
+    episode_title: Option<&'a str>,
+    episode_id: &'a str,
+}
+
+/// The single document `--json-only` prints.
+#[derive(Serialize, schemars::JsonSchema)]
+struct JsonOnlyOutput<'a> {
+    /// What the search found, empty when continuing from the history
+    search_results: &'a [SearchOutput],
+    selection: SelectionOutput<'a>,
+    server: String,
+    /// The stream in the chosen quality
+    stream: String,
+    sources: &'a [Source],
+    subtitles: SubtitlesOutput<'a>,
+}
+
+/// The schemas of the `--json` output, keyed by the mode that prints them.
+fn json_schema() -> serde_json::Value {
+    json!({
+        "json_only": schemars::schema_for!(JsonOnlyOutput),
+        "search": schemars::schema_for!(SearchOutput),
+        "sources": schemars::schema_for!(FlixHQSources),
+        "subtitles": schemars::schema_for!(SubtitlesOutput),
+    })
+}
+
+async fn subtitles_only(
+    settings: &Args,
+    config: &Config,
+    media_title: &str,
+    subtitles: Vec<String>,
+    subtitle_language: LanguageTag,
+    fallback: bool,
+) -> anyhow::Result<()> {
+    if subtitles.is_empty() {
+        warn!("No {} subtitles found for {}", subtitle_language, media_title);
+        return Ok(());
+    }
+
+    if settings.media.json {
+        println!(
+            "{}",
+            json!(SubtitlesOutput {
+                title: media_title,
+                language: subtitle_language.code,
+                fallback,
+                subtitles,
+            })
+        );
+        return Ok(());
+    }
+
+    let download_dir = settings
+        .download
+        .as_ref()
+        .and_then(|inner| inner.clone())
+        .unwrap_or_else(|| config.download.clone());
+
+    let subtitles = convert_subtitles(subtitles).await;
+
+    save_subtitles(&download_dir, media_title, &subtitles, subtitle_language).await
+}
+
+fn track_preference(prefer: bool, exclude: bool) -> Option<bool> {
+    match (prefer, exclude) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+async fn subtitle_picker<'a>(settings: &Args, subtitles: &[&'a Subtitle]) -> Vec<&'a Subtitle> {
+    if subtitles.is_empty() {
+        return vec![];
+    }
+
+    let mut subtitle_choices = vec![String::from("None")];
+    subtitle_choices.extend(subtitles.iter().map(|subtitle| subtitle.label.clone()));
+
+    let subtitle_choice = launcher(
+        &vec![],
+        settings.media.rofi,
+        &mut RofiArgs {
+            process_stdin: Some(subtitle_choices.join("\n")),
+            mesg: Some("Choose subtitles".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(subtitle_choices.join("\n")),
+            reverse: true,
+            header: Some("Choose subtitles".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    debug!("Subtitle choice: {}", subtitle_choice);
+
+    subtitles
+        .iter()
+        .find(|subtitle| subtitle.label == subtitle_choice)
+        .map(|subtitle| vec![*subtitle])
+        .unwrap_or_default()
+}
+
+/// Called when FlixHQ has nothing to play. With the `torrents` feature and a
+/// `torrent_indexer` in the config this searches for a torrent instead.
+#[cfg_attr(not(feature = "torrents"), allow(unused_variables))]
+async fn no_sources(
+    config: &Config,
+    settings: &Args,
+    query: &str,
+    error: anyhow::Error,
+) -> anyhow::Result<()> {
+    #[cfg(feature = "torrents")]
+    if let Some(indexer) = config
+        .torrent_indexer
+        .as_ref()
+        .filter(|_| !settings.play.json_only && !settings.unattended())
+    {
+        warn!("{}, searching torrents instead", error);
+        return torrent_fallback(config, settings, indexer, query).await;
+    }
+
+    Err(error)
+}
+
+#[cfg(feature = "torrents")]
+async fn torrent_fallback(
+    config: &Config,
+    settings: &Args,
+    indexer: &str,
+    query: &str,
+) -> anyhow::Result<()> {
+    let torrents = TorrentIndexer::new(indexer).search(query).await?;
+
+    if torrents.is_empty() {
+        return Err(anyhow!("No torrents found for {}", query));
+    }
+
+    let choices: Vec<String> = torrents
+        .iter()
+        .map(|torrent| match torrent.size {
+            Some(size) => format!(
+                "{} [{} seeders, {}]",
+                torrent.title,
+                torrent.seeders,
+                format_size(size)
+            ),
+            None => format!("{} [{} seeders]", torrent.title, torrent.seeders),
+        })
+        .collect();
+
+    let torrent_choice = launcher(
+        &vec![],
+        settings.media.rofi,
+        &mut RofiArgs {
+            process_stdin: Some(choices.join("\n")),
+            mesg: Some("Choose a torrent".to_string()),
+            dmenu: true,
+            case_sensitive: true,
+            entry_prompt: Some("".to_string()),
+            ..Default::default()
+        },
+        &mut FzfArgs {
+            process_stdin: Some(choices.join("\n")),
+            reverse: true,
+            header: Some("Choose a torrent".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let torrent = choices
+        .iter()
+        .position(|choice| *choice == torrent_choice)
+        .map(|i| &torrents[i])
+        .ok_or_else(|| anyhow!("No selection made. Exiting..."))?;
+
+    debug!("Torrent choice: {:?}", torrent);
+
+    let command = utils::template::expand_command(
+        &config.torrent_command,
+        &[
+            ("magnet", vec![torrent.link.clone()]),
+            ("title", vec![torrent.title.clone()]),
+        ],
+    );
+
+    let Some((executable, args)) = command.split_first().filter(|_| !settings.media.json) else {
+        println!("{}", torrent.link);
+        return Ok(());
+    };
+
+    Command::new(executable)
+        .args(args)
+        .status()
+        .map_err(|e| {
+            error!("Failed to start {}: {}", executable, e);
+            SpawnError::IOError(e)
+        })?;
+
+    Ok(())
+}
+
 pub async fn handle_servers(
     config: Arc<Config>,
     settings: Arc<Args>,
     next_episode: Option<bool>,
     media_info: (Option<String>, &str, &str, &str, &str),
-    show_info: Option<(usize, usize, Vec<Vec<FlixHQEpisode>>)>,
+    show_info: Option<(usize, usize, FlixHQSeason)>,
+) -> anyhow::Result<()> {
+    play_from_servers(config, settings, next_episode, media_info, show_info, vec![]).await
+}
+
+/// Plays the episode from the first server that isn't in `failed_servers`, the servers
+/// whose stream couldn't be played while retrying this episode.
+async fn play_from_servers(
+    config: Arc<Config>,
+    settings: Arc<Args>,
+    next_episode: Option<bool>,
+    media_info: (Option<String>, &str, &str, &str, &str),
+    show_info: Option<(usize, usize, FlixHQSeason)>,
+    failed_servers: Vec<Provider>,
 ) -> anyhow::Result<()> {
     debug!(
         "Fetching servers for episode_id: {}, media_id: {}",
@@ -887,10 +2715,14 @@ pub async fn handle_servers(
             let mut episode_number = show_info.1; 
             let mut season_number = show_info.0; 
 
-            let total_seasons = show_info.2.len();
+            let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+            let seasons = &show_info.2;
+            let total_seasons = seasons.total_seasons;
 
-            if next_episode {
-                let total_episodes = show_info.2[season_number - 1].len();
+            if next_episode && settings.media.shuffle {
+                (season_number, episode_number) = random_episode(&flixhq, seasons).await?;
+            } else if next_episode {
+                let total_episodes = seasons.episodes(&flixhq, season_number).await?.len();
 
                 if episode_number + 1 < total_episodes {
                     // Move to next episode
@@ -901,8 +2733,7 @@ pub async fn handle_servers(
                     episode_number = 0;
                 } else {
                     // No next episode or season available, staying at the last episode
-                    error!("No next episode or season available.");
-                    std::process::exit(1);
+                    return Err(anyhow!("No next episode or season available."));
                 }
             } else {
                 // Move to the previous episode
@@ -911,21 +2742,20 @@ pub async fn handle_servers(
                 } else if season_number > 1 {
                     // Move to the last episode of the previous season
                     season_number -= 1;
-                    episode_number = show_info.2[season_number - 1].len() - 1;
+                    episode_number = seasons.episodes(&flixhq, season_number).await?.len() - 1;
                 } else {
                     // No previous episode available, staying at the first episode
-                    error!("No previous episode available.");
-                    std::process::exit(1);
+                    return Err(anyhow!("No previous episode available."));
                 }
             }
 
-            let episode_info= show_info.2[season_number - 1][episode_number].clone();
+            let episode_info = seasons.episodes(&flixhq, season_number).await?[episode_number].clone();
 
             (
                 episode_info.id.clone(),
                 Some(episode_info.title),
                 Some((season_number, episode_number, show_info.2)),
-                FlixHQ
+                flixhq
                     .servers(&episode_info.id, media_info.2)
                     .await
                     .map_err(|_| anyhow::anyhow!("Timeout while fetching servers"))?,
@@ -935,15 +2765,28 @@ pub async fn handle_servers(
                 media_info.1.to_string(),
                 media_info.0,
                 show_info,
-                FlixHQ
+                FlixHQ::new(&*SCRAPER_CLIENT)
                     .servers(media_info.1, media_info.2)
                     .await
                     .map_err(|_| anyhow::anyhow!("Timeout while fetching servers"))?,
             )
         };
 
+    let torrent_query = match &new_show_info {
+        Some((season, episode, _)) => {
+            format!("{} S{:02}E{:02}", media_info.3, season, episode + 1)
+        }
+        None => media_info.3.to_string(),
+    };
+
     if server_results.servers.is_empty() {
-        return Err(anyhow::anyhow!("No servers found"));
+        return no_sources(
+            &config,
+            &settings,
+            &torrent_query,
+            anyhow::anyhow!("No servers found"),
+        )
+        .await;
     }
 
     let servers: Vec<Provider> = server_results
@@ -956,75 +2799,369 @@ pub async fn handle_servers(
         })
         .collect();
 
-    let server_choice = settings.provider.unwrap_or(Provider::Vidcloud);
+    let server_choice = settings.media.provider.unwrap_or(Provider::Vidcloud);
+
+    let servers: Vec<Provider> = servers
+        .into_iter()
+        .filter(|server| !failed_servers.contains(server))
+        .collect();
+
+    if servers.is_empty() && !failed_servers.is_empty() {
+        return no_sources(
+            &config,
+            &settings,
+            &torrent_query,
+            anyhow::anyhow!("None of the servers could be played"),
+        )
+        .await;
+    }
 
     let server = servers
         .iter()
         .find(|&&x| x == server_choice)
+        .or(servers.first())
         .unwrap_or(&Provider::Vidcloud);
 
+    if !settings.unattended() && !settings.play.incognito {
+        let last = LastSelection {
+            media_id: media_info.2.to_string(),
+            title: media_info.3.to_string(),
+            image: media_info.4.to_string(),
+            episode: new_show_info
+                .as_ref()
+                .map(|(season, episode, _)| (*season, *episode)),
+            provider: *server,
+            quality: settings.media.quality.map(Quality::to_u32),
+        };
+
+        if let Err(e) = save_last_selection(&last) {
+            warn!("Failed to save the last selection: {}", e);
+        }
+    }
+
     debug!("Fetching sources for selected server: {:?}", server);
 
-    let sources = FlixHQ
+    let Ok(sources) = FlixHQ::new(&*SCRAPER_CLIENT)
         .sources(episode_id.as_str(), media_info.2, *server)
         .await
-        .map_err(|_| anyhow::anyhow!("Timeout while fetching sources"))?;
+    else {
+        return no_sources(
+            &config,
+            &settings,
+            &torrent_query,
+            anyhow::anyhow!("Timeout while fetching sources"),
+        )
+        .await;
+    };
 
-    debug!("{}", json!(sources));
+    debug!("{}", redact(&json!(sources).to_string()));
 
-    if settings.json {
+    if settings.media.json {
         info!("{}", serde_json::to_value(&sources).unwrap());
     }
 
     match (sources.sources, sources.subtitles) {
         (
             FlixHQSourceType::VidCloud(vidcloud_sources),
-            FlixHQSubtitles::VidCloud(vidcloud_subtitles),
+            FlixHQSubtitles::VidCloud(vidcloud_tracks),
         ) => {
             if vidcloud_sources.is_empty() {
-                return Err(anyhow::anyhow!("No sources available from VidCloud"));
+                return no_sources(
+                    &config,
+                    &settings,
+                    &torrent_query,
+                    anyhow::anyhow!("No sources available from VidCloud"),
+                )
+                .await;
+            }
+
+            let vidcloud_subtitles: Vec<Subtitle> =
+                vidcloud_tracks.into_iter().map(Subtitle::from).collect();
+
+            debug!("{}", redact(&json!(vidcloud_subtitles).to_string()));
+
+            // Languages given with --language win over what was picked for this show before
+            let explicit_language = settings.media.language != config.subs_language;
+
+            let preference = if !explicit_language && !settings.media.choose_subs {
+                subtitle_preference(media_info.2)
+            } else {
+                None
+            };
+
+            let subtitles_declined = preference
+                .as_ref()
+                .is_some_and(|preference| preference.language.is_none());
+
+            let mut languages = if settings.media.language.is_empty() {
+                vec![LanguageTag::from(Languages::English)]
+            } else {
+                settings.media.language.clone()
+            };
+
+            if let Some(language) = preference.as_ref().and_then(|preference| preference.language) {
+                languages.retain(|tag| *tag != language);
+                languages.insert(0, language);
             }
 
-            debug!("{}", json!(vidcloud_subtitles));
+            let language_tracks = |language: &LanguageTag| {
+                let subtitles = filter_subtitles(
+                    vidcloud_subtitles
+                        .iter()
+                        .filter(|subtitle| subtitle.language == *language)
+                        .collect(),
+                    track_preference(settings.media.sdh, settings.media.no_sdh),
+                    track_preference(settings.media.forced, settings.media.no_forced),
+                );
+
+                (!subtitles.is_empty()).then_some((*language, subtitles))
+            };
+
+            let (subtitle_language, language_subtitles) = languages
+                .iter()
+                .find_map(language_tracks)
+                .unwrap_or((languages[0], vec![]));
+
+            debug!("Using subtitle language: {}", subtitle_language);
+
+            let subtitle_candidates: Vec<&Subtitle> = if settings.media.choose_subs {
+                vidcloud_subtitles.iter().collect()
+            } else {
+                language_subtitles
+            };
 
-            let selected_subtitles: Vec<String> = futures::stream::iter(vidcloud_subtitles)
-                .filter(|subtitle| {
-                    let settings = Arc::clone(&settings);
-                    let subtitle_label = subtitle.label.clone();
-                    async move {
-                        let language = settings.language.unwrap_or(Languages::English).to_string();
-                        subtitle_label.contains(&language)
+            let remembered_subtitle = preference.as_ref().and_then(|preference| {
+                subtitle_candidates
+                    .iter()
+                    .find(|subtitle| preference.label.as_ref() == Some(&subtitle.label))
+                    .copied()
+            });
+
+            let show_picker = !settings.media.no_subs
+                && !subtitles_declined
+                && remembered_subtitle.is_none()
+                && !settings.unattended()
+                && (settings.media.choose_subs || subtitle_candidates.len() > 1);
+
+            let picked_subtitles: Vec<&Subtitle> = if subtitles_declined {
+                vec![]
+            } else if let Some(subtitle) = remembered_subtitle {
+                vec![subtitle]
+            } else if show_picker {
+                subtitle_picker(&settings, &subtitle_candidates).await
+            } else {
+                subtitle_candidates
+            };
+
+            let remember = show_picker
+                || (explicit_language && !settings.media.no_subs && !picked_subtitles.is_empty());
+
+            if remember && !settings.play.incognito {
+                let preference = SubtitlePreference {
+                    language: picked_subtitles.first().map(|subtitle| subtitle.language),
+                    label: picked_subtitles.first().map(|subtitle| subtitle.label.clone()),
+                };
+
+                if let Err(e) = save_subtitle_preference(media_info.2, &preference) {
+                    warn!("Failed to save subtitle preference: {}", e);
+                }
+            }
+
+            let mut subtitle_tag = picked_subtitles
+                .first()
+                .map(|subtitle| subtitle.language)
+                .unwrap_or(subtitle_language);
+
+            let mut selected_subtitles: Vec<String> = picked_subtitles
+                .iter()
+                .map(|subtitle| subtitle.url.clone())
+                .collect();
+
+            if selected_subtitles.is_empty()
+                && !settings.media.no_subs
+                && !settings.media.choose_subs
+                && !subtitles_declined
+            {
+                if let Some(api_key) = &config.opensubtitles_api_key {
+                    info!(
+                        "No {} subtitles from {}, trying OpenSubtitles",
+                        subtitle_language, server
+                    );
+
+                    let season_and_episode = new_show_info
+                        .as_ref()
+                        .map(|(season, episode, _)| (*season, episode + 1));
+
+                    match OpenSubtitles::new(api_key)
+                        .fetch(media_info.3, subtitle_language, season_and_episode)
+                        .await
+                    {
+                        Ok(subtitle_path) => {
+                            selected_subtitles.push(subtitle_path.display().to_string())
+                        }
+                        Err(e) => warn!("OpenSubtitles fallback failed: {}", e),
                     }
-                })
-                .map(|subtitle| subtitle.file.clone())
-                .collect()
+                }
+            }
+
+            let mut subtitle_fallback = None;
+
+            if selected_subtitles.is_empty()
+                && !settings.media.no_subs
+                && !settings.media.choose_subs
+                && !subtitles_declined
+            {
+                if let Some((language, subtitles)) = config
+                    .subs_fallback
+                    .iter()
+                    .filter(|language| !languages.contains(language))
+                    .find_map(language_tracks)
+                {
+                    info!(
+                        "No {} subtitles, falling back to {}",
+                        subtitle_language, language
+                    );
+
+                    selected_subtitles = subtitles
+                        .iter()
+                        .map(|subtitle| subtitle.url.clone())
+                        .collect();
+                    subtitle_tag = language;
+                    subtitle_fallback = Some(language);
+                }
+            }
+
+            if settings.media.subs_only {
+                let media_title = match &episode_title {
+                    Some(episode_title) => format!("{} - {}", media_info.3, episode_title),
+                    None => media_info.3.to_string(),
+                };
+
+                return subtitles_only(
+                    &settings,
+                    &config,
+                    &media_title,
+                    selected_subtitles,
+                    subtitle_tag,
+                    subtitle_fallback.is_some(),
+                )
                 .await;
+            }
+
+            if settings.play.print_link {
+                let url = url_quality(vidcloud_sources[0].file.to_string(), settings.media.quality)
+                    .await?;
 
-            debug!("Selected subtitles: {:?}", selected_subtitles);
+                println!("{}", url);
+
+                if settings.play.print_subs {
+                    for subtitle in &selected_subtitles {
+                        println!("{}", subtitle);
+                    }
+                }
+
+                return Ok(());
+            }
+
+            if settings.play.json_only {
+                let stream =
+                    url_quality(vidcloud_sources[0].file.to_string(), settings.media.quality)
+                        .await?;
 
-            let mut player = match config.player.to_lowercase().as_str() {
+                let title = match &episode_title {
+                    Some(episode_title) => format!("{} - {}", media_info.3, episode_title),
+                    None => media_info.3.to_string(),
+                };
+
+                let output = JsonOnlyOutput {
+                    search_results: &settings.search_results,
+                    selection: SelectionOutput {
+                        media_id: media_info.2,
+                        title: media_info.3,
+                        image: media_info.4,
+                        season: new_show_info.as_ref().map(|(season, _, _)| *season),
+                        episode: new_show_info.as_ref().map(|(_, episode, _)| episode + 1),
+                        episode_title: episode_title.as_deref(),
+                        episode_id: &episode_id,
+                    },
+                    server: server.to_string(),
+                    stream,
+                    sources: &vidcloud_sources,
+                    subtitles: SubtitlesOutput {
+                        title: &title,
+                        language: subtitle_tag.code,
+                        fallback: subtitle_fallback.is_some(),
+                        subtitles: selected_subtitles,
+                    },
+                };
+
+                println!("{}", serde_json::to_string_pretty(&output)?);
+
+                return Ok(());
+            }
+
+            let selected_subtitles = convert_subtitles(selected_subtitles).await;
+
+            debug!("Selected subtitles: {}", redact(&format!("{:?}", selected_subtitles)));
+
+            let player_name = if settings.play.open_with.is_some() {
+                debug!("Opening the stream with the `--open-with` command");
+                String::from("custom")
+            } else {
+                choose_player(&config)
+            };
+
+            let mut player = match player_name.as_str() {
                 "vlc" => Player::Vlc,
                 "mpv" => Player::Mpv,
                 "syncplay" => Player::SyncPlay,
                 "iina" => Player::Iina,
                 "celluloid" => Player::Celluloid,
-                _ => {
-                    error!("Player not supported");
-                    std::process::exit(1);
+                "dlna" => Player::Dlna,
+                "custom" => {
+                    if settings.play.open_with.is_none() && config.player_command.is_none() {
+                        return Err(anyhow!(
+                            r#"`player = "custom"` needs a `player_command` in the config"#
+                        ));
+                    }
+
+                    Player::Custom
                 }
+                _ => return Err(anyhow!("Player not supported")),
             };
 
-            if cfg!(target_os = "android") {
+            if cfg!(target_os = "android") && settings.play.open_with.is_none() {
                 player = Player::MpvAndroid;
             }
 
-            if settings.syncplay {
+            if settings.play.syncplay && settings.play.open_with.is_none() {
                 player = Player::SyncPlay;
             }
 
+            if settings.play.dlna.is_some() {
+                player = Player::Dlna;
+            }
+
             debug!("Starting stream with player: {:?}", player);
 
-            handle_stream(
+            let settings = if settings.subtitle_fallback != subtitle_fallback {
+                Arc::new(Args {
+                    subtitle_fallback,
+                    ..(*settings).clone()
+                })
+            } else {
+                settings
+            };
+
+            remember_stream(&vidcloud_sources[0].file);
+
+            // Discord shows TMDB's poster when there is one
+            let large_image = match settings.play.rpc {
+                true => tmdb_poster(media_info.2, config.tmdb_api_key.as_deref()).await,
+                false => None,
+            };
+
+            let result = handle_stream(
                 Arc::clone(&settings),
                 Arc::clone(&config),
                 player,
@@ -1035,17 +3172,164 @@ pub async fn handle_servers(
                     .cloned(),
                 vidcloud_sources[0].file.to_string(),
                 (
-                    episode_title,
-                    episode_id,
+                    episode_title.clone(),
+                    episode_id.clone(),
                     media_info.2.to_string(),
                     media_info.3.to_string(),
-                    media_info.4.to_string(),
+                    large_image.unwrap_or_else(|| media_info.4.to_string()),
                 ),
-                new_show_info.map(|(a, b, c)| (a, b, c)),
+                new_show_info.clone(),
                 selected_subtitles,
-                Some(settings.language.unwrap_or(Languages::English)),
+                Some(subtitle_tag),
             )
-            .await?;
+            .await;
+
+            if let Err(e) = &result {
+                if let Some(StreamError::PlaybackFailed) = e.downcast_ref::<StreamError>() {
+                    warn!(
+                        "The stream from {} couldn't be played, trying another server.",
+                        server
+                    );
+
+                    let mut failed_servers = failed_servers;
+                    failed_servers.push(*server);
+
+                    return Box::pin(play_from_servers(
+                        config,
+                        settings,
+                        None,
+                        (
+                            episode_title,
+                            episode_id.as_str(),
+                            media_info.2,
+                            media_info.3,
+                            media_info.4,
+                        ),
+                        new_show_info,
+                        failed_servers,
+                    ))
+                    .await;
+                }
+            }
+
+            result?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The configured player if it's installed, otherwise the first installed player from
+/// `player_priority`.
+fn choose_player(config: &Config) -> String {
+    let player = config.player.to_lowercase();
+
+    if cfg!(target_os = "android")
+        || ["custom", "dlna"].contains(&player.as_str())
+        || find_player(&player).is_some()
+    {
+        return player;
+    }
+
+    match config
+        .player_priority
+        .iter()
+        .map(|fallback| fallback.to_lowercase())
+        .find(|fallback| find_player(fallback).is_some())
+    {
+        Some(fallback) => {
+            info!("{} isn't installed, using {} instead.", player, fallback);
+            fallback
+        }
+        None => {
+            warn!("None of the players in `player_priority` are installed.");
+            player
+        }
+    }
+}
+
+/// Plays the YouTube trailer of a movie or show with the configured player. mpv needs
+/// yt-dlp for it, VLC opens YouTube links on its own.
+pub async fn play_trailer(settings: &Args, config: &Config, media_id: &str) -> anyhow::Result<()> {
+    let details = load_details(media_id, config.tmdb_api_key.as_deref()).await?;
+
+    let trailer = match details.flixhq.trailer {
+        Some(trailer) => Some(trailer),
+        // Details cached before trailers were read don't have one
+        None => {
+            FlixHQ::new(&*SCRAPER_CLIENT)
+                .details(media_id)
+                .await?
+                .trailer
+        }
+    };
+
+    let Some(trailer) = trailer else {
+        warn!("FlixHQ has no trailer for {}", details.flixhq.title);
+        return Ok(());
+    };
+
+    let url = format!("https://www.youtube.com/watch?v={}", trailer);
+    let title = format!("{} (trailer)", details.flixhq.title);
+
+    debug!("Playing the trailer of {}: {}", details.flixhq.title, url);
+
+    let player = if settings.play.open_with.is_some() {
+        String::from("custom")
+    } else {
+        choose_player(config)
+    };
+
+    match player.as_str() {
+        "vlc" => Vlc::new().play(VlcArgs {
+            url,
+            meta_title: Some(title),
+            fullscreen: settings.play.fullscreen,
+            ..Default::default()
+        })?,
+        "iina" => Iina::new().play(IinaArgs {
+            url,
+            no_stdin: true,
+            keep_running: true,
+            mpv_force_media_title: Some(title),
+            mpv_fullscreen: settings.play.fullscreen,
+            ..Default::default()
+        })?,
+        "celluloid" => Celluloid::new().play(CelluloidArgs {
+            url,
+            mpv_force_media_title: Some(title),
+            mpv_fullscreen: settings.play.fullscreen,
+            ..Default::default()
+        })?,
+        "custom" => {
+            let command = settings
+                .play
+                .open_with
+                .as_deref()
+                .or(config.player_command.as_deref())
+                .unwrap_or_default();
+
+            Custom::new(command).play(CustomArgs {
+                url,
+                title,
+                sub_files: None,
+                extra_args: settings.play.player_args.clone(),
+            })?
+        }
+        _ => {
+            let mpv = Mpv::new();
+
+            if let Some(mut child) = mpv.play(MpvArgs {
+                url,
+                force_media_title: Some(title),
+                fullscreen: settings.play.fullscreen,
+                profile: config.mpv_profile.clone(),
+                config_dir: config.mpv_config_dir.clone(),
+                ..Default::default()
+            })? {
+                track_position(&mut child)?;
+                child.wait()?;
+            }
         }
     }
 
@@ -1065,6 +3349,13 @@ fn is_command_available(command: &str) -> bool {
     }
 }
 
+/// Whether fzf can be used for the menus, otherwise the built-in picker takes over.
+fn fzf_installed() -> bool {
+    static INSTALLED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+
+    *INSTALLED.get_or_init(|| is_command_available("fzf"))
+}
+
 fn check_dependencies() {
     let dependencies = if cfg!(target_os = "windows") {
         vec!["mpv", "chafa", "ffmpeg", "fzf"]
@@ -1075,7 +3366,15 @@ fn check_dependencies() {
     };
 
     for dep in dependencies {
-        if !is_command_available(dep) {
+        let available = if dep == "mpv" {
+            find_player(dep).is_some()
+        } else if dep == "fzf" {
+            fzf_installed()
+        } else {
+            is_command_available(dep)
+        };
+
+        if !available {
             match dep {
                 "chafa" => {
                     warn!(
@@ -1087,10 +3386,18 @@ fn check_dependencies() {
                     warn!("Rofi isn't installed. You won't be able to use rofi to search.");
                     continue;
                 }
+                "fzf" => {
+                    warn!("Fzf isn't installed. The built-in picker will be used instead.");
+                    continue;
+                }
                 "ffmpeg" => {
                     warn!("Ffmpeg isn't installed. You won't be able to download.");
                     continue;
                 }
+                "mpv" => {
+                    warn!("Mpv isn't installed. Another player from `player_priority` will be used.");
+                    continue;
+                }
                 _ => {
                     error!("{} is missing. Please install it.", dep);
                     std::process::exit(1);
@@ -1102,19 +3409,86 @@ fn check_dependencies() {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+    let args = Args::parse().resolve_command();
 
-    let log_level = if args.debug {
+    // Passed on to fzf and the players through the environment, crossterm reads it too
+    if args.no_color {
+        std::env::set_var("NO_COLOR", "1");
+    }
+
+    let log_level = if args.play.json_only || args.preview_details.is_some() {
+        LevelFilter::Off
+    } else if args.quiet {
+        LevelFilter::Warn
+    } else if args.verbose >= 2 {
+        LevelFilter::Trace
+    } else if args.verbose == 1 || args.debug || args.debug_unsafe {
         LevelFilter::Debug
     } else {
         LevelFilter::Info
     };
 
     rich_logger::init(log_level).unwrap();
+    redact::show_secrets(args.debug_unsafe);
+
+    if let Some(duration) = args.play.sleep {
+        sleep_timer::start(duration);
+    }
+
+    if args.json_schema {
+        println!("{}", serde_json::to_string_pretty(&json_schema())?);
+        std::process::exit(0);
+    }
 
-    check_dependencies();
+    if let Some(dir) = &args.generate_man {
+        let command = Args::command();
 
-    if args.update {
+        match dir {
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                clap_mangen::generate_to(command, dir)?;
+                info!("Wrote the man pages to {}", dir.display());
+            }
+            None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+        }
+
+        std::process::exit(0);
+    }
+
+    if args.self_test {
+        let passed = self_test().await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    if let Some(profile) = args
+        .profile
+        .clone()
+        .or_else(|| std::env::var("LOBSTER_PROFILE").ok())
+    {
+        set_profile(&profile);
+    }
+
+    if let Some(media_id) = &args.preview_details {
+        print_details(media_id).await;
+        std::process::exit(0);
+    }
+
+    // The subcommands don't play anything, so they work without a player or fzf
+    if args.command.is_none() {
+        check_dependencies();
+    }
+
+    if let Some(Commands::Completions { shell }) = args.command {
+        clap_complete::generate(
+            shell,
+            &mut Args::command(),
+            "lobster-rs",
+            &mut std::io::stdout(),
+        );
+        std::process::exit(0);
+    }
+
+    if let Some(Commands::Update) = args.command {
         let update_result = tokio::task::spawn_blocking(move || update()).await?;
 
         match update_result {
@@ -1128,34 +3502,126 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    if args.edit {
-        if cfg!(not(target_os = "windows")) {
-            let editor = std::env::var("EDITOR").map_err(|_| {
-                error!("EDITOR environment variable not set!");
-                std::process::exit(1);
-            }).unwrap();
-            std::process::Command::new(editor)
-                .arg(
-                    dirs::config_dir()
-                        .expect("Failed to get config directory")
-                        .join("lobster-rs/config.toml"),
-                )
-                .status()
-                .expect("Failed to open config file with editor");
+    if let Some(Commands::Config { command }) = &args.command {
+        manage_config(command)?;
+        std::process::exit(0);
+    }
+
+    let config = match Config::load_config() {
+        Ok(config) => {
+            report_config_problems();
+            Arc::new(config)
+        }
+        Err(e) => {
+            if report_config_problems() {
+                error!("Couldn't load the config because of the problems above.");
+            } else {
+                error!("Failed to load config file: {:#}", e);
+            }
 
-            info!("Done editing config file.");
-            std::process::exit(0);
-        } else {
-            error!("The `edit` flag is not supported on Windows.");
             std::process::exit(1);
         }
+    };
+
+    SCRAPER_CLIENT.limit(
+        config.max_concurrent_requests,
+        std::time::Duration::from_millis(config.request_delay_ms),
+    );
+
+    if let Some(histfile) = &config.histfile {
+        set_history_file(histfile);
     }
 
-    let config = Arc::new(Config::load_config().expect("Failed to load config file"));
+    set_ui_language(config.ui_language);
+    set_menu(config.menu);
+
+    if let Some(command) = &config.external_menu_command {
+        set_menu_command(command);
+    }
+
+    if let Err(e) = prune_history(config.history_max_entries, config.history_max_age_days) {
+        warn!("Failed to prune the history: {}", e);
+    }
+
+    if let Some(Commands::History { command }) = &args.command {
+        manage_history(command)?;
+        std::process::exit(0);
+    }
+
+    if let Some(Commands::Search {
+        query,
+        recent,
+        trending,
+        genre,
+        json,
+        filters,
+    }) = &args.command
+    {
+        search(
+            query.as_deref(),
+            recent.as_ref(),
+            trending.as_ref(),
+            genre.as_ref(),
+            filters,
+            *json,
+            config.tmdb_api_key.as_deref(),
+        )
+        .await?;
+        std::process::exit(0);
+    }
+
+    if args.anime_login {
+        anime_sync::login(&config).await?;
+        std::process::exit(0);
+    }
+
+    if args.report {
+        let report = generate_report(&config)?;
+        println!("{}", report);
+        info!(
+            "Report saved to {}",
+            std::env::temp_dir().join("lobster-report.md").display()
+        );
+        std::process::exit(0);
+    }
+
+    if args.stats {
+        print!("{}", watch_stats()?);
+        std::process::exit(0);
+    }
+
+    if args.download_status {
+        print!("{}", download_status()?);
+        std::process::exit(0);
+    }
+
+    if let Some(dir) = &args.pack {
+        let archive = pack(dir)?;
+        info!("Packed {} into {}", dir.display(), archive.display());
+        std::process::exit(0);
+    }
 
     let settings = Arc::new(Config::program_configuration(args, &config));
 
-    run(settings, config).await?;
+    set_dry_run(settings.play.dry_run);
 
-    Ok(())
+    if settings.play.history {
+        sync_history(&config.history_sync).await;
+    }
+
+    let result = match &settings.batch {
+        Some(path) => match load_batch(path) {
+            Ok(entries) => batch(entries, Arc::clone(&settings), Arc::clone(&config)).await,
+            Err(e) => Err(e),
+        },
+        None if settings.play.tui => tui::run(Arc::clone(&settings), Arc::clone(&config)).await,
+        None => run(Arc::clone(&settings), Arc::clone(&config)).await,
+    };
+
+    // Also when the session ended with an error, so its progress isn't lost
+    if settings.play.history {
+        sync_history(&config.history_sync).await;
+    }
+
+    result
 }
@@ -0,0 +1,150 @@
+use crate::{utils::anime_sync::AnimeEntry, CLIENT};
+use anyhow::anyhow;
+use log::debug;
+use serde::Deserialize;
+use serde_json::json;
+
+const API_URL: &str = "https://graphql.anilist.co";
+
+const SEARCH_QUERY: &str = "query ($search: String) {
+  Page(perPage: 5) {
+    media(search: $search, type: ANIME) {
+      id
+      episodes
+      title { romaji english native }
+      synonyms
+      mediaListEntry { progress }
+    }
+  }
+}";
+
+const SAVE_QUERY: &str = "mutation ($mediaId: Int, $progress: Int, $status: MediaListStatus) {
+  SaveMediaListEntry(mediaId: $mediaId, progress: $progress, status: $status) { id }
+}";
+
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<ResponseError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SearchData {
+    page: Page,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    media: Vec<Media>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Media {
+    id: u64,
+    episodes: Option<u32>,
+    title: MediaTitle,
+    #[serde(default)]
+    synonyms: Vec<String>,
+    media_list_entry: Option<MediaListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MediaTitle {
+    romaji: Option<String>,
+    english: Option<String>,
+    native: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MediaListEntry {
+    progress: Option<u32>,
+}
+
+pub struct AniList {
+    token: String,
+}
+
+impl AniList {
+    pub fn new(token: &str) -> Self {
+        debug!("Initializing AniList instance.");
+        Self {
+            token: token.to_string(),
+        }
+    }
+
+    /// The page to log in on, which shows the access token afterwards.
+    pub fn login_url(client_id: &str) -> String {
+        format!(
+            "https://anilist.co/api/v2/oauth/authorize?client_id={}&response_type=token",
+            client_id
+        )
+    }
+
+    async fn query<T: for<'de> Deserialize<'de>>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> anyhow::Result<T> {
+        let response: Response<T> = CLIENT
+            .post(API_URL)
+            .bearer_auth(&self.token)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .body(json!({ "query": query, "variables": variables }).to_string())
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.errors.first() {
+            return Err(anyhow!("AniList: {}", error.message));
+        }
+
+        response
+            .data
+            .ok_or_else(|| anyhow!("AniList returned no data"))
+    }
+
+    pub async fn search(&self, title: &str) -> anyhow::Result<Vec<AnimeEntry>> {
+        debug!("Searching AniList for {}", title);
+
+        let data: SearchData = self.query(SEARCH_QUERY, json!({ "search": title })).await?;
+
+        Ok(data
+            .page
+            .media
+            .into_iter()
+            .map(|media| AnimeEntry {
+                id: media.id,
+                titles: [media.title.romaji, media.title.english, media.title.native]
+                    .into_iter()
+                    .flatten()
+                    .chain(media.synonyms)
+                    .collect(),
+                episodes: media.episodes,
+                progress: media.media_list_entry.and_then(|entry| entry.progress),
+            })
+            .collect())
+    }
+
+    pub async fn update(&self, id: u64, progress: u32, completed: bool) -> anyhow::Result<()> {
+        let status = if completed { "COMPLETED" } else { "CURRENT" };
+
+        let _: serde_json::Value = self
+            .query(
+                SAVE_QUERY,
+                json!({ "mediaId": id, "progress": progress, "status": status }),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
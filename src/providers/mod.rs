@@ -1,5 +1,7 @@
-pub mod vidcloud;
-
-pub trait VideoExtractor {
-    async fn extract(&mut self, video_url: &str) -> anyhow::Result<()>;
-}
+pub mod anilist;
+pub mod myanimelist;
+pub mod opensubtitles;
+pub mod tmdb;
+#[cfg(feature = "torrents")]
+pub mod torrents;
+pub use lobster_core::providers::vidcloud;
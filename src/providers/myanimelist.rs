@@ -0,0 +1,195 @@
+use crate::{utils::anime_sync::AnimeEntry, CLIENT};
+use anyhow::anyhow;
+use log::debug;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+const API_URL: &str = "https://api.myanimelist.net/v2";
+const OAUTH_URL: &str = "https://myanimelist.net/v1/oauth2";
+
+/// Searches are cut to this many characters, the API refuses longer ones
+const MAX_QUERY_LENGTH: usize = 64;
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    data: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    node: Anime,
+}
+
+#[derive(Debug, Deserialize)]
+struct Anime {
+    id: u64,
+    title: String,
+    #[serde(default)]
+    alternative_titles: AlternativeTitles,
+    /// 0 while the number of episodes isn't known
+    #[serde(default)]
+    num_episodes: u32,
+    my_list_status: Option<ListStatus>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct AlternativeTitles {
+    #[serde(default)]
+    synonyms: Vec<String>,
+    en: Option<String>,
+    ja: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListStatus {
+    num_episodes_watched: u32,
+}
+
+/// The tokens from logging in, refreshed once the access token expires.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MyAnimeListTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+pub struct MyAnimeList {
+    client_id: String,
+    tokens: MyAnimeListTokens,
+}
+
+impl MyAnimeList {
+    pub fn new(client_id: &str, tokens: MyAnimeListTokens) -> Self {
+        debug!("Initializing MyAnimeList instance.");
+        Self {
+            client_id: client_id.to_string(),
+            tokens,
+        }
+    }
+
+    /// The page to log in on. MyAnimeList only accepts the `plain` PKCE method, so the
+    /// verifier is sent as is and has to be passed to [`MyAnimeList::login`] again.
+    pub fn login_url(client_id: &str, code_verifier: &str) -> String {
+        format!(
+            "{}/authorize?response_type=code&client_id={}&code_challenge={}&code_challenge_method=plain",
+            OAUTH_URL, client_id, code_verifier
+        )
+    }
+
+    /// Trades the code MyAnimeList redirected to after logging in for tokens.
+    pub async fn login(
+        client_id: &str,
+        code: &str,
+        code_verifier: &str,
+    ) -> anyhow::Result<MyAnimeListTokens> {
+        Self::request_tokens(&[
+            ("client_id", client_id),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", code_verifier),
+        ])
+        .await
+    }
+
+    async fn request_tokens(form: &[(&str, &str)]) -> anyhow::Result<MyAnimeListTokens> {
+        let response = CLIENT
+            .post(format!("{}/token", OAUTH_URL))
+            .form(form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "MyAnimeList refused the login: {}",
+                response.text().await?
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Gets a new access token when the old one expired. Returns the new tokens so they can
+    /// be saved for the next time.
+    pub async fn refresh(&mut self) -> anyhow::Result<MyAnimeListTokens> {
+        debug!("Refreshing the MyAnimeList access token");
+
+        self.tokens = Self::request_tokens(&[
+            ("client_id", &self.client_id),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &self.tokens.refresh_token),
+        ])
+        .await?;
+
+        Ok(self.tokens.clone())
+    }
+
+    /// Searches for `title`, see [`MyAnimeList::is_unauthorized`] for an expired token.
+    pub async fn search(&self, title: &str) -> anyhow::Result<Vec<AnimeEntry>> {
+        debug!("Searching MyAnimeList for {}", title);
+
+        let query: String = title.chars().take(MAX_QUERY_LENGTH).collect();
+
+        let response = CLIENT
+            .get(format!("{}/anime", API_URL))
+            .query(&[
+                ("q", query.as_str()),
+                ("limit", "5"),
+                ("fields", "alternative_titles,num_episodes,my_list_status"),
+            ])
+            .bearer_auth(&self.tokens.access_token)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let search: SearchResponse = response.json().await?;
+
+        Ok(search
+            .data
+            .into_iter()
+            .map(|result| {
+                let anime = result.node;
+
+                AnimeEntry {
+                    id: anime.id,
+                    titles: [
+                        Some(anime.title),
+                        anime.alternative_titles.en,
+                        anime.alternative_titles.ja,
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .chain(anime.alternative_titles.synonyms)
+                    .collect(),
+                    episodes: (anime.num_episodes > 0).then_some(anime.num_episodes),
+                    progress: anime
+                        .my_list_status
+                        .map(|status| status.num_episodes_watched),
+                }
+            })
+            .collect())
+    }
+
+    pub async fn update(&self, id: u64, progress: u32, completed: bool) -> anyhow::Result<()> {
+        let status = if completed { "completed" } else { "watching" };
+
+        CLIENT
+            .patch(format!("{}/anime/{}/my_list_status", API_URL, id))
+            .bearer_auth(&self.tokens.access_token)
+            .form(&[
+                ("num_watched_episodes", progress.to_string()),
+                ("status", status.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Whether a request failed because the access token expired.
+    pub fn is_unauthorized(error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(|error| error.status())
+            == Some(StatusCode::UNAUTHORIZED)
+    }
+}
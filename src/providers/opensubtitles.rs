@@ -0,0 +1,135 @@
+use crate::{utils::subtitles::LanguageTag, CLIENT};
+use anyhow::anyhow;
+use log::{debug, error};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::PathBuf;
+
+const API_URL: &str = "https://api.opensubtitles.com/api/v1";
+const USER_AGENT: &str = concat!("lobster-rs v", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    data: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    attributes: SubtitleAttributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubtitleAttributes {
+    #[serde(default)]
+    ratings: f32,
+    #[serde(default)]
+    download_count: u64,
+    files: Vec<SubtitleFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubtitleFile {
+    file_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadResponse {
+    link: String,
+    file_name: String,
+}
+
+pub struct OpenSubtitles {
+    api_key: String,
+}
+
+impl OpenSubtitles {
+    pub fn new(api_key: &str) -> Self {
+        debug!("Initializing OpenSubtitles instance.");
+        Self {
+            api_key: api_key.to_string(),
+        }
+    }
+
+    /// Searches by title (streams have no local file to hash) and downloads the
+    /// best rated subtitle into the temp directory, returning its path.
+    pub async fn fetch(
+        &self,
+        title: &str,
+        language: LanguageTag,
+        season_and_episode: Option<(usize, usize)>,
+    ) -> anyhow::Result<PathBuf> {
+        let mut query = vec![
+            ("query", title.to_string()),
+            ("languages", language.code.to_string()),
+        ];
+
+        if let Some((season, episode)) = season_and_episode {
+            query.push(("type", "episode".to_string()));
+            query.push(("season_number", season.to_string()));
+            query.push(("episode_number", episode.to_string()));
+        } else {
+            query.push(("type", "movie".to_string()));
+        }
+
+        debug!("Searching OpenSubtitles with query: {:?}", query);
+
+        let response = CLIENT
+            .get(format!("{}/subtitles", API_URL))
+            .query(&query)
+            .header("Api-Key", &self.api_key)
+            .header("User-Agent", USER_AGENT)
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        let search: SearchResponse = serde_json::from_str(&response).map_err(|e| {
+            error!("Failed to deserialize OpenSubtitles search response: {}", e);
+            e
+        })?;
+
+        let best = search
+            .data
+            .iter()
+            .filter(|result| !result.attributes.files.is_empty())
+            .max_by(|a, b| {
+                a.attributes
+                    .ratings
+                    .total_cmp(&b.attributes.ratings)
+                    .then(a.attributes.download_count.cmp(&b.attributes.download_count))
+            })
+            .ok_or_else(|| anyhow!("No subtitles found on OpenSubtitles for {}", title))?;
+
+        let file_id = best.attributes.files[0].file_id;
+        debug!("Selected OpenSubtitles file id: {}", file_id);
+
+        let response = CLIENT
+            .post(format!("{}/download", API_URL))
+            .header("Api-Key", &self.api_key)
+            .header("User-Agent", USER_AGENT)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .body(json!({ "file_id": file_id }).to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        let download: DownloadResponse = serde_json::from_str(&response).map_err(|e| {
+            error!("Failed to deserialize OpenSubtitles download response: {}", e);
+            e
+        })?;
+
+        let subtitle = CLIENT.get(&download.link).send().await?.bytes().await?;
+
+        let subtitle_dir = std::env::temp_dir().join("lobster-rs/subtitles");
+        std::fs::create_dir_all(&subtitle_dir)?;
+
+        let subtitle_path = subtitle_dir.join(download.file_name.replace('/', "-"));
+        std::fs::write(&subtitle_path, subtitle)?;
+
+        debug!("Saved OpenSubtitles subtitle to {:?}", subtitle_path);
+
+        Ok(subtitle_path)
+    }
+}
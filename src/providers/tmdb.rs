@@ -0,0 +1,193 @@
+use crate::{MediaType, CLIENT};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+const API_URL: &str = "https://api.themoviedb.org/3";
+const IMAGE_URL: &str = "https://image.tmdb.org/t/p/w500";
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct FindResponse {
+    movie_results: Vec<SearchResult>,
+    tv_results: Vec<SearchResult>,
+}
+
+/// A movie or show as `/movie/{id}` and `/tv/{id}` return it, shows having `name` and
+/// `first_air_date` where movies have `title` and `release_date`.
+#[derive(Debug, Deserialize)]
+struct DetailsResponse {
+    id: u64,
+    #[serde(alias = "name")]
+    title: String,
+    #[serde(default)]
+    overview: String,
+    vote_average: Option<f32>,
+    #[serde(default)]
+    genres: Vec<Genre>,
+    poster_path: Option<String>,
+    #[serde(default, alias = "first_air_date")]
+    release_date: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Genre {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeasonResponse {
+    #[serde(default)]
+    episodes: Vec<TmdbEpisode>,
+}
+
+/// An episode of a season as TMDB lists it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TmdbEpisode {
+    pub episode_number: usize,
+    #[serde(default)]
+    pub overview: String,
+    /// e.g. `2005-03-24`, left out until it's announced
+    pub air_date: Option<String>,
+    /// In minutes
+    pub runtime: Option<u32>,
+}
+
+/// What TMDB knows about a movie or show.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TmdbEntry {
+    pub id: u64,
+    pub media_type: MediaType,
+    pub title: String,
+    pub overview: String,
+    /// Out of 10, left out while nobody voted
+    pub rating: Option<f32>,
+    pub genres: Vec<String>,
+    /// The full URL of the poster, 500 pixels wide
+    pub poster: Option<String>,
+    /// e.g. `2005-03-24`
+    pub release_date: String,
+}
+
+pub struct Tmdb {
+    api_key: String,
+}
+
+impl Tmdb {
+    pub fn new(api_key: &str) -> Self {
+        debug!("Initializing TMDB instance.");
+        Self {
+            api_key: api_key.to_string(),
+        }
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> anyhow::Result<T> {
+        let request = CLIENT.get(format!("{}{}", API_URL, path)).query(query);
+
+        // Both the short API key and the long read access token work
+        let request = if self.api_key.starts_with("eyJ") {
+            request.bearer_auth(&self.api_key)
+        } else {
+            request.query(&[("api_key", &self.api_key)])
+        };
+
+        Ok(request.send().await?.error_for_status()?.json().await?)
+    }
+
+    /// Looks a movie or show up by its title, preferring one from `year` if it's known.
+    pub async fn find(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<u32>,
+    ) -> anyhow::Result<Option<TmdbEntry>> {
+        debug!("Searching TMDB for {} ({:?})", title, year);
+
+        let (year_key, path) = match media_type {
+            MediaType::Movie => ("year", "movie"),
+            MediaType::Tv => ("first_air_date_year", "tv"),
+        };
+
+        let mut query = vec![("query", title.to_string())];
+        query.extend(year.map(|year| (year_key, year.to_string())));
+
+        let mut search: SearchResponse = self.get(&format!("/search/{}", path), &query).await?;
+
+        // FlixHQ and TMDB don't always agree on the year
+        if search.results.is_empty() && year.is_some() {
+            search = self.get(&format!("/search/{}", path), &query[..1]).await?;
+        }
+
+        let Some(result) = search.results.first() else {
+            return Ok(None);
+        };
+
+        self.by_id(result.id, media_type).await.map(Some)
+    }
+
+    /// The movie or show with an IMDb id like `tt0386676`.
+    pub async fn by_imdb_id(&self, imdb_id: &str) -> anyhow::Result<Option<TmdbEntry>> {
+        debug!("Looking up {} on TMDB", imdb_id);
+
+        let found: FindResponse = self
+            .get(
+                &format!("/find/{}", imdb_id),
+                &[("external_source", "imdb_id".to_string())],
+            )
+            .await?;
+
+        let result = match (found.movie_results.first(), found.tv_results.first()) {
+            (Some(movie), _) => (movie.id, MediaType::Movie),
+            (None, Some(tv)) => (tv.id, MediaType::Tv),
+            (None, None) => return Ok(None),
+        };
+
+        self.by_id(result.0, result.1).await.map(Some)
+    }
+
+    /// The episodes of a season of the show with a TMDB id, `season_number` starting at 1.
+    pub async fn season(&self, id: u64, season_number: usize) -> anyhow::Result<Vec<TmdbEpisode>> {
+        debug!("Fetching season {} of {} from TMDB", season_number, id);
+
+        let season: SeasonResponse = self
+            .get(&format!("/tv/{}/season/{}", id, season_number), &[])
+            .await?;
+
+        Ok(season.episodes)
+    }
+
+    /// The movie or show with a TMDB id, the two having ids of their own.
+    pub async fn by_id(&self, id: u64, media_type: MediaType) -> anyhow::Result<TmdbEntry> {
+        let path = match media_type {
+            MediaType::Movie => "movie",
+            MediaType::Tv => "tv",
+        };
+
+        let details: DetailsResponse = self.get(&format!("/{}/{}", path, id), &[]).await?;
+
+        Ok(TmdbEntry {
+            id: details.id,
+            media_type,
+            title: details.title,
+            overview: details.overview,
+            rating: details.vote_average.filter(|rating| *rating > 0.0),
+            genres: details.genres.into_iter().map(|genre| genre.name).collect(),
+            poster: details
+                .poster_path
+                .map(|poster| format!("{}{}", IMAGE_URL, poster)),
+            release_date: details.release_date,
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use crate::CLIENT;
+use log::debug;
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub struct Torrent {
+    pub title: String,
+    /// Magnet link, or a `.torrent` URL when the indexer doesn't give one
+    pub link: String,
+    pub seeders: u32,
+    pub size: Option<u64>,
+}
+
+/// A Torznab indexer, as exposed by Jackett or Prowlarr.
+pub struct TorrentIndexer {
+    url: String,
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.trim()
+        .trim_start_matches("<![CDATA[")
+        .trim_end_matches("]]>")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+impl TorrentIndexer {
+    pub fn new(url: &str) -> Self {
+        debug!("Initializing torrent indexer instance.");
+        Self {
+            url: url.to_string(),
+        }
+    }
+
+    /// Searches the indexer for `query`, best seeded torrents first.
+    pub async fn search(&self, query: &str) -> anyhow::Result<Vec<Torrent>> {
+        debug!("Searching torrents for: {}", query);
+
+        let response = CLIENT
+            .get(&self.url)
+            .query(&[("t", "search"), ("q", query)])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let item_re = Regex::new(r"(?s)<item>(.*?)</item>").unwrap();
+        let title_re = Regex::new(r"(?s)<title>(.*?)</title>").unwrap();
+        let magnet_re = Regex::new(r#"name="magneturl"\s+value="([^"]+)""#).unwrap();
+        let link_re = Regex::new(r"(?s)<link>(.*?)</link>").unwrap();
+        let seeders_re = Regex::new(r#"name="seeders"\s+value="(\d+)""#).unwrap();
+        let size_re = Regex::new(r"<size>(\d+)</size>").unwrap();
+
+        let mut torrents: Vec<Torrent> = item_re
+            .captures_iter(&response)
+            .filter_map(|item| {
+                let item = &item[1];
+
+                let link = magnet_re
+                    .captures(item)
+                    .or_else(|| link_re.captures(item))
+                    .map(|captures| unescape_xml(&captures[1]))?;
+
+                Some(Torrent {
+                    title: title_re
+                        .captures(item)
+                        .map(|captures| unescape_xml(&captures[1]))?,
+                    link,
+                    seeders: seeders_re
+                        .captures(item)
+                        .and_then(|captures| captures[1].parse().ok())
+                        .unwrap_or(0),
+                    size: size_re
+                        .captures(item)
+                        .and_then(|captures| captures[1].parse().ok()),
+                })
+            })
+            .collect();
+
+        torrents.sort_by_key(|torrent| std::cmp::Reverse(torrent.seeders));
+
+        debug!("Found {} torrents", torrents.len());
+
+        Ok(torrents)
+    }
+}
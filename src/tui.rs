@@ -0,0 +1,818 @@
+use crate::cli::find_media;
+use crate::flixhq::{
+    flixhq::{FlixHQ, FlixHQEpisode, FlixHQInfo, FlixHQSeason},
+    ids::{EpisodeId, MediaId},
+};
+use crate::utils::{
+    config::Config,
+    history::{mark_watched, HistoryEntry, Store},
+    image_preview::{chafa_format, images_dir},
+};
+use crate::{handle_servers, is_command_available, Args, CLIENT, SCRAPER_CLIENT};
+use anyhow::anyhow;
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{enable_raw_mode, EnterAlternateScreen},
+};
+use log::debug;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, List, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, OnceLock},
+};
+use unicode_width::UnicodeWidthStr;
+
+const HELP: &str = "/ search  H history  Tab switch pane  Enter select  q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pane {
+    Search,
+    Results,
+    History,
+    Seasons,
+    Episodes,
+}
+
+/// A search result, as much as the panes need of it.
+struct Media {
+    id: String,
+    title: String,
+    image: String,
+    details: String,
+    is_tv: bool,
+}
+
+impl From<FlixHQInfo> for Media {
+    fn from(info: FlixHQInfo) -> Self {
+        match info {
+            FlixHQInfo::Movie(movie) => Media {
+                details: format!("Movie, {}, {}", movie.year, movie.duration),
+                id: movie.id,
+                title: movie.title,
+                image: movie.image,
+                is_tv: false,
+            },
+            FlixHQInfo::Tv(tv) => Media {
+                details: format!("TV show, {} seasons", tv.seasons.total_seasons),
+                id: tv.id,
+                title: tv.title,
+                image: tv.image,
+                is_tv: true,
+            },
+        }
+    }
+}
+
+/// The show whose seasons and episodes are being browsed.
+struct Show {
+    media_id: String,
+    title: String,
+    image: String,
+    seasons: FlixHQSeason,
+    /// The season the episodes are listed of, starting at 1
+    season: usize,
+    episodes: Vec<FlixHQEpisode>,
+    /// The episode titles with what was watched marked
+    labels: Vec<String>,
+}
+
+struct App {
+    pane: Pane,
+    /// Whether the results or the history are shown in the left pane
+    left: Pane,
+    query: String,
+    results: Vec<Media>,
+    results_state: ListState,
+    history: Vec<HistoryEntry>,
+    history_state: ListState,
+    show: Option<Show>,
+    seasons_state: ListState,
+    episodes_state: ListState,
+    status: String,
+    /// Posters are only shown with --image-preview and chafa installed
+    posters_enabled: bool,
+    /// Downloaded posters by image URL
+    posters: HashMap<String, PathBuf>,
+    poster_area: Rect,
+    /// The poster on screen and where, ratatui leaves those cells alone while it's there
+    drawn_poster: Option<(PathBuf, Rect)>,
+}
+
+/// Draws posters as kitty images where the terminal supports them, sixels otherwise.
+fn poster_format() -> &'static str {
+    static FORMAT: OnceLock<&str> = OnceLock::new();
+
+    FORMAT.get_or_init(|| {
+        let kitty = std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || std::env::var("TERM")
+                .is_ok_and(|term| term.contains("kitty") || term.contains("ghostty"));
+
+        if kitty {
+            "kitty"
+        } else {
+            chafa_format()
+        }
+    })
+}
+
+/// Kitty images stay until they are deleted, unlike sixels, which text simply overwrites.
+fn clear_posters() {
+    if poster_format() == "kitty" {
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(b"\x1b_Ga=d\x1b\\");
+        let _ = stdout.flush();
+    }
+}
+
+/// Draws the poster at `path` into `area` with chafa, past ratatui.
+fn draw_poster(path: &Path, area: Rect) -> anyhow::Result<()> {
+    if area.width == 0 || area.height == 0 {
+        return Ok(());
+    }
+
+    let format = poster_format();
+
+    let output = Command::new("chafa")
+        .args([
+            "-f",
+            format,
+            "-s",
+            &format!("{}x{}", area.width, area.height),
+        ])
+        .arg(path)
+        .output()?;
+
+    let mut stdout = std::io::stdout();
+
+    if format == "symbols" {
+        // Every line has to start at the left edge of the area, not of the screen
+        for (row, line) in String::from_utf8_lossy(&output.stdout).lines().enumerate() {
+            execute!(stdout, MoveTo(area.x, area.y + row as u16))?;
+            stdout.write_all(line.as_bytes())?;
+        }
+    } else {
+        execute!(stdout, MoveTo(area.x, area.y))?;
+        stdout.write_all(&output.stdout)?;
+    }
+
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// Moves the selection of a list by `step`, staying within its `len` items.
+fn step(state: &mut ListState, len: usize, step: isize) {
+    if len == 0 {
+        state.select(None);
+        return;
+    }
+
+    let selected = state.selected().unwrap_or(0) as isize + step;
+    state.select(Some(selected.clamp(0, len as isize - 1) as usize));
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::bordered().title(title);
+
+    if focused {
+        block.border_style(Style::new().fg(Color::Cyan))
+    } else {
+        block
+    }
+}
+
+impl App {
+    fn new(posters_enabled: bool) -> Self {
+        let mut app = App {
+            pane: Pane::Search,
+            left: Pane::Results,
+            query: String::new(),
+            results: vec![],
+            results_state: ListState::default(),
+            history: vec![],
+            history_state: ListState::default(),
+            show: None,
+            seasons_state: ListState::default(),
+            episodes_state: ListState::default(),
+            status: String::new(),
+            posters_enabled,
+            posters: HashMap::new(),
+            poster_area: Rect::default(),
+            drawn_poster: None,
+        };
+
+        app.reload_history();
+        app
+    }
+
+    fn reload_history(&mut self) {
+        self.history = match Store::load() {
+            Ok(store) => store.entries().to_vec(),
+            Err(e) => {
+                self.status = format!("Failed to load the history: {}", e);
+                vec![]
+            }
+        };
+
+        step(&mut self.history_state, self.history.len(), 0);
+
+        if let Some(show) = &mut self.show {
+            let titles: Vec<String> = show.episodes.iter().map(|e| e.title.clone()).collect();
+            show.labels = mark_watched(&show.media_id, show.season, &titles);
+        }
+    }
+
+    /// The title, details and image of what is highlighted, for the middle pane.
+    fn highlighted(&self) -> Option<(&str, String, &str)> {
+        match (&self.show, self.pane) {
+            (Some(show), Pane::Seasons | Pane::Episodes) => Some((
+                &show.title,
+                format!(
+                    "TV show, {} seasons\nSeason {}, {} episodes",
+                    show.seasons.total_seasons,
+                    show.season,
+                    show.episodes.len()
+                ),
+                &show.image,
+            )),
+            _ if self.left == Pane::History => {
+                let entry = &self.history[self.history_state.selected()?];
+
+                let details = match &entry.episode {
+                    Some(episode) => format!("Season {}, {}", episode.season, episode.title),
+                    None => "Movie".to_string(),
+                };
+
+                Some((&entry.title, details, &entry.image))
+            }
+            _ => {
+                let media = &self.results[self.results_state.selected()?];
+                Some((&media.title, media.details.clone(), &media.image))
+            }
+        }
+    }
+
+    /// Downloads the poster of what is highlighted, unless it was already.
+    async fn fetch_poster(&mut self) -> Option<PathBuf> {
+        if !self.posters_enabled {
+            return None;
+        }
+
+        let image = self.highlighted()?.2.to_string();
+
+        if let Some(path) = self.posters.get(&image) {
+            return Some(path.clone());
+        }
+
+        let fetch = async {
+            let bytes = CLIENT.get(&image).send().await?.bytes().await?;
+
+            let images_dir = images_dir();
+            std::fs::create_dir_all(&images_dir)?;
+
+            let path = images_dir.join(format!("tui-{}.jpg", self.posters.len()));
+            image::load_from_memory(&bytes)?.save(&path)?;
+
+            anyhow::Ok(path)
+        };
+
+        match fetch.await {
+            Ok(path) => {
+                self.posters.insert(image, path.clone());
+                Some(path)
+            }
+            Err(e) => {
+                debug!("Failed to fetch the poster {}: {}", image, e);
+                None
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, keep_poster: bool) {
+        let [search_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let [left_area, details_area, seasons_area, episodes_area] = Layout::horizontal([
+            Constraint::Percentage(30),
+            Constraint::Percentage(25),
+            Constraint::Length(14),
+            Constraint::Min(0),
+        ])
+        .areas(main_area);
+
+        frame.render_widget(
+            Paragraph::new(self.query.as_str())
+                .block(pane_block("Search", self.pane == Pane::Search)),
+            search_area,
+        );
+
+        if self.pane == Pane::Search {
+            frame.set_cursor_position((
+                search_area.x + 1 + self.query.width() as u16,
+                search_area.y + 1,
+            ));
+        }
+
+        let left_focused = matches!(self.pane, Pane::Results | Pane::History);
+
+        if self.left == Pane::History {
+            let items = self.history.iter().map(|entry| match &entry.episode {
+                Some(episode) => format!(
+                    "{}{} S{:02} {}",
+                    if entry.watched { "✓ " } else { "" },
+                    entry.title,
+                    episode.season,
+                    episode.title
+                ),
+                None => format!("{}{}", if entry.watched { "✓ " } else { "" }, entry.title),
+            });
+
+            frame.render_stateful_widget(
+                List::new(items)
+                    .block(pane_block("History", left_focused))
+                    .highlight_style(Style::new().reversed()),
+                left_area,
+                &mut self.history_state,
+            );
+        } else {
+            let items = self.results.iter().map(|media| {
+                format!(
+                    "{} ({})",
+                    media.title,
+                    if media.is_tv { "tv" } else { "movie" }
+                )
+            });
+
+            frame.render_stateful_widget(
+                List::new(items)
+                    .block(pane_block("Results", left_focused))
+                    .highlight_style(Style::new().reversed()),
+                left_area,
+                &mut self.results_state,
+            );
+        }
+
+        let details_block = pane_block("", false);
+        let details_inner = details_block.inner(details_area);
+
+        let (details_block, details) = match self.highlighted() {
+            Some((title, details, _)) => (details_block.title(title.to_string()), details),
+            None => (details_block, String::new()),
+        };
+
+        let [text_area, poster_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(details_inner);
+
+        frame.render_widget(details_block, details_area);
+        frame.render_widget(Paragraph::new(details).wrap(Wrap { trim: true }), text_area);
+
+        self.poster_area = poster_area;
+
+        if keep_poster {
+            let buffer = frame.buffer_mut();
+
+            for y in poster_area.top()..poster_area.bottom() {
+                for x in poster_area.left()..poster_area.right() {
+                    buffer[(x, y)].set_skip(true);
+                }
+            }
+        }
+
+        let (seasons, episodes) = match &self.show {
+            Some(show) => (
+                (1..=show.seasons.total_seasons)
+                    .map(|season| format!("Season {}", season))
+                    .collect(),
+                show.labels.clone(),
+            ),
+            None => (vec![], vec![]),
+        };
+
+        frame.render_stateful_widget(
+            List::new(seasons)
+                .block(pane_block("Seasons", self.pane == Pane::Seasons))
+                .highlight_style(Style::new().reversed()),
+            seasons_area,
+            &mut self.seasons_state,
+        );
+
+        frame.render_stateful_widget(
+            List::new(episodes)
+                .block(pane_block("Episodes", self.pane == Pane::Episodes))
+                .highlight_style(Style::new().reversed()),
+            episodes_area,
+            &mut self.episodes_state,
+        );
+
+        let status = if self.status.is_empty() {
+            HELP
+        } else {
+            &self.status
+        };
+
+        frame.render_widget(Paragraph::new(status).dim(), status_area);
+    }
+
+    async fn search(
+        &mut self,
+        query: Option<&str>,
+        settings: &Args,
+        config: &Config,
+    ) -> anyhow::Result<()> {
+        let (results, _) = find_media(
+            query,
+            settings.media.recent.as_ref(),
+            settings.media.trending.as_ref(),
+            // A genre picker can't be shown over the TUI
+            settings
+                .media
+                .genre
+                .as_ref()
+                .filter(|genre| genre.is_some()),
+            &settings.media.filters,
+            false,
+            config.tmdb_api_key.as_deref(),
+        )
+        .await?;
+
+        self.results = results.into_iter().map(Media::from).collect();
+        self.results_state.select(Some(0));
+        self.left = Pane::Results;
+        self.pane = Pane::Results;
+
+        Ok(())
+    }
+
+    /// Lists the seasons of a show, going to where the history left off.
+    async fn open_show(&mut self, media_id: &str, title: &str, image: &str) -> anyhow::Result<()> {
+        let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+        let FlixHQInfo::Tv(tv) = flixhq.info(media_id).await? else {
+            return Err(anyhow!("{} isn't a show", title));
+        };
+
+        let saved = self
+            .history
+            .iter()
+            .find(|entry| entry.media_id == media_id)
+            .and_then(|entry| entry.episode.clone());
+
+        self.show = Some(Show {
+            media_id: media_id.to_string(),
+            title: title.to_string(),
+            image: image.to_string(),
+            seasons: tv.seasons,
+            season: 0,
+            episodes: vec![],
+            labels: vec![],
+        });
+
+        self.open_season(saved.as_ref().map_or(1, |episode| episode.season))
+            .await?;
+
+        if let (Some(saved), Some(show)) = (saved, &self.show) {
+            let position = show.episodes.iter().position(|e| e.title == saved.title);
+            self.episodes_state.select(position.or(Some(0)));
+        }
+
+        self.pane = Pane::Episodes;
+
+        Ok(())
+    }
+
+    async fn open_season(&mut self, season: usize) -> anyhow::Result<()> {
+        let Some(show) = &mut self.show else {
+            return Ok(());
+        };
+
+        let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+        show.episodes = show.seasons.episodes(&flixhq, season).await?.to_vec();
+        show.season = season;
+
+        let titles: Vec<String> = show.episodes.iter().map(|e| e.title.clone()).collect();
+        show.labels = mark_watched(&show.media_id, season, &titles);
+
+        self.seasons_state.select(Some(season - 1));
+        self.episodes_state.select(Some(0));
+
+        Ok(())
+    }
+
+    /// Hands the terminal over to the player, and takes it back afterwards with everything
+    /// as it was.
+    async fn play(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        settings: &Arc<Args>,
+        config: &Arc<Config>,
+        media_info: (Option<String>, &str, &str, &str, &str),
+        show_info: Option<(usize, usize, FlixHQSeason)>,
+    ) -> anyhow::Result<()> {
+        clear_posters();
+        ratatui::restore();
+
+        let result = handle_servers(
+            config.clone(),
+            settings.clone(),
+            None,
+            media_info,
+            show_info,
+        )
+        .await;
+
+        enable_raw_mode()?;
+        execute!(std::io::stdout(), EnterAlternateScreen)?;
+        terminal.clear()?;
+
+        self.drawn_poster = None;
+        self.reload_history();
+
+        result
+    }
+
+    /// Acts on Enter in the focused pane.
+    async fn select(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        settings: &Arc<Args>,
+        config: &Arc<Config>,
+    ) -> anyhow::Result<()> {
+        match self.pane {
+            Pane::Search => {
+                if self.query.trim().is_empty() {
+                    return Ok(());
+                }
+
+                let query = self.query.clone();
+                self.search(Some(&query), settings, config).await
+            }
+            Pane::Results | Pane::History => {
+                let (media_id, title, image, is_tv) = if self.pane == Pane::History {
+                    let Some(entry) = self.history_state.selected().map(|i| &self.history[i])
+                    else {
+                        return Ok(());
+                    };
+
+                    (
+                        entry.media_id.clone(),
+                        entry.title.clone(),
+                        entry.image.clone(),
+                        entry.media_id.parse::<MediaId>()?.is_tv(),
+                    )
+                } else {
+                    let Some(media) = self.results_state.selected().map(|i| &self.results[i])
+                    else {
+                        return Ok(());
+                    };
+
+                    (
+                        media.id.clone(),
+                        media.title.clone(),
+                        media.image.clone(),
+                        media.is_tv,
+                    )
+                };
+
+                if is_tv {
+                    return self.open_show(&media_id, &title, &image).await;
+                }
+
+                let episode_id = EpisodeId::from(&media_id.parse::<MediaId>()?);
+
+                self.play(
+                    terminal,
+                    settings,
+                    config,
+                    (None, episode_id.as_str(), &media_id, &title, &image),
+                    None,
+                )
+                .await
+            }
+            Pane::Seasons => {
+                let season = self.seasons_state.selected().unwrap_or(0) + 1;
+                self.open_season(season).await?;
+                self.pane = Pane::Episodes;
+
+                Ok(())
+            }
+            Pane::Episodes => {
+                let Some(show) = &self.show else {
+                    return Ok(());
+                };
+
+                let Some(episode) = self.episodes_state.selected() else {
+                    return Ok(());
+                };
+
+                let episode_info = show.episodes[episode].clone();
+                let (media_id, title, image) = (
+                    show.media_id.clone(),
+                    show.title.clone(),
+                    show.image.clone(),
+                );
+                let show_info = Some((show.season, episode, show.seasons.clone()));
+
+                self.play(
+                    terminal,
+                    settings,
+                    config,
+                    (
+                        Some(episode_info.title),
+                        &episode_info.id,
+                        &media_id,
+                        &title,
+                        &image,
+                    ),
+                    show_info,
+                )
+                .await
+            }
+        }
+    }
+
+    fn focus(&mut self, pane: Pane) {
+        if matches!(pane, Pane::Results | Pane::History) {
+            self.left = pane;
+        }
+
+        self.pane = pane;
+    }
+
+    /// The panes in the order Tab goes through them.
+    fn cycle(&mut self, forward: bool) {
+        let panes = [Pane::Search, self.left, Pane::Seasons, Pane::Episodes];
+        let current = panes
+            .iter()
+            .position(|&pane| pane == self.pane)
+            .unwrap_or(0);
+
+        let next = if forward {
+            (current + 1) % panes.len()
+        } else {
+            (current + panes.len() - 1) % panes.len()
+        };
+
+        self.focus(panes[next]);
+    }
+
+    fn selected_list(&mut self) -> Option<(&mut ListState, usize)> {
+        match self.pane {
+            Pane::Search => None,
+            Pane::Results => Some((&mut self.results_state, self.results.len())),
+            Pane::History => Some((&mut self.history_state, self.history.len())),
+            Pane::Seasons => {
+                let len = self.show.as_ref().map_or(0, |s| s.seasons.total_seasons);
+                Some((&mut self.seasons_state, len))
+            }
+            Pane::Episodes => {
+                let len = self.show.as_ref().map_or(0, |s| s.episodes.len());
+                Some((&mut self.episodes_state, len))
+            }
+        }
+    }
+
+    /// Handles a key press, returns false to quit.
+    async fn handle_key(
+        &mut self,
+        key: KeyEvent,
+        terminal: &mut DefaultTerminal,
+        settings: &Arc<Args>,
+        config: &Arc<Config>,
+    ) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        self.status.clear();
+
+        match (self.pane, key.code) {
+            (Pane::Search, KeyCode::Char(c)) => self.query.push(c),
+            (Pane::Search, KeyCode::Backspace) => {
+                self.query.pop();
+            }
+            (Pane::Search, KeyCode::Esc) => self.focus(self.left),
+            (_, KeyCode::Char('q') | KeyCode::Esc) => return false,
+            (_, KeyCode::Char('/') | KeyCode::Char('s')) => self.focus(Pane::Search),
+            (_, KeyCode::Char('H')) => {
+                self.reload_history();
+                self.focus(Pane::History);
+            }
+            (_, KeyCode::Tab | KeyCode::Right | KeyCode::Char('l')) => self.cycle(true),
+            (_, KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h')) => self.cycle(false),
+            (_, KeyCode::Down | KeyCode::Char('j')) => {
+                if let Some((state, len)) = self.selected_list() {
+                    step(state, len, 1);
+                }
+            }
+            (_, KeyCode::Up | KeyCode::Char('k')) => {
+                if let Some((state, len)) = self.selected_list() {
+                    step(state, len, -1);
+                }
+            }
+            (_, KeyCode::Enter) => {
+                self.status = "Loading...".to_string();
+                let _ = terminal.draw(|frame| self.draw(frame, self.drawn_poster.is_some()));
+
+                self.status.clear();
+
+                if let Err(e) = self.select(terminal, settings, config).await {
+                    self.status = e.to_string();
+                }
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        settings: &Arc<Args>,
+        config: &Arc<Config>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let poster = self.fetch_poster().await;
+
+            let keep_poster = poster.is_some()
+                && self.drawn_poster.as_ref().map(|(path, _)| path) == poster.as_ref()
+                && self.drawn_poster.as_ref().map(|(_, area)| *area) == Some(self.poster_area);
+
+            terminal.draw(|frame| self.draw(frame, keep_poster))?;
+
+            if !keep_poster {
+                clear_posters();
+                self.drawn_poster = None;
+
+                if let Some(path) = poster {
+                    if let Err(e) = draw_poster(&path, self.poster_area) {
+                        debug!("Failed to draw the poster: {}", e);
+                    }
+
+                    // Not tried again on failure, until something else is highlighted
+                    self.drawn_poster = Some((path, self.poster_area));
+                }
+            }
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && !self.handle_key(key, terminal, settings, config).await
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Runs the full-screen interface of `--tui`, which stays open between playbacks.
+pub async fn run(settings: Arc<Args>, config: Arc<Config>) -> anyhow::Result<()> {
+    let posters_enabled = settings.media.image_preview && is_command_available("chafa");
+    let mut app = App::new(posters_enabled);
+
+    let mut terminal = ratatui::init();
+
+    if settings.query.is_some()
+        || settings.media.recent.is_some()
+        || settings.media.trending.is_some()
+        || matches!(settings.media.genre, Some(Some(_)))
+        || settings.media.filters.country.is_some()
+        || settings.media.filters.actor.is_some()
+        || settings.media.filters.has_id()
+    {
+        app.query = settings.query.clone().unwrap_or_default();
+
+        if let Err(e) = app
+            .search(settings.query.as_deref(), &settings, &config)
+            .await
+        {
+            app.status = e.to_string();
+        }
+    }
+
+    let result = app.run(&mut terminal, &settings, &config).await;
+
+    clear_posters();
+    ratatui::restore();
+
+    if images_dir().exists() {
+        std::fs::remove_dir_all(images_dir())?;
+    }
+
+    result
+}
@@ -0,0 +1,295 @@
+use crate::{
+    providers::{
+        anilist::AniList,
+        myanimelist::{MyAnimeList, MyAnimeListTokens},
+    },
+    utils::config::{AnimeTracker, Config},
+};
+use anyhow::anyhow;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    path::PathBuf,
+};
+
+/// An anime found on AniList or MyAnimeList.
+#[derive(Debug)]
+pub struct AnimeEntry {
+    pub id: u64,
+    pub titles: Vec<String>,
+    pub episodes: Option<u32>,
+    /// Episodes watched according to the tracker, if it's on the list
+    pub progress: Option<u32>,
+}
+
+/// Kept apart from `config.toml`, which is easily shared, and only readable by the user.
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Tokens {
+    anilist: Option<String>,
+    myanimelist: Option<MyAnimeListTokens>,
+}
+
+fn tokens_file() -> PathBuf {
+    dirs::config_dir()
+        .expect("Failed to find config dir")
+        .join("lobster-rs/anime_tokens.toml")
+}
+
+fn load_tokens() -> anyhow::Result<Tokens> {
+    let tokens_file = tokens_file();
+
+    if !tokens_file.exists() {
+        return Ok(Tokens::default());
+    }
+
+    Ok(toml::from_str(&std::fs::read_to_string(tokens_file)?)?)
+}
+
+fn save_tokens(tokens: &Tokens) -> anyhow::Result<()> {
+    let tokens_file = tokens_file();
+
+    if let Some(parent) = tokens_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        options.mode(0o600);
+
+        if tokens_file.exists() {
+            std::fs::set_permissions(&tokens_file, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    options
+        .open(&tokens_file)?
+        .write_all(toml::to_string(tokens)?.as_bytes())?;
+
+    debug!("Saved anime tracker tokens to {:?}", tokens_file);
+
+    Ok(())
+}
+
+/// A random PKCE code verifier, 64 hex characters.
+fn code_verifier() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0),
+    );
+
+    Sha256::digest(hasher.finish().to_le_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn read_line(prompt: &str) -> anyhow::Result<String> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+
+    Ok(line.trim().to_string())
+}
+
+/// Logs in to the tracker from `anime_tracker` in the config and saves the tokens.
+pub async fn login(config: &Config) -> anyhow::Result<()> {
+    let tracker = config.anime_tracker.ok_or_else(|| {
+        anyhow!("Set `anime_tracker` in the config to \"anilist\" or \"myanimelist\" first")
+    })?;
+
+    let mut tokens = load_tokens()?;
+
+    match tracker {
+        AnimeTracker::Anilist => {
+            let client_id = config
+                .anilist_client_id
+                .as_deref()
+                .ok_or_else(|| anyhow!("Set `anilist_client_id` in the config first"))?;
+
+            println!("Log in to AniList at {}", AniList::login_url(client_id));
+            tokens.anilist = Some(read_line("Paste the token it shows: ")?);
+        }
+        AnimeTracker::Myanimelist => {
+            let client_id = config
+                .myanimelist_client_id
+                .as_deref()
+                .ok_or_else(|| anyhow!("Set `myanimelist_client_id` in the config first"))?;
+
+            let code_verifier = code_verifier();
+
+            println!(
+                "Log in to MyAnimeList at {}",
+                MyAnimeList::login_url(client_id, &code_verifier)
+            );
+
+            // Either the code or the whole address it was redirected to
+            let redirect = read_line("Paste the address you were sent to afterwards: ")?;
+            let code = match redirect.split_once("code=") {
+                Some((_, code)) => code.split('&').next().unwrap_or_default(),
+                None => redirect.as_str(),
+            };
+
+            tokens.myanimelist = Some(MyAnimeList::login(client_id, code, &code_verifier).await?);
+        }
+    }
+
+    save_tokens(&tokens)?;
+    info!("Logged in, anime progress will be synced from now on.");
+
+    Ok(())
+}
+
+/// Lowercase letters and digits only, so punctuation doesn't get in the way of matching.
+fn normalize(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Picks the result that is the show itself. For later seasons a title starting with the
+/// show's and containing the season number is enough, e.g. "Attack on Titan Season 2".
+fn find_match(entries: Vec<AnimeEntry>, title: &str, season: usize) -> Option<AnimeEntry> {
+    let title = normalize(title);
+
+    entries.into_iter().find(|entry| {
+        entry.titles.iter().map(|name| normalize(name)).any(|name| {
+            if season > 1 {
+                name.starts_with(&title) && name[title.len()..].contains(&season.to_string())
+            } else {
+                name == title
+            }
+        })
+    })
+}
+
+enum Tracker {
+    AniList(AniList),
+    MyAnimeList(MyAnimeList),
+}
+
+impl Tracker {
+    fn name(&self) -> &'static str {
+        match self {
+            Tracker::AniList(_) => "AniList",
+            Tracker::MyAnimeList(_) => "MyAnimeList",
+        }
+    }
+
+    async fn search(
+        &mut self,
+        query: &str,
+        tokens: &mut Tokens,
+    ) -> anyhow::Result<Vec<AnimeEntry>> {
+        match self {
+            Tracker::AniList(anilist) => anilist.search(query).await,
+            Tracker::MyAnimeList(myanimelist) => match myanimelist.search(query).await {
+                Err(e) if MyAnimeList::is_unauthorized(&e) => {
+                    tokens.myanimelist = Some(myanimelist.refresh().await?);
+                    save_tokens(tokens)?;
+
+                    myanimelist.search(query).await
+                }
+                result => result,
+            },
+        }
+    }
+
+    async fn update(&self, id: u64, progress: u32, completed: bool) -> anyhow::Result<()> {
+        match self {
+            Tracker::AniList(anilist) => anilist.update(id, progress, completed).await,
+            Tracker::MyAnimeList(myanimelist) => myanimelist.update(id, progress, completed).await,
+        }
+    }
+}
+
+async fn sync(
+    config: &Config,
+    tracker: AnimeTracker,
+    title: &str,
+    episode: Option<(usize, usize)>,
+) -> anyhow::Result<()> {
+    let mut tokens = load_tokens()?;
+
+    let mut tracker = match tracker {
+        AnimeTracker::Anilist => {
+            Tracker::AniList(AniList::new(tokens.anilist.as_deref().ok_or_else(
+                || anyhow!("not logged in to AniList, run `lobster-rs --anime-login`"),
+            )?))
+        }
+        AnimeTracker::Myanimelist => Tracker::MyAnimeList(MyAnimeList::new(
+            config
+                .myanimelist_client_id
+                .as_deref()
+                .ok_or_else(|| anyhow!("`myanimelist_client_id` isn't set in the config"))?,
+            tokens.myanimelist.clone().ok_or_else(|| {
+                anyhow!("not logged in to MyAnimeList, run `lobster-rs --anime-login`")
+            })?,
+        )),
+    };
+
+    // Movies count as a single episode
+    let (season, progress) = episode.unwrap_or((1, 1));
+
+    let query = if season > 1 {
+        format!("{} Season {}", title, season)
+    } else {
+        title.to_string()
+    };
+
+    let results = tracker.search(&query, &mut tokens).await?;
+
+    let Some(entry) = find_match(results, title, season) else {
+        debug!("{} isn't an anime on {}", query, tracker.name());
+        return Ok(());
+    };
+
+    let progress = progress as u32;
+
+    if entry.progress.is_some_and(|watched| watched >= progress) {
+        debug!("{} already has {} episodes watched", query, progress);
+        return Ok(());
+    }
+
+    tracker
+        .update(entry.id, progress, entry.episodes == Some(progress))
+        .await?;
+
+    info!(
+        "Updated {} to episode {} on {}",
+        query,
+        progress,
+        tracker.name()
+    );
+
+    Ok(())
+}
+
+/// Updates the progress on the tracker from `anime_tracker` in the config after an
+/// episode (season and 1-based number) or a movie was watched to the end. Titles that
+/// aren't found as an anime are skipped, and failures only logged.
+pub async fn sync_progress(config: &Config, title: &str, episode: Option<(usize, usize)>) {
+    let Some(tracker) = config.anime_tracker else {
+        return;
+    };
+
+    if let Err(e) = sync(config, tracker, title, episode).await {
+        warn!("Failed to sync anime progress: {}", e);
+    }
+}
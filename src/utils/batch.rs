@@ -0,0 +1,48 @@
+use anyhow::Context;
+use serde::Deserialize;
+use std::{io::Read, path::Path};
+
+/// What to do with an entry of a `--batch` file.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchAction {
+    #[default]
+    Play,
+    /// Print the stream URL, like `--print-link`
+    Link,
+    Download,
+}
+
+/// One entry of a `--batch` file. Shows need the episode, either as `season` and
+/// `episode` or as `s03e07` at the end of the query.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchEntry {
+    #[serde(default)]
+    pub query: String,
+    /// Taken instead of the first search result, e.g. `tv/watch-the-office-39383`
+    pub id: Option<String>,
+    pub season: Option<usize>,
+    /// 1-based
+    pub episode: Option<usize>,
+    #[serde(default)]
+    pub action: BatchAction,
+    /// Falls back to `-q`, or the best one
+    pub quality: Option<u32>,
+    /// Where to download to, instead of the `download` directory from the config
+    pub dir: Option<String>,
+}
+
+/// Reads the entries of a `--batch` file, a JSON array. `-` reads them from stdin.
+pub fn load_batch(path: &Path) -> anyhow::Result<Vec<BatchEntry>> {
+    let contents = if path == Path::new("-") {
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    };
+
+    serde_json::from_str(&contents).context("Invalid batch file")
+}
@@ -1,24 +1,300 @@
-use crate::{Args, Languages, Provider};
-use anyhow::Context;
-use log::{debug, warn};
-use serde::{Deserialize, Serialize};
+use crate::{
+    utils::{
+        config_check::report_config_problems, i18n::UiLanguage, menu::Menu,
+        network::is_metered_connection, subtitles::LanguageTag, template::expand_command,
+    },
+    Args, ConfigCommand, Languages, Provider, Quality,
+};
+use anyhow::{anyhow, Context};
+use log::{debug, info, warn};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
 pub struct Config {
     pub use_external_menu: bool,
+    /// The external menu, rofi or one that works like dmenu
+    pub menu: Menu,
+    /// Any command that works like dmenu, used instead of `menu`
+    pub external_menu_command: Option<String>,
     pub download: String,
     pub provider: Provider,
-    pub subs_language: Languages,
+    #[serde(deserialize_with = "deserialize_languages")]
+    pub subs_language: Vec<LanguageTag>,
+    #[serde(deserialize_with = "deserialize_languages")]
+    pub subs_fallback: Vec<LanguageTag>,
     pub player: String,
+    pub player_command: Option<String>,
+    /// The DLNA renderer `player = "dlna"` casts to, matched by part of its name
+    pub dlna_renderer: Option<String>,
+    pub player_priority: Vec<String>,
     pub history: bool,
+    /// Where to keep the history instead of `history.json` in the data directory
+    pub histfile: Option<String>,
+    pub history_max_entries: Option<usize>,
+    pub history_max_age_days: Option<u64>,
+    pub history_sync: Option<HistorySync>,
+    pub completion_threshold: f32,
+    pub autoplay: bool,
+    pub smart_resume: bool,
     pub image_preview: bool,
+    /// Shows the synopsis, genres, rating and cast of the highlighted result in fzf
+    pub preview_details: bool,
+    pub subtitles: SubtitlesMode,
+    /// Takes the default answer of yes/no prompts, like `subtitles = "ask"`, instead of asking
+    pub skip_prompts: bool,
+    /// The language of the translated prompts and messages
+    pub ui_language: UiLanguage,
+    /// Older name for `subtitles = "never"`, still read from existing config files
+    #[serde(skip_serializing)]
     pub no_subs: bool,
     pub debug: bool,
+    /// Never play or download above this quality, e.g. in a profile for a slow connection
+    pub max_quality: Option<u32>,
+    pub max_quality_on_metered: u32,
+    pub opensubtitles_api_key: Option<String>,
+    /// The whisper.cpp binary `download --whisper` runs
+    pub whisper_command: String,
+    /// The ggml model file for whisper.cpp, e.g. `~/models/ggml-base.bin`
+    pub whisper_model: Option<String>,
+    /// Used to add TMDB's synopsis, rating, genres and poster to the previews, the Discord
+    /// presence and the `.nfo` files of downloads
+    pub tmdb_api_key: Option<String>,
+    pub player_restart_attempts: u32,
+    pub max_concurrent_requests: usize,
+    pub request_delay_ms: u64,
+    pub organize_downloads: bool,
+    pub torrent_indexer: Option<String>,
+    pub torrent_command: String,
+    pub title_max_width: usize,
+    pub sub_delay: Option<f32>,
+    pub sub_font_size: Option<u32>,
+    pub sub_scale: Option<f32>,
+    pub mpv_profile: Option<String>,
+    pub mpv_config_dir: Option<String>,
+    pub syncplay_server: Option<String>,
+    pub syncplay_room: Option<String>,
+    pub syncplay_username: Option<String>,
+    pub limits: Option<Limits>,
+    pub anime_tracker: Option<AnimeTracker>,
+    pub anilist_client_id: Option<String>,
+    pub myanimelist_client_id: Option<String>,
+    /// The editor `config edit` opens the config in, instead of `$EDITOR`
+    pub lobster_editor: Option<String>,
+    /// `[profile.<name>]` tables of settings that `--profile <name>` puts over the others
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, toml::Table>,
+}
+
+/// The `[history_sync]` table for sharing the history between devices.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct HistorySync {
+    /// A WebDAV address of the file, or `rclone:<remote>:<path>` to go through rclone
+    pub remote: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Where to sync the episode progress of anime to.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimeTracker {
+    Anilist,
+    Myanimelist,
+}
+
+/// Whether to play with subtitles, or ask every time they are available.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitlesMode {
+    #[default]
+    Always,
+    Never,
+    Ask,
+}
+
+/// The `[limits]` table for restricting when and how long can be watched. Keys that are
+/// left out aren't enforced.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Limits {
+    pub max_hours_per_day: Option<f32>,
+    /// Window like `08:00-20:00`
+    pub allowed_hours: Option<String>,
+    /// Lifts the limits for the rest of the session when entered
+    pub pin: Option<String>,
+}
+
+/// Set from `--profile` at startup, before the config is loaded
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Loads the config with the settings of `[profile.<name>]` over the others.
+pub fn set_profile(name: &str) {
+    let _ = PROFILE.set(name.to_string());
+}
+
+/// The profile set with `--profile` or `LOBSTER_PROFILE`, if any.
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+/// Puts the keys of `overlay` over those of `base`, going into tables so a profile can change
+/// a single key of one, e.g. `history_sync.remote`.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The config file's content with the settings of the profile from `--profile` applied.
+fn apply_profile(content: &str, name: &str) -> anyhow::Result<String> {
+    let mut config: toml::Table = toml::from_str(content).context("Failed to parse config.toml")?;
+
+    let profile = config
+        .get("profile")
+        .and_then(|profiles| profiles.get(name))
+        .and_then(|profile| profile.as_table())
+        .cloned()
+        .with_context(|| format!("There's no [profile.{}] in the config", name))?;
+
+    debug!(
+        "Applying profile {:?} to {:?}",
+        name,
+        profile.keys().collect::<Vec<_>>()
+    );
+    merge(&mut config, profile);
+
+    Ok(toml::to_string(&config)?)
+}
+
+/// An environment variable's value as TOML, so `true` and `["mpv", "vlc"]` work, or as a
+/// string when it isn't.
+fn parse_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Settings from `LOBSTER_<KEY>` environment variables, with `__` between a table and its
+/// keys, e.g. `LOBSTER_PLAYER=vlc` or `LOBSTER_LIMITS__PIN=1234`. `LOBSTER_DOWNLOAD_DIR` is
+/// `download`.
+fn env_overrides() -> toml::Table {
+    let mut overrides = toml::Table::new();
+
+    for (name, raw) in std::env::vars() {
+        // `LOBSTER_PROFILE` picks the profile, like `--profile`
+        let Some(key) = name
+            .strip_prefix("LOBSTER_")
+            .filter(|key| *key != "PROFILE")
+        else {
+            continue;
+        };
+
+        let key = match key {
+            "DOWNLOAD_DIR" => "download".to_string(),
+            key => key.to_lowercase().replace("__", "."),
+        };
+
+        let nest = |value| {
+            key.rsplit('.').fold(value, |value, part| {
+                toml::Value::Table(toml::Table::from_iter([(part.to_string(), value)]))
+            })
+        };
+
+        // Taken as text when the setting doesn't take the TOML, so `LOBSTER_LIMITS__PIN=1234`
+        // stays a string
+        let parsed = nest(parse_value(&raw));
+        let value = if parsed.clone().try_into::<Config>().is_ok() {
+            parsed
+        } else {
+            nest(toml::Value::String(raw))
+        };
+
+        debug!("Setting `{}` from {}", key, name);
+
+        if let toml::Value::Table(table) = value {
+            merge(&mut overrides, table);
+        }
+    }
+
+    overrides
+}
+
+/// The config file's content with the settings from the environment over its own.
+fn apply_env(content: String) -> anyhow::Result<String> {
+    let overrides = env_overrides();
+
+    if overrides.is_empty() {
+        return Ok(content);
+    }
+
+    let mut config: toml::Table =
+        toml::from_str(&content).context("Failed to parse config.toml")?;
+    merge(&mut config, overrides);
+
+    Ok(toml::to_string(&config)?)
+}
+
+/// Accepts either a single language or a priority list of languages.
+fn deserialize_languages<'de, D>(deserializer: D) -> Result<Vec<LanguageTag>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(LanguageTag),
+        Many(Vec<LanguageTag>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(language) => vec![language],
+        OneOrMany::Many(languages) => languages,
+    })
+}
+
+/// Expands a leading `~` and environment variables like `$XDG_STATE_HOME` or `${HOME}` in a
+/// path from the config. Variables that aren't set are left as they are.
+pub fn expand_path(path: &str) -> PathBuf {
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => match dirs::home_dir() {
+            Some(home) => format!("{}{}", home.display(), rest),
+            None => path.to_string(),
+        },
+        _ => path.to_string(),
+    };
+
+    let variable = Regex::new(r"\$\{(\w+)\}|\$(\w+)").unwrap();
+
+    variable
+        .replace_all(&path, |captures: &Captures| {
+            let name = captures.get(1).or(captures.get(2)).unwrap().as_str();
+            std::env::var(name).unwrap_or_else(|_| captures[0].to_string())
+        })
+        .into_owned()
+        .into()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
@@ -32,61 +308,131 @@ impl Config {
 
         Self {
             player: String::from("mpv"),
+            player_command: None,
+            dlna_renderer: None,
+            player_priority: vec![
+                String::from("mpv"),
+                String::from("vlc"),
+                String::from("celluloid"),
+            ],
             download: download_dir,
             provider: Provider::Vidcloud,
             history: false,
-            subs_language: Languages::English,
+            histfile: None,
+            history_max_entries: None,
+            history_max_age_days: None,
+            history_sync: None,
+            completion_threshold: 90.0,
+            autoplay: false,
+            smart_resume: false,
+            subs_language: vec![LanguageTag::from(Languages::English)],
+            subs_fallback: vec![],
             use_external_menu: false,
+            menu: Menu::Rofi,
+            external_menu_command: None,
             image_preview: false,
+            preview_details: false,
+            subtitles: SubtitlesMode::Always,
+            skip_prompts: false,
+            ui_language: UiLanguage::English,
             no_subs: false,
             debug: false,
+            max_quality: None,
+            max_quality_on_metered: 720,
+            opensubtitles_api_key: None,
+            whisper_command: String::from("whisper-cli"),
+            whisper_model: None,
+            tmdb_api_key: None,
+            player_restart_attempts: 3,
+            max_concurrent_requests: 4,
+            request_delay_ms: 0,
+            organize_downloads: false,
+            torrent_indexer: None,
+            torrent_command: String::from("webtorrent {magnet} --mpv"),
+            title_max_width: 0,
+            sub_delay: None,
+            sub_font_size: None,
+            sub_scale: None,
+            mpv_profile: None,
+            mpv_config_dir: None,
+            syncplay_server: None,
+            syncplay_room: None,
+            syncplay_username: None,
+            limits: None,
+            anime_tracker: None,
+            anilist_client_id: None,
+            myanimelist_client_id: None,
+            lobster_editor: None,
+            profile: BTreeMap::new(),
         }
     }
 
+    pub fn config_file() -> anyhow::Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to retrieve the config directory")?;
+
+        Ok(config_dir.join("lobster-rs/config.toml"))
+    }
+
     pub fn load_config() -> anyhow::Result<Self> {
         debug!("Loading configuration...");
-        let config_dir = dirs::config_dir().context("Failed to retrieve the config directory")?;
 
-        let config_path = format!("{}/lobster-rs/config.toml", config_dir.display());
+        let config_path = Config::config_file()?;
         debug!("Looking for config file at path: {:?}", config_path);
 
-        let config = Config::load_from_file(Path::new(&config_path))?;
+        let config = Config::load_from_file(&config_path)?;
         debug!("Configuration loaded successfully.");
         Ok(config)
     }
 
-    pub fn load_from_file(file_path: &Path) -> anyhow::Result<Self> {
-        if !file_path.exists() {
-            warn!(
-                "Config file not found at {:?}. Creating a default configuration.",
-                file_path
-            );
+    /// Writes a config file with the defaults at `file_path`.
+    fn create_default(file_path: &Path) -> anyhow::Result<()> {
+        warn!(
+            "Config file not found at {:?}. Creating a default configuration.",
+            file_path
+        );
 
-            let default_config = Config::new();
-            let content = toml::to_string(&default_config)
-                .with_context(|| "Failed to serialize the default configuration")?;
+        let default_config = Config::new();
+        let content = toml::to_string(&default_config)
+            .with_context(|| "Failed to serialize the default configuration")?;
 
-            if let Some(parent) = file_path.parent() {
-                debug!("Creating config directory: {:?}", parent);
-                fs::create_dir_all(parent)
-                    .with_context(|| format!("Failed to create config directory: {:?}", parent))?;
-            }
+        if let Some(parent) = file_path.parent() {
+            debug!("Creating config directory: {:?}", parent);
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create config directory: {:?}", parent))?;
+        }
+
+        let mut file = File::create(file_path)
+            .with_context(|| format!("Failed to create config file: {:?}", file_path))?;
 
-            let mut file = File::create(&file_path)
-                .with_context(|| format!("Failed to create config file: {:?}", file_path))?;
+        debug!("Writing default configuration to file.");
+        file.write_all(content.as_bytes())
+            .with_context(|| format!("Failed to write to config file: {:?}", file_path))?;
 
-            debug!("Writing default configuration to file.");
-            file.write_all(content.as_bytes())
-                .with_context(|| format!("Failed to write to config file: {:?}", file_path))?;
+        debug!("Default configuration created successfully.");
+        Ok(())
+    }
 
-            debug!("Default configuration created successfully.");
-            return Ok(default_config);
+    pub fn load_from_file(file_path: &Path) -> anyhow::Result<Self> {
+        if !file_path.exists() {
+            Config::create_default(file_path)?;
+
+            // A file that was only just made has no profiles
+            if let Some(name) = PROFILE.get() {
+                return Err(anyhow!("There's no [profile.{}] in the config", name));
+            }
         }
 
         debug!("Reading config file from {:?}", file_path);
         let content = fs::read_to_string(&file_path)
             .with_context(|| format!("Failed to read config file: {:?}", file_path))?;
 
+        let content = match PROFILE.get() {
+            Some(name) => apply_profile(&content, name)?,
+            None => content,
+        };
+
+        let content = apply_env(content)?;
+
         debug!("Parsing config file content.");
         toml::from_str(&content).context("Failed to parse config.toml")
     }
@@ -95,51 +441,90 @@ impl Config {
         debug!("Applying configuration to program arguments.");
 
         if cfg!(target_os = "linux") {
-            args.rofi = if !args.rofi {
+            args.media.rofi = if !args.media.rofi {
                 debug!("Setting `rofi` to {}", config.use_external_menu);
                 config.use_external_menu
             } else {
-                args.rofi
+                args.media.rofi
             };
 
-            match std::process::Command::new("rofi").arg("-v").output() {
-                Ok(_) => {}
-                Err(_) => {
-                    args.rofi = false;
+            // A command of the user's own isn't run just to see if it's there
+            if config.external_menu_command.is_none() {
+                match std::process::Command::new(config.menu.executable())
+                    .arg("-v")
+                    .output()
+                {
+                    Ok(_) => {}
+                    Err(_) => {
+                        args.media.rofi = false;
+                    }
                 }
             }
         } else {
             debug!("Disabling `rofi` as it is not supported on this OS.");
-            args.rofi = false;
+            args.media.rofi = false;
         }
 
-        args.image_preview = if !args.image_preview {
+        args.media.image_preview = if !args.media.image_preview {
             debug!("Setting `image_preview` to {}", config.image_preview);
             config.image_preview
         } else {
-            args.image_preview
+            args.media.image_preview
+        };
+
+        args.play.history = if !args.play.history && !args.play.no_history {
+            debug!("Setting `history` to {}", config.history);
+            config.history
+        } else {
+            args.play.history
+        };
+
+        if args.play.incognito {
+            // The anime tracker sync only runs when the history is saved
+            debug!("Incognito, not saving history or showing Discord presence");
+            args.play.history = false;
+            args.play.rpc = false;
+        }
+
+        if args.play.dry_run {
+            // Nothing is played, so there's nothing to save or show
+            debug!("Dry run, not saving history or showing Discord presence");
+            args.play.history = false;
+            args.play.rpc = false;
+        }
+
+        args.play.autoplay = if !args.play.autoplay {
+            debug!("Setting `autoplay` to {}", config.autoplay);
+            config.autoplay
+        } else {
+            args.play.autoplay
+        };
+
+        args.play.smart_resume = if !args.play.smart_resume {
+            debug!("Setting `smart_resume` to {}", config.smart_resume);
+            config.smart_resume
+        } else {
+            args.play.smart_resume
         };
 
-        args.no_subs = if !args.no_subs {
-            debug!("Setting `no_subs` to {}", config.no_subs);
-            config.no_subs
+        args.media.no_subs = if !args.media.subs && !args.media.no_subs {
+            let never = config.no_subs || config.subtitles == SubtitlesMode::Never;
+            debug!("Setting `no_subs` to {}", never);
+            never
         } else {
-            args.no_subs
+            args.media.no_subs
         };
 
         args.download = args.download.as_ref().map(|download| {
             if download.is_some() {
                 debug!("Using provided download directory: {:?}", download);
             } else {
-                warn!(
-                    "Provided download directory is empty. Using default download directory: {:?}",
-                    config.download
-                );
+                debug!("Using default download directory: {:?}", config.download);
             }
             Some(download.clone().unwrap_or_else(|| config.download.clone()))
         });
 
-        args.provider = Some(match &args.provider {
+        args.media.provider = Some(match &args.media.provider {
             Some(provider) => {
                 debug!("Using provided provider: {:?}", provider);
                 *provider
@@ -150,16 +535,55 @@ impl Config {
             }
         });
 
-        args.language = Some(match &args.language {
-            Some(language) => {
-                debug!("Using provided language: {:?}", language);
-                *language
-            }
-            None => {
-                debug!("Using default language: {:?}", config.subs_language);
-                config.subs_language
-            }
-        });
+        args.media.language = if args.media.language.is_empty() {
+            debug!("Using default languages: {:?}", config.subs_language);
+            config.subs_language.clone()
+        } else {
+            debug!("Using provided languages: {:?}", args.media.language);
+            args.media.language
+        };
+
+        if !args.media.metered && cfg!(target_os = "linux") {
+            args.media.metered = is_metered_connection();
+        }
+
+        let max_quality = [
+            config.max_quality,
+            args.media.metered.then_some(config.max_quality_on_metered),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+
+        if let Some(max_quality) = max_quality {
+            let max_quality = Quality::from_str(&max_quality.to_string()).unwrap_or(Quality::Q720);
+
+            args.media.quality = Some(match args.media.quality {
+                Some(quality) if quality.to_u32() <= max_quality.to_u32() => quality,
+                _ => {
+                    debug!("Capping quality to {}", max_quality);
+                    max_quality
+                }
+            });
+        }
+
+        if args.play.syncplay_server.is_none() {
+            debug!("Setting `syncplay_server` to {:?}", config.syncplay_server);
+            args.play.syncplay_server = config.syncplay_server.clone();
+        }
+
+        if args.play.syncplay_room.is_none() {
+            debug!("Setting `syncplay_room` to {:?}", config.syncplay_room);
+            args.play.syncplay_room = config.syncplay_room.clone();
+        }
+
+        if args.play.syncplay_username.is_none() {
+            debug!(
+                "Setting `syncplay_username` to {:?}",
+                config.syncplay_username
+            );
+            args.play.syncplay_username = config.syncplay_username.clone();
+        }
 
         args.debug = if !args.debug {
             debug!("Setting `debug` to {}", config.debug);
@@ -171,3 +595,190 @@ impl Config {
         args
     }
 }
+
+/// Runs `lobster-rs config`. Editing works without loading the config first, so a broken one
+/// can still be fixed.
+pub fn manage_config(command: &ConfigCommand) -> anyhow::Result<()> {
+    match command {
+        ConfigCommand::Edit => {
+            let config_file = Config::config_file()?;
+
+            if !config_file.exists() {
+                Config::create_default(&config_file)?;
+            }
+
+            let editor = editor(&config_file)?;
+            let Some((program, args)) = editor.split_first() else {
+                return Err(anyhow!("`lobster_editor` is empty"));
+            };
+
+            debug!("Opening the config with {:?}", editor);
+
+            std::process::Command::new(program)
+                .args(args)
+                .arg(&config_file)
+                .status()
+                .context("Failed to open config file with editor")?;
+
+            info!("Done editing config file.");
+            report_config_problems();
+        }
+        ConfigCommand::Path => println!("{}", Config::config_file()?.display()),
+        ConfigCommand::Show => print!("{}", toml::to_string(&Config::load_config()?)?),
+        ConfigCommand::Get { key } => {
+            let config = toml::Value::try_from(Config::load_config()?)?;
+
+            match lookup(&config, key) {
+                Some(toml::Value::String(value)) => println!("{}", value),
+                Some(value) => println!("{}", value),
+                None => return Err(anyhow!("`{}` isn't set or isn't a setting", key)),
+            }
+        }
+        ConfigCommand::Set { key, value } => set_value(key, value)?,
+        ConfigCommand::Check => {
+            if report_config_problems() {
+                return Err(anyhow!("The config has problems"));
+            }
+
+            info!("No problems found in the config.");
+        }
+    }
+
+    Ok(())
+}
+
+/// The command to edit the config with: `lobster_editor` from the config, `$VISUAL` or
+/// `$EDITOR`, and on Windows Notepad when none are set. The config is only read for
+/// `lobster_editor`, so a broken one can still be opened.
+fn editor(config_file: &Path) -> anyhow::Result<Vec<String>> {
+    let lobster_editor = fs::read_to_string(config_file).ok().and_then(|content| {
+        match toml::from_str::<toml::Table>(&content) {
+            Ok(config) => config.get("lobster_editor")?.as_str().map(String::from),
+            // The line is looked for on its own when the rest doesn't parse
+            Err(_) => Regex::new(r#"(?m)^\s*lobster_editor\s*=\s*["']([^"']*)["']"#)
+                .unwrap()
+                .captures(&content)
+                .map(|captures| captures[1].to_string()),
+        }
+    });
+
+    let editor = lobster_editor
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty());
+
+    match editor {
+        Some(editor) => Ok(expand_command(&editor, &[])),
+        None if cfg!(target_os = "windows") => Ok(vec!["notepad".to_string()]),
+        None => Err(anyhow!(
+            "No editor set, set `lobster_editor` in the config or the EDITOR environment variable"
+        )),
+    }
+}
+
+/// The value at a dotted key like `history_sync.remote`.
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+/// Puts `value` at the dotted `key` in the document, keeping the comment after the old value.
+fn insert_value(
+    document: &mut toml_edit::DocumentMut,
+    key: &str,
+    mut value: toml_edit::Value,
+) -> anyhow::Result<()> {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().unwrap_or_default();
+
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+
+    for part in parts {
+        table = table
+            .entry(part)
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` isn't a table", part))?;
+    }
+
+    // Replaced in place, as inserting would drop the comments above the key too
+    match table.get_mut(last) {
+        Some(toml_edit::Item::Value(old)) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        _ => {
+            table.insert(last, toml_edit::Item::Value(value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `lobster-rs config set`. The value is read as TOML when it can be, so `true`, `30` and
+/// `["mpv", "vlc"]` work, and as a string otherwise. Nothing is written unless the config
+/// still loads afterwards. With `--profile`, the setting is changed in that profile.
+fn set_value(key: &str, raw: &str) -> anyhow::Result<()> {
+    let config_file = Config::config_file()?;
+
+    if !config_file.exists() {
+        Config::create_default(&config_file)?;
+    }
+
+    let content = fs::read_to_string(&config_file)
+        .with_context(|| format!("Failed to read config file: {:?}", config_file))?;
+
+    let mut candidates = vec![toml_edit::Value::from(raw)];
+
+    if let Ok(value) = raw.parse::<toml_edit::Value>() {
+        if !value.is_str() {
+            candidates.insert(0, value);
+        }
+    }
+
+    let path = match PROFILE.get() {
+        Some(name) => format!("profile.{}.{}", name, key),
+        None => key.to_string(),
+    };
+
+    let mut error = String::new();
+
+    for value in candidates {
+        let mut document: toml_edit::DocumentMut =
+            content.parse().context("Failed to parse config.toml")?;
+
+        insert_value(&mut document, &path, value)?;
+
+        let updated = match PROFILE.get() {
+            Some(name) => apply_profile(&document.to_string(), name)?,
+            None => document.to_string(),
+        };
+
+        let config = match toml::from_str::<Config>(&updated) {
+            Ok(config) => config,
+            Err(e) => {
+                error = e.message().to_string();
+                continue;
+            }
+        };
+
+        // Keys the config doesn't have are ignored when loading, so they're caught here
+        if lookup(&toml::Value::try_from(config)?, key).is_none() {
+            return Err(anyhow!("`{}` isn't a setting", key));
+        }
+
+        fs::write(&config_file, document.to_string())
+            .with_context(|| format!("Failed to write to config file: {:?}", config_file))?;
+
+        info!("Set `{}` to {}", path, raw);
+
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "{:?} isn't a valid value for `{}`: {}",
+        raw,
+        key,
+        error
+    ))
+}
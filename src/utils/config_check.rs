@@ -0,0 +1,287 @@
+use crate::utils::config::{expand_path, Config, HistorySync, Limits};
+use log::warn;
+use regex::Regex;
+use serde::{
+    de::{self, Visitor},
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use std::path::Path;
+
+/// The players `player` and `player_priority` can name.
+const PLAYERS: [&str; 7] = ["mpv", "vlc", "iina", "celluloid", "syncplay", "custom", "dlna"];
+
+/// A deserializer that only records the field names of the struct it's asked for.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only structs have field names"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names are needed"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+/// The keys serde reads for `T`.
+fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// The closest of `candidates` to a misspelled `value`, if any is close enough to be meant.
+/// Jaro-Winkler finds cut off keys like `max_hour`, the edit distance swapped letters in
+/// short values like `vcl`.
+fn suggestion<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let value = value.to_lowercase();
+
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let candidate_lower = candidate.to_lowercase();
+            let jaro_winkler = strsim::jaro_winkler(&value, &candidate_lower);
+            let edits = strsim::normalized_damerau_levenshtein(&value, &candidate_lower);
+
+            (jaro_winkler > 0.8 || edits > 0.6).then_some((jaro_winkler + edits, candidate))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+fn did_you_mean<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match suggestion(value, candidates) {
+        Some(candidate) => format!(", did you mean `{}`?", candidate),
+        None => String::new(),
+    }
+}
+
+/// Explains why `value` can't be read for `key`, with the closest allowed value when serde
+/// lists them, as it does for the enums.
+fn invalid_value(key: &str, value: &toml::Value, error: &str) -> String {
+    let variants = Regex::new(r"unknown variant `([^`]*)`, expected (.*)").unwrap();
+
+    if let Some(captures) = variants.captures(error) {
+        let expected: Vec<&str> = captures
+            .get(2)
+            .map(|expected| expected.as_str())
+            .unwrap_or_default()
+            .split('`')
+            .skip(1)
+            .step_by(2)
+            .collect();
+
+        return format!(
+            "`{}` can't be {:?}, it's one of {}{}",
+            key,
+            &captures[1],
+            expected.join(", "),
+            did_you_mean(&captures[1], expected.iter().copied())
+        );
+    }
+
+    format!("`{}` can't be {}: {}", key, value, error)
+}
+
+/// Looks for unknown keys in one table of the config, `prefix` being where it is, e.g.
+/// `limits.`.
+fn check_keys(table: &toml::Table, prefix: &str, fields: &[&str], problems: &mut Vec<String>) {
+    for (key, value) in table {
+        let path = format!("{}{}", prefix, key);
+
+        if !fields.contains(&key.as_str()) {
+            problems.push(format!(
+                "Unknown key `{}`{}",
+                path,
+                did_you_mean(key, fields.iter().copied())
+            ));
+            continue;
+        }
+
+        let (nested, fields) = match (key.as_str(), value) {
+            ("history_sync", toml::Value::Table(nested)) => (nested, field_names::<HistorySync>()),
+            ("limits", toml::Value::Table(nested)) => (nested, field_names::<Limits>()),
+            _ => continue,
+        };
+
+        check_keys(nested, &format!("{}.", path), fields, problems);
+    }
+}
+
+/// Reads the values of the config one at a time, so every one that can't be read is
+/// reported on its own. The keys of `[history_sync]` and `[limits]` are read one by one too.
+fn check_values(table: &toml::Table, prefix: &str, problems: &mut Vec<String>) {
+    let fields = field_names::<Config>();
+
+    for (key, value) in table {
+        if !fields.contains(&key.as_str()) || key == "profile" {
+            continue;
+        }
+
+        let values: Vec<(String, toml::Value, &toml::Value)> = match value {
+            toml::Value::Table(nested) if matches!(key.as_str(), "history_sync" | "limits") => {
+                nested
+                    .iter()
+                    .map(|(nested_key, nested_value)| {
+                        let single =
+                            toml::Table::from_iter([(nested_key.clone(), nested_value.clone())]);
+
+                        (
+                            format!("{}{}.{}", prefix, key, nested_key),
+                            toml::Value::Table(single),
+                            nested_value,
+                        )
+                    })
+                    .collect()
+            }
+            _ => vec![(format!("{}{}", prefix, key), value.clone(), value)],
+        };
+
+        for (path, single, value) in values {
+            let single = toml::Table::from_iter([(key.clone(), single)]);
+
+            if let Err(e) = toml::Value::Table(single).try_into::<Config>() {
+                problems.push(invalid_value(&path, value, e.message()));
+            }
+        }
+
+        match (key.as_str(), value) {
+            ("player", toml::Value::String(player)) => {
+                check_player(&format!("{}{}", prefix, key), player, problems)
+            }
+            ("player_priority", toml::Value::Array(players)) => {
+                for player in players.iter().filter_map(|player| player.as_str()) {
+                    check_player(&format!("{}{}", prefix, key), player, problems);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_player(key: &str, player: &str, problems: &mut Vec<String>) {
+    if !PLAYERS.contains(&player.to_lowercase().as_str()) {
+        problems.push(format!(
+            "`{}` can't be {:?}, it's one of {}{}",
+            key,
+            player,
+            PLAYERS.join(", "),
+            did_you_mean(player, PLAYERS)
+        ));
+    }
+}
+
+/// Checks that the paths in a config that loaded exist.
+fn check_paths(config: &Config, problems: &mut Vec<String>) {
+    let mut directories = vec![("download", config.download.clone())];
+    directories.extend(
+        config
+            .mpv_config_dir
+            .iter()
+            .map(|dir| ("mpv_config_dir", dir.clone())),
+    );
+
+    for (key, dir) in directories {
+        if !expand_path(&dir).is_dir() {
+            problems.push(format!("`{}` is {:?}, which isn't a directory", key, dir));
+        }
+    }
+
+    if let Some(model) = &config.whisper_model {
+        if !expand_path(model).is_file() {
+            problems.push(format!("`whisper_model` is {:?}, which isn't a file", model));
+        }
+    }
+
+    if let Some(histfile) = &config.histfile {
+        let parent = expand_path(histfile)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        if !parent.as_os_str().is_empty() && !parent.is_dir() {
+            problems.push(format!(
+                "`histfile` is {:?}, but {} doesn't exist",
+                histfile,
+                parent.display()
+            ));
+        }
+    }
+
+    if config.player.eq_ignore_ascii_case("custom") && config.player_command.is_none() {
+        problems.push(r#"`player = "custom"` needs a `player_command`"#.to_string());
+    }
+}
+
+/// Looks for mistakes in a config file: unknown keys, values that can't be read, with the
+/// closest valid one when there's a fixed set, and paths that don't exist. Returns one line
+/// per problem, each saying what to change.
+pub fn check_config(content: &str) -> Vec<String> {
+    let table: toml::Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(e) => return vec![format!("config.toml isn't valid TOML: {}", e)],
+    };
+
+    let mut problems = vec![];
+
+    check_keys(&table, "", field_names::<Config>(), &mut problems);
+    check_values(&table, "", &mut problems);
+
+    let profiles = table
+        .get("profile")
+        .and_then(|profiles| profiles.as_table());
+
+    for (name, profile) in profiles.into_iter().flatten() {
+        match profile {
+            toml::Value::Table(profile) => {
+                let prefix = format!("profile.{}.", name);
+                check_keys(profile, &prefix, field_names::<Config>(), &mut problems);
+                check_values(profile, &prefix, &mut problems);
+            }
+            _ => problems.push(format!("`profile.{}` has to be a table", name)),
+        }
+    }
+
+    if let Ok(config) = toml::Value::Table(table).try_into::<Config>() {
+        check_paths(&config, &mut problems);
+    }
+
+    problems
+}
+
+/// Warns about the problems with the config file, returning whether there were any.
+pub fn report_config_problems() -> bool {
+    let Ok(config_file) = Config::config_file() else {
+        return false;
+    };
+
+    let Ok(content) = std::fs::read_to_string(&config_file) else {
+        return false;
+    };
+
+    let problems = check_config(&content);
+
+    if !problems.is_empty() {
+        warn!("Problems in {}:", config_file.display());
+
+        for problem in &problems {
+            warn!("  {}", problem);
+        }
+    }
+
+    !problems.is_empty()
+}
@@ -0,0 +1,230 @@
+use crate::flixhq::flixhq::{FlixHQ, FlixHQDetails};
+use crate::flixhq::ids::MediaId;
+use crate::providers::tmdb::{Tmdb, TmdbEntry, TmdbEpisode};
+use crate::utils::config::{profile, Config};
+use crate::{MediaType, SCRAPER_CLIENT};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+/// The details of a title, with what TMDB has on it when `tmdb_api_key` is set.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Details {
+    #[serde(flatten)]
+    pub flixhq: FlixHQDetails,
+    pub tmdb: Option<TmdbEntry>,
+}
+
+impl Details {
+    /// TMDB's synopsis, FlixHQ's being cut short at times.
+    pub fn description(&self) -> &str {
+        self.tmdb
+            .as_ref()
+            .map(|tmdb| tmdb.overview.as_str())
+            .filter(|overview| !overview.is_empty())
+            .unwrap_or(&self.flixhq.description)
+    }
+
+    pub fn genres(&self) -> &[String] {
+        self.tmdb
+            .as_ref()
+            .map(|tmdb| tmdb.genres.as_slice())
+            .filter(|genres| !genres.is_empty())
+            .unwrap_or(&self.flixhq.genres)
+    }
+
+    pub fn year(&self) -> Option<u32> {
+        self.flixhq.released.get(..4)?.parse().ok()
+    }
+
+    pub fn poster(&self) -> Option<&str> {
+        self.tmdb.as_ref()?.poster.as_deref()
+    }
+}
+
+/// Where the details of a title are kept once fetched, so moving back and forth in the
+/// menu doesn't fetch them again.
+fn details_file(media_id: &str) -> Option<PathBuf> {
+    Some(
+        dirs::cache_dir()?
+            .join("lobster-rs/details")
+            .join(format!("{}.json", media_id.replace('/', "-"))),
+    )
+}
+
+async fn find_on_tmdb(
+    media_id: &str,
+    details: &FlixHQDetails,
+    api_key: &str,
+) -> anyhow::Result<Option<TmdbEntry>> {
+    let media_type = media_id.parse::<MediaId>()?.media_type();
+    let year = details.released.get(..4).and_then(|year| year.parse().ok());
+
+    Tmdb::new(api_key)
+        .find(&details.title, media_type, year)
+        .await
+}
+
+/// The details of a title, from the cache if they were fetched before. They're looked up
+/// on TMDB too when there's an API key and it wasn't done yet.
+pub async fn load_details(media_id: &str, tmdb_api_key: Option<&str>) -> anyhow::Result<Details> {
+    let details_file = details_file(media_id);
+
+    let cached: Option<Details> = details_file
+        .as_ref()
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+
+    let tmdb_missing = |details: &Details| tmdb_api_key.is_some() && details.tmdb.is_none();
+
+    let mut details = match cached {
+        Some(details) if !tmdb_missing(&details) => {
+            debug!("Using cached details for {}", media_id);
+            return Ok(details);
+        }
+        Some(details) => details,
+        None => Details {
+            flixhq: FlixHQ::new(&*SCRAPER_CLIENT).details(media_id).await?,
+            tmdb: None,
+        },
+    };
+
+    if let Some(api_key) = tmdb_api_key {
+        match find_on_tmdb(media_id, &details.flixhq, api_key).await {
+            Ok(tmdb) => details.tmdb = tmdb,
+            Err(e) => debug!("Failed to look {} up on TMDB: {}", media_id, e),
+        }
+    }
+
+    if let Some(details_file) = details_file {
+        if let Some(parent) = details_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(details_file, serde_json::to_string(&details)?)?;
+    }
+
+    Ok(details)
+}
+
+/// TMDB's episodes of a season of a show, keyed by their episode number. Empty without
+/// `tmdb_api_key` or when TMDB doesn't know the show.
+pub async fn season_details(
+    media_id: &str,
+    season_number: usize,
+    tmdb_api_key: Option<&str>,
+) -> HashMap<usize, TmdbEpisode> {
+    let Some(api_key) = tmdb_api_key else {
+        return HashMap::new();
+    };
+
+    let episodes: anyhow::Result<Vec<TmdbEpisode>> = async {
+        let details = load_details(media_id, tmdb_api_key).await?;
+
+        match details.tmdb {
+            Some(tmdb) if tmdb.media_type == MediaType::Tv => {
+                Tmdb::new(api_key).season(tmdb.id, season_number).await
+            }
+            _ => Ok(vec![]),
+        }
+    }
+    .await;
+
+    match episodes {
+        Ok(episodes) => episodes
+            .into_iter()
+            .map(|episode| (episode.episode_number, episode))
+            .collect(),
+        Err(e) => {
+            debug!(
+                "No TMDB episodes for season {} of {}: {}",
+                season_number, media_id, e
+            );
+            HashMap::new()
+        }
+    }
+}
+
+/// Prints the synopsis, year, genres, rating and cast of a title for the fzf preview. Errors
+/// are printed too, the preview is the only place they'd be seen.
+pub async fn print_details(media_id: &str) {
+    let tmdb_api_key = Config::load_config()
+        .ok()
+        .and_then(|config| config.tmdb_api_key);
+
+    let details = match load_details(media_id, tmdb_api_key.as_deref()).await {
+        Ok(details) => details,
+        Err(e) => {
+            println!("Failed to load the details: {}", e);
+            return;
+        }
+    };
+
+    match details.year() {
+        Some(year) => println!("{} ({})", details.flixhq.title, year),
+        None => println!("{}", details.flixhq.title),
+    }
+
+    let facts: Vec<String> = [
+        details
+            .flixhq
+            .rating
+            .as_ref()
+            .map(|rating| format!("IMDb {}", rating)),
+        details
+            .tmdb
+            .as_ref()
+            .and_then(|tmdb| tmdb.rating)
+            .map(|rating| format!("TMDB {:.1}", rating)),
+        Some(details.flixhq.duration.clone()).filter(|duration| !duration.is_empty()),
+        Some(details.flixhq.country.join(", ")).filter(|country| !country.is_empty()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if !facts.is_empty() {
+        println!("{}", facts.join(" | "));
+    }
+
+    if !details.genres().is_empty() {
+        println!("{}", details.genres().join(", "));
+    }
+
+    if !details.description().is_empty() {
+        println!("\n{}", details.description());
+    }
+
+    if !details.flixhq.casts.is_empty() {
+        println!("\nCast: {}", details.flixhq.casts.join(", "));
+    }
+}
+
+/// TMDB's poster of a title, sharper than FlixHQ's.
+pub async fn tmdb_poster(media_id: &str, tmdb_api_key: Option<&str>) -> Option<String> {
+    tmdb_api_key?;
+
+    let details = load_details(media_id, tmdb_api_key)
+        .await
+        .inspect_err(|e| debug!("No details for {}: {}", media_id, e))
+        .ok()?;
+
+    details.poster().map(str::to_string)
+}
+
+/// The fzf preview command printing the details of the highlighted result, whose id is
+/// its second field.
+pub fn details_preview() -> String {
+    let executable = std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "lobster-rs".to_string());
+
+    // The preview reads the config on its own, from the same profile
+    match profile() {
+        Some(profile) => format!(
+            "\"{}\" --profile \"{}\" --preview-details {{2}}",
+            executable, profile
+        ),
+        None => format!("\"{}\" --preview-details {{2}}", executable),
+    }
+}
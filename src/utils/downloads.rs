@@ -0,0 +1,192 @@
+use log::debug;
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// A download running in the background, started with `--detach`.
+pub struct DownloadJob {
+    pub id: String,
+    pub pid: u32,
+    pub output_file: String,
+}
+
+impl DownloadJob {
+    pub fn new(output_file: String) -> Self {
+        Self {
+            id: chrono::Local::now().format("%Y%m%d%H%M%S%3f").to_string(),
+            pid: 0,
+            output_file,
+        }
+    }
+
+    /// File receiving ffmpeg's error output.
+    pub fn log_file(&self) -> PathBuf {
+        downloads_dir().join(format!("{}.log", self.id))
+    }
+
+    /// File receiving ffmpeg's `-progress` reports.
+    pub fn progress_file(&self) -> PathBuf {
+        downloads_dir().join(format!("{}.progress", self.id))
+    }
+}
+
+pub fn downloads_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .expect("Failed to find local dir")
+        .join("lobster-rs/downloads")
+}
+
+fn jobs_file() -> PathBuf {
+    downloads_dir().join("jobs.txt")
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+
+    if bytes >= 1e9 {
+        format!("{:.1} GB", bytes / 1e9)
+    } else {
+        format!("{:.0} MB", bytes / 1e6)
+    }
+}
+
+pub fn register_download(job: &DownloadJob) -> anyhow::Result<()> {
+    std::fs::create_dir_all(downloads_dir())?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(jobs_file())?;
+
+    writeln!(file, "{}\t{}\t{}", job.id, job.pid, job.output_file)?;
+
+    debug!("Registered background download {} (pid {})", job.id, job.pid);
+
+    Ok(())
+}
+
+fn read_jobs() -> anyhow::Result<Vec<DownloadJob>> {
+    let jobs_file = jobs_file();
+
+    if !jobs_file.exists() {
+        return Ok(vec![]);
+    }
+
+    let jobs = std::fs::read_to_string(jobs_file)?
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+
+            Some(DownloadJob {
+                id: fields.next()?.to_string(),
+                pid: fields.next()?.parse().ok()?,
+                output_file: fields.next()?.to_string(),
+            })
+        })
+        .collect();
+
+    Ok(jobs)
+}
+
+fn is_running(pid: u32) -> bool {
+    if cfg!(target_os = "windows") {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+            .unwrap_or(false)
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// Latest value of every key in an ffmpeg `-progress` report.
+fn read_progress(job: &DownloadJob) -> HashMap<String, String> {
+    std::fs::read_to_string(job.progress_file())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn last_error(job: &DownloadJob) -> Option<String> {
+    std::fs::read_to_string(job.log_file())
+        .ok()?
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| line.trim().to_string())
+}
+
+/// Describes every background download, forgetting the ones that are no longer running
+/// once they have been reported.
+pub fn download_status() -> anyhow::Result<String> {
+    let jobs = read_jobs()?;
+
+    if jobs.is_empty() {
+        return Ok(String::from("No background downloads.\n"));
+    }
+
+    let mut status = String::new();
+    let mut running_jobs = vec![];
+
+    for job in jobs {
+        let progress = read_progress(&job);
+        let running = is_running(job.pid);
+
+        let downloaded = progress
+            .get("out_time")
+            .map(|out_time| out_time.split('.').next().unwrap_or(out_time).to_string())
+            .unwrap_or_else(|| String::from("00:00:00"));
+
+        let size = progress
+            .get("total_size")
+            .and_then(|total_size| total_size.parse().ok())
+            .map(format_size)
+            .unwrap_or_else(|| format_size(0));
+
+        let state = if running {
+            match progress.get("speed") {
+                Some(speed) => format!("downloading at {}", speed),
+                None => String::from("downloading"),
+            }
+        } else if let Some(error) = last_error(&job) {
+            format!("failed: {}", error)
+        } else if progress.get("progress").map(String::as_str) == Some("end") {
+            String::from("finished")
+        } else {
+            String::from("stopped")
+        };
+
+        status.push_str(&format!(
+            "{}\n    {}, {} of video ({})\n",
+            job.output_file, state, downloaded, size
+        ));
+
+        if running {
+            running_jobs.push(job);
+        } else {
+            let _ = std::fs::remove_file(job.log_file());
+            let _ = std::fs::remove_file(job.progress_file());
+        }
+    }
+
+    let remaining: String = running_jobs
+        .iter()
+        .map(|job| format!("{}\t{}\t{}\n", job.id, job.pid, job.output_file))
+        .collect();
+
+    std::fs::write(jobs_file(), remaining)?;
+
+    Ok(status)
+}
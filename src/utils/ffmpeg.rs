@@ -1,6 +1,12 @@
-use std::sync::{atomic::AtomicBool, Arc};
+use std::{
+    fs::File,
+    path::Path,
+    process::{Child, Stdio},
+    sync::{atomic::AtomicBool, Arc},
+};
 
 use crate::utils::SpawnError;
+use lobster_core::redact::redact;
 use log::{debug, error};
 
 pub struct Ffmpeg {
@@ -27,16 +33,22 @@ pub struct FfmpegArgs<'a> {
     pub subtitle_files: Option<&'a Vec<String>>,
     pub subtitle_language: Option<String>,
     pub codec: Option<String>,
+    pub progress: Option<String>,
 }
 
 pub trait FfmpegSpawn {
     fn embed_video(&self, args: FfmpegArgs) -> Result<(), SpawnError>;
+    fn spawn_video(&self, args: FfmpegArgs) -> Result<Child, SpawnError>;
+    fn embed_video_detached(
+        &self,
+        args: FfmpegArgs,
+        log_file: &Path,
+        progress_file: &Path,
+    ) -> Result<u32, SpawnError>;
 }
 
-impl FfmpegSpawn for Ffmpeg {
-    fn embed_video(&self, args: FfmpegArgs) -> Result<(), SpawnError> {
-        debug!("Starting embed_video with input file: {}", args.input_file);
-
+impl Ffmpeg {
+    fn build_args(&self, args: &FfmpegArgs) -> Vec<String> {
         let mut temp_args = self.args.clone();
         temp_args.push("-i".to_string());
         temp_args.push(args.input_file.to_owned());
@@ -46,6 +58,12 @@ impl FfmpegSpawn for Ffmpeg {
             temp_args.push("-stats".to_string());
         }
 
+        if let Some(progress) = &args.progress {
+            debug!("Writing progress to: {}", progress);
+            temp_args.push("-progress".to_string());
+            temp_args.push(progress.to_owned());
+        }
+
         if let Some(log_level) = &args.log_level {
             debug!("Setting log level to: {}", log_level);
             temp_args.push("-loglevel".to_string());
@@ -58,7 +76,7 @@ impl FfmpegSpawn for Ffmpeg {
 
             if subtitle_count > 1 {
                 for subtitle_file in subtitle_files {
-                    debug!("Adding subtitle file: {}", subtitle_file);
+                    debug!("Adding subtitle file: {}", redact(subtitle_file));
                     temp_args.push("-i".to_string());
                     temp_args.push(subtitle_file.to_string());
                 }
@@ -72,15 +90,14 @@ impl FfmpegSpawn for Ffmpeg {
 
                 temp_args.extend("-c:v copy -c:a copy -c:s srt".split(" ").map(String::from));
 
-                for i in 1..=subtitle_count {
-                    let metadata = format!(
-                        "-metadata:s:s:{} language={}_{}",
-                        i - 1,
-                        args.subtitle_language.as_deref().unwrap_or("English"),
-                        i
+                for i in 0..subtitle_count {
+                    let language = format!(
+                        "language={}",
+                        args.subtitle_language.as_deref().unwrap_or("eng")
                     );
-                    debug!("Adding metadata: {}", metadata);
-                    temp_args.push(metadata);
+                    debug!("Adding metadata for subtitle {}: {}", i, language);
+                    temp_args.push(format!("-metadata:s:s:{}", i));
+                    temp_args.push(language);
                 }
             } else {
                 temp_args.push("-i".to_string());
@@ -89,7 +106,7 @@ impl FfmpegSpawn for Ffmpeg {
                 temp_args.push("-metadata:s:s:0".to_string());
                 let language = format!(
                     "language={}",
-                    args.subtitle_language.as_deref().unwrap_or("English")
+                    args.subtitle_language.as_deref().unwrap_or("eng")
                 );
                 debug!("Adding single subtitle metadata: {}", language);
                 temp_args.push(language);
@@ -105,9 +122,23 @@ impl FfmpegSpawn for Ffmpeg {
         temp_args.push(args.output_file.to_owned());
         debug!("Output file set to: {}", args.output_file);
 
+        temp_args
+    }
+}
+
+impl FfmpegSpawn for Ffmpeg {
+    fn embed_video(&self, args: FfmpegArgs) -> Result<(), SpawnError> {
+        debug!(
+            "Starting embed_video with input file: {}",
+            redact(&args.input_file)
+        );
+
+        let temp_args = self.build_args(&args);
+
         debug!(
-            "Executing ffmpeg command: {} {:?}",
-            self.executable, temp_args
+            "Executing ffmpeg command: {} {}",
+            self.executable,
+            redact(&format!("{:?}", temp_args))
         );
 
         let running = Arc::new(AtomicBool::new(true));
@@ -136,4 +167,79 @@ impl FfmpegSpawn for Ffmpeg {
 
         Ok(())
     }
+
+    fn spawn_video(&self, args: FfmpegArgs) -> Result<Child, SpawnError> {
+        debug!("Spawning ffmpeg with input file: {}", redact(&args.input_file));
+
+        let temp_args = self.build_args(&args);
+
+        debug!(
+            "Executing ffmpeg command: {} {}",
+            self.executable,
+            redact(&format!("{:?}", temp_args))
+        );
+
+        std::process::Command::new(&self.executable)
+            .args(temp_args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                error!("Error executing ffmpeg command: {}", e);
+                SpawnError::IOError(e)
+            })
+    }
+
+    fn embed_video_detached(
+        &self,
+        mut args: FfmpegArgs,
+        log_file: &Path,
+        progress_file: &Path,
+    ) -> Result<u32, SpawnError> {
+        debug!(
+            "Starting detached embed_video with input file: {}",
+            redact(&args.input_file)
+        );
+
+        args.progress = Some(progress_file.display().to_string());
+
+        let mut temp_args = vec!["-nostdin".to_string()];
+        temp_args.extend(self.build_args(&args));
+
+        debug!(
+            "Executing detached ffmpeg command: {} {}",
+            self.executable,
+            redact(&format!("{:?}", temp_args))
+        );
+
+        let log = File::create(log_file).map_err(SpawnError::IOError)?;
+
+        let mut command = std::process::Command::new(&self.executable);
+        command
+            .args(temp_args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(log);
+
+        // Keep ffmpeg out of the terminal's process group so closing the terminal doesn't hang it up
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const DETACHED_PROCESS: u32 = 0x00000008;
+            const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+            command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+        }
+
+        let child = command.spawn().map_err(|e| {
+            error!("Error executing ffmpeg command: {}", e);
+            SpawnError::IOError(e)
+        })?;
+
+        Ok(child.id())
+    }
 }
@@ -30,6 +30,13 @@ pub struct FzfArgs {
     pub prompt: Option<String>,
     pub delimiter: Option<String>,
     pub preview_window: Option<String>,
+    pub with_shell: Option<String>,
+    /// Lets several lines be marked with Tab, which are then printed one per line
+    pub multi: bool,
+    /// Keys that pick the highlighted line like Enter does, e.g. `ctrl-d,ctrl-s`
+    pub expect: Option<String>,
+    /// Which of the `expect` keys the line was picked with, filled in by the launcher
+    pub pressed: Option<String>,
 }
 
 pub trait FzfSpawn {
@@ -90,6 +97,21 @@ impl FzfSpawn for Fzf {
             temp_args.push(format!("--preview-window={}", preview_window));
         }
 
+        if let Some(with_shell) = &args.with_shell {
+            debug!("Setting with-shell: {}", with_shell);
+            temp_args.push(format!("--with-shell={}", with_shell));
+        }
+
+        if args.multi {
+            debug!("Adding multi flag.");
+            temp_args.push("--multi".to_string());
+        }
+
+        if let Some(expect) = &args.expect {
+            debug!("Setting expect: {}", expect);
+            temp_args.push(format!("--expect={}", expect));
+        }
+
         let mut command = std::process::Command::new(&self.executable);
         command.args(&temp_args);
 
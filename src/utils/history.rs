@@ -1,18 +1,813 @@
-use crate::flixhq::flixhq::FlixHQEpisode;
-use anyhow::anyhow;
+use crate::flixhq::{
+    flixhq::{FlixHQ, FlixHQSeason},
+    ids::MediaId,
+};
+use crate::{
+    utils::config::expand_path, HistoryCommand, HistoryFormat, MediaType, CLIENT, SCRAPER_CLIENT,
+};
+use anyhow::{anyhow, Context};
+use lobster_core::playlist;
+use log::{debug, info, warn};
 use reqwest::Client;
-use std::fs::OpenOptions;
-use std::io::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-pub async fn save_progress(url: String) -> anyhow::Result<(String, f32)> {
-    let watchlater_dir = std::path::PathBuf::new().join(format!(
-        "{}/lobster-rs/watchlater",
-        std::env::temp_dir().display()
-    ));
+/// Where a movie or show was left off.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub title: String,
+    pub media_id: String,
+    pub image: String,
+    /// Seconds into the movie or episode
+    pub position: f32,
+    /// Percentage of the movie or episode watched, when the player reported its length
+    #[serde(default)]
+    pub progress: Option<f32>,
+    /// The episode to continue with, for shows
+    pub episode: Option<HistoryEpisode>,
+    /// Whether the previous episode was watched to the end, so nothing of `episode` has
+    /// been seen yet
+    #[serde(default)]
+    pub watched: bool,
+    /// Unix timestamp of the last save, 0 for entries saved before it was recorded
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEpisode {
+    pub id: String,
+    pub season: usize,
+    /// Title like "Eps 5: Pilot"
+    pub title: String,
+}
+
+impl HistoryEntry {
+    /// Parses a line of the old tab separated history file. Movies had 4 fields (title,
+    /// position, media id, image) and shows 7 (title, position, media id, episode id,
+    /// season, episode title, image).
+    fn from_tsv(line: &str) -> Option<Self> {
+        let fields = line.split('\t').collect::<Vec<&str>>();
+
+        let (episode, image) = match fields.as_slice() {
+            [_, _, _, image] => (None, image),
+            [_, _, _, episode_id, season, episode_title, image] => (
+                Some(HistoryEpisode {
+                    id: episode_id.to_string(),
+                    season: season.parse().ok()?,
+                    title: episode_title.to_string(),
+                }),
+                image,
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            title: fields[0].to_string(),
+            media_id: fields[2].to_string(),
+            image: image.to_string(),
+            position: position_seconds(fields[1]).unwrap_or(0.0),
+            progress: None,
+            episode,
+            watched: false,
+            updated_at: 0,
+        })
+    }
+}
+
+/// A movie or show that was removed from the history, kept for a while so merging with the
+/// history of another device doesn't bring it back.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Removal {
+    pub media_id: String,
+    /// Unix timestamp
+    pub removed_at: u64,
+    /// Whether it was removed by hand rather than finished, which deletes its sessions as
+    /// well
+    #[serde(default)]
+    pub forgotten: bool,
+}
+
+/// A playback of a movie or episode, kept for `--stats` after its entry is finished.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub media_id: String,
+    pub title: String,
+    /// Unix timestamp
+    pub watched_at: u64,
+}
+
+/// How long removals are remembered.
+const REMOVAL_DAYS: u64 = 90;
+
+/// Version of the `history.json` layout, bumped whenever it changes. Version 0 is the old
+/// `lobster_history.txt`, version 1 a plain list of entries without a header, version 2
+/// didn't have `removed` yet and version 3 didn't have `sessions`.
+pub const HISTORY_VERSION: u32 = 4;
+
+#[derive(Serialize)]
+struct HistoryFile<'a> {
+    version: u32,
+    entries: &'a [HistoryEntry],
+    removed: &'a [Removal],
+    sessions: &'a [Session],
+}
+
+/// The contents of a history file.
+#[derive(Deserialize, Debug, Default)]
+pub struct HistoryData {
+    pub entries: Vec<HistoryEntry>,
+    pub removed: Vec<Removal>,
+    pub sessions: Vec<Session>,
+}
+
+/// Upgrades the contents of `history.json` to [`HISTORY_VERSION`] one version at a time,
+/// returning them and the version the file was at.
+pub fn migrate(mut history: serde_json::Value) -> anyhow::Result<(HistoryData, u32)> {
+    let from_version = match &history {
+        serde_json::Value::Array(_) => 1,
+        _ => history
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or_else(|| anyhow!("missing the version header"))? as u32,
+    };
+
+    if from_version > HISTORY_VERSION {
+        return Err(anyhow!(
+            "history version {} is newer than this lobster-rs supports ({}), please update",
+            from_version,
+            HISTORY_VERSION
+        ));
+    }
+
+    for version in from_version..HISTORY_VERSION {
+        debug!("Migrating history from version {}", version);
+
+        history = match version {
+            1 => serde_json::json!({ "version": 2, "entries": history }),
+            2 => {
+                history["version"] = 3.into();
+                history["removed"] = serde_json::json!([]);
+                history
+            }
+            3 => {
+                history["version"] = 4.into();
+                history["sessions"] = serde_json::json!([]);
+                history
+            }
+            _ => unreachable!("no migration from history version {}", version),
+        };
+    }
+
+    Ok((serde_json::from_value(history)?, from_version))
+}
+
+/// A history entry as a CSV row, with the episode spread over its own columns.
+#[derive(Serialize, Deserialize)]
+struct CsvEntry {
+    title: String,
+    media_id: String,
+    image: String,
+    position: f32,
+    season: Option<usize>,
+    episode_id: Option<String>,
+    episode_title: Option<String>,
+    #[serde(default)]
+    watched: bool,
+    #[serde(default)]
+    updated_at: u64,
+}
+
+impl From<&HistoryEntry> for CsvEntry {
+    fn from(entry: &HistoryEntry) -> Self {
+        Self {
+            title: entry.title.clone(),
+            media_id: entry.media_id.clone(),
+            image: entry.image.clone(),
+            position: entry.position,
+            season: entry.episode.as_ref().map(|episode| episode.season),
+            episode_id: entry.episode.as_ref().map(|episode| episode.id.clone()),
+            episode_title: entry.episode.as_ref().map(|episode| episode.title.clone()),
+            watched: entry.watched,
+            updated_at: entry.updated_at,
+        }
+    }
+}
+
+impl TryFrom<CsvEntry> for HistoryEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(row: CsvEntry) -> anyhow::Result<Self> {
+        let episode = match (row.season, row.episode_id, row.episode_title) {
+            (Some(season), Some(id), Some(title)) => Some(HistoryEpisode { id, season, title }),
+            (None, None, None) => None,
+            _ => {
+                return Err(anyhow!(
+                    "{} needs all of season, episode_id and episode_title, or none of them",
+                    row.title
+                ))
+            }
+        };
+
+        Ok(Self {
+            title: row.title,
+            media_id: row.media_id,
+            image: row.image,
+            position: row.position,
+            progress: None,
+            episode,
+            watched: row.watched,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// The watch history, one entry per movie or show, kept as JSON in the local data
+/// directory.
+pub struct Store {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+    removed: Vec<Removal>,
+    sessions: Vec<Session>,
+    /// Held by [`Store::load_for_update`] until the store is dropped
+    lock: Option<File>,
+}
+
+/// Set from `histfile` in the config at startup
+static HISTFILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keeps the history at `histfile` from the config instead of in the data directory.
+pub fn set_history_file(histfile: &str) {
+    let _ = HISTFILE.set(expand_path(histfile));
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `history.json.lock`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+impl Store {
+    fn data_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .expect("Failed to find local dir")
+            .join("lobster-rs")
+    }
+
+    fn history_file() -> PathBuf {
+        HISTFILE
+            .get()
+            .cloned()
+            .unwrap_or_else(|| Self::data_dir().join("history.json"))
+    }
+
+    /// Takes the advisory lock that keeps two lobster-rs sessions from changing the history
+    /// at the same time, waiting for the other one to finish.
+    fn lock() -> anyhow::Result<File> {
+        let history_file = Self::history_file();
+
+        if let Some(parent) = history_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(with_suffix(&history_file, ".lock"))?;
+
+        if lock.try_lock().is_err() {
+            debug!("Waiting for another lobster-rs to finish writing the history");
+            lock.lock()?;
+        }
+
+        Ok(lock)
+    }
+
+    /// Loads the history for reading.
+    pub fn load() -> anyhow::Result<Self> {
+        let mut store = Self::load_for_update()?;
+        store.lock = None;
+
+        Ok(store)
+    }
+
+    /// Loads the history and keeps other sessions from changing it until the store is
+    /// dropped, so nothing they save in between is overwritten by [`Store::save`].
+    ///
+    /// A history written by an older release is upgraded to [`HISTORY_VERSION`] first. The
+    /// old file is kept next to it with a `.bak` extension, for the `lobster_history.txt` of
+    /// the first releases as `lobster_history.txt.bak`.
+    pub fn load_for_update() -> anyhow::Result<Self> {
+        let lock = Some(Self::lock()?);
+        let path = Self::history_file();
 
-    let mut durations: Vec<f32> = vec![];
+        if path.exists() {
+            debug!("Reading history from {:?}", path);
+            let (history, from_version) = serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(anyhow::Error::from)
+                .and_then(migrate)
+                .with_context(|| format!("Failed to read {:?}", path))?;
 
-    let re = regex::Regex::new(r#"#EXTINF:([0-9]*\.?[0-9]+),"#).unwrap();
+            let mut store = Self {
+                path,
+                entries: history.entries,
+                removed: history.removed,
+                sessions: history.sessions,
+                lock,
+            };
+
+            if from_version < HISTORY_VERSION {
+                store.import_watch_log()?;
+
+                let backup = with_suffix(&store.path, &format!(".v{}.bak", from_version));
+                std::fs::copy(&store.path, &backup)?;
+                store.save()?;
+
+                info!(
+                    "Upgraded the history from version {} to {}, the old file is kept as {:?}",
+                    from_version, HISTORY_VERSION, backup
+                );
+            }
+
+            return Ok(store);
+        }
+
+        let mut store = Self {
+            path,
+            entries: vec![],
+            removed: vec![],
+            sessions: vec![],
+            lock,
+        };
+
+        if store.import_watch_log()? {
+            store.save()?;
+        }
+
+        let legacy_file = Self::data_dir().join("lobster_history.txt");
+
+        if legacy_file.exists() {
+            for line in std::fs::read_to_string(&legacy_file)?.lines() {
+                match HistoryEntry::from_tsv(line) {
+                    Some(entry) => store.upsert(entry),
+                    None if line.is_empty() => {}
+                    None => warn!("Skipping history entry: {}", line),
+                }
+            }
+
+            store.save()?;
+            std::fs::rename(&legacy_file, legacy_file.with_extension("txt.bak"))?;
+
+            info!(
+                "Moved {} entries from lobster_history.txt to {:?}",
+                store.entries.len(),
+                store.path
+            );
+        }
+
+        Ok(store)
+    }
+
+    /// Deletes the history, including a leftover `lobster_history.txt` or
+    /// `lobster_watchlog.txt`.
+    pub fn clear() -> anyhow::Result<()> {
+        let _lock = Self::lock()?;
+
+        for path in [
+            Self::history_file(),
+            Self::data_dir().join("lobster_history.txt"),
+            Self::data_dir().join("lobster_watchlog.txt"),
+        ] {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the history to a temporary file that then replaces `history.json`, so a
+    /// session reading it at the same time never sees half of it.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let temp_file = with_suffix(&self.path, ".tmp");
+
+        std::fs::write(&temp_file, self.export(HistoryFormat::Json)?)?;
+        std::fs::rename(temp_file, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Moves the sessions of the `lobster_watchlog.txt` that versions before 4 kept next to
+    /// the history into it, keeping the log as `lobster_watchlog.txt.bak`. Returns whether
+    /// there was one.
+    fn import_watch_log(&mut self) -> anyhow::Result<bool> {
+        let watch_log = Self::data_dir().join("lobster_watchlog.txt");
+
+        if !watch_log.exists() {
+            return Ok(false);
+        }
+
+        for line in std::fs::read_to_string(&watch_log)?.lines() {
+            let mut fields = line.splitn(3, '\t');
+
+            let (Some(Ok(watched_at)), Some(media_id), Some(title)) = (
+                fields.next().map(str::parse::<u64>),
+                fields.next(),
+                fields.next(),
+            ) else {
+                continue;
+            };
+
+            self.sessions.push(Session {
+                media_id: media_id.to_string(),
+                title: title.to_string(),
+                watched_at,
+            });
+        }
+
+        std::fs::rename(&watch_log, watch_log.with_extension("txt.bak"))?;
+        info!("Moved {} sessions from lobster_watchlog.txt", self.sessions.len());
+
+        Ok(true)
+    }
+
+    /// The history in an exchange format. JSON is the same as `history.json`.
+    pub fn export(&self, format: HistoryFormat) -> anyhow::Result<String> {
+        match format {
+            HistoryFormat::Json => Ok(serde_json::to_string_pretty(&HistoryFile {
+                version: HISTORY_VERSION,
+                entries: &self.entries,
+                removed: &self.removed,
+                sessions: &self.sessions,
+            })?),
+            HistoryFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(vec![]);
+
+                for entry in &self.entries {
+                    writer.serialize(CsvEntry::from(entry))?;
+                }
+
+                Ok(String::from_utf8(writer.into_inner()?)?)
+            }
+        }
+    }
+
+    /// Reads entries written by [`Store::export`], JSON from any history version.
+    pub fn parse_export(
+        contents: &str,
+        format: HistoryFormat,
+    ) -> anyhow::Result<Vec<HistoryEntry>> {
+        match format {
+            HistoryFormat::Json => Ok(migrate(serde_json::from_str(contents)?)?.0.entries),
+            HistoryFormat::Csv => csv::Reader::from_reader(contents.as_bytes())
+                .deserialize::<CsvEntry>()
+                .map(|row| HistoryEntry::try_from(row?))
+                .collect(),
+        }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// Records that a movie or episode of `media_id` was just played.
+    pub fn add_session(&mut self, media_id: &str, title: &str) {
+        self.sessions.push(Session {
+            media_id: media_id.to_string(),
+            title: title.to_string(),
+            watched_at: unix_time(),
+        });
+    }
+
+    pub fn get(&self, media_id: &str) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.media_id == media_id)
+    }
+
+    /// Adds an entry, replacing the one for the same movie or show.
+    pub fn upsert(&mut self, entry: HistoryEntry) {
+        self.entries.retain(|old| old.media_id != entry.media_id);
+        self.removed
+            .retain(|removal| removal.media_id != entry.media_id);
+        self.entries.push(entry);
+    }
+
+    /// Removes the entry for a movie or show and remembers when, see [`Removal`]. Its
+    /// sessions are deleted as well.
+    pub fn remove(&mut self, media_id: &str) {
+        self.remove_at(media_id, unix_time(), true);
+    }
+
+    /// Removes the entry for a movie or show that was watched to the end, keeping its
+    /// sessions.
+    pub fn remove_finished(&mut self, media_id: &str) {
+        self.remove_at(media_id, unix_time(), false);
+    }
+
+    fn remove_at(&mut self, media_id: &str, removed_at: u64, forgotten: bool) {
+        self.entries.retain(|entry| entry.media_id != media_id);
+        self.removed.retain(|removal| removal.media_id != media_id);
+        self.removed.push(Removal {
+            media_id: media_id.to_string(),
+            removed_at,
+            forgotten,
+        });
+
+        if forgotten {
+            self.sessions.retain(|session| {
+                session.media_id != media_id || session.watched_at > removed_at
+            });
+        }
+
+        let oldest = unix_time().saturating_sub(REMOVAL_DAYS * 24 * 60 * 60);
+        self.removed.retain(|removal| removal.removed_at >= oldest);
+    }
+
+    fn removed_at(&self, media_id: &str) -> Option<u64> {
+        self.removed
+            .iter()
+            .find(|removal| removal.media_id == media_id)
+            .map(|removal| removal.removed_at)
+    }
+
+    /// Merges the history of another device into this one. For every movie or show the
+    /// latest change wins, whether it was saved or removed.
+    pub fn merge(&mut self, other: HistoryData) {
+        for entry in other.entries {
+            let newer = |changed_at: Option<u64>| {
+                changed_at.is_none_or(|changed_at| entry.updated_at > changed_at)
+            };
+
+            if newer(self.get(&entry.media_id).map(|local| local.updated_at))
+                && newer(self.removed_at(&entry.media_id))
+            {
+                self.upsert(entry);
+            }
+        }
+
+        for removal in other.removed {
+            let local = self.get(&removal.media_id).map(|local| local.updated_at);
+            let removed_at = self.removed_at(&removal.media_id);
+
+            if local.is_none_or(|updated_at| updated_at < removal.removed_at)
+                && removed_at.is_none_or(|removed_at| removed_at < removal.removed_at)
+            {
+                self.remove_at(&removal.media_id, removal.removed_at, removal.forgotten);
+            }
+        }
+
+        // Sessions are only ever added, unless their movie or show was removed by hand since
+        for session in other.sessions {
+            let forgotten = self.removed.iter().any(|removal| {
+                removal.forgotten
+                    && removal.media_id == session.media_id
+                    && removal.removed_at >= session.watched_at
+            });
+
+            if !forgotten && !self.sessions.contains(&session) {
+                self.sessions.push(session);
+            }
+        }
+
+        self.sessions.sort_by_key(|session| session.watched_at);
+    }
+
+    /// Removes the oldest completed entries beyond `max_entries` of them, then the completed
+    /// ones not saved in the last `max_age_days`. Entries with something left to resume are
+    /// never pruned. Entries without a timestamp count as the oldest, and for the age they
+    /// get the current time the first time, so they are aged from then on. Returns how many
+    /// entries were removed.
+    pub fn prune(&mut self, max_entries: Option<usize>, max_age_days: Option<u64>) -> usize {
+        let mut pruned: Vec<String> = vec![];
+        let now = unix_time();
+
+        if let Some(max_entries) = max_entries {
+            let mut completed: Vec<&HistoryEntry> =
+                self.entries.iter().filter(|entry| entry.watched).collect();
+
+            // Stable, so entries saved at the same time keep their order
+            completed.sort_by_key(|entry| entry.updated_at);
+
+            let excess = completed.len().saturating_sub(max_entries);
+            pruned.extend(
+                completed[..excess]
+                    .iter()
+                    .map(|entry| entry.media_id.clone()),
+            );
+        }
+
+        if let Some(max_age_days) = max_age_days {
+            for entry in self
+                .entries
+                .iter_mut()
+                .filter(|entry| entry.updated_at == 0)
+            {
+                entry.updated_at = now;
+            }
+
+            let old: Vec<String> = self
+                .entries
+                .iter()
+                .filter(|entry| {
+                    entry.watched
+                        && now.saturating_sub(entry.updated_at) > max_age_days * 24 * 60 * 60
+                        && !pruned.contains(&entry.media_id)
+                })
+                .map(|entry| entry.media_id.clone())
+                .collect();
+
+            pruned.extend(old);
+        }
+
+        // Removed like a finished entry, so syncing doesn't bring them back and the watch
+        // log keeps them
+        for media_id in &pruned {
+            self.remove_finished(media_id);
+        }
+
+        pruned.len()
+    }
+}
+
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.episode {
+            Some(episode) => write!(
+                f,
+                "{} (tv) Season {} {}",
+                self.title, episode.season, episode.title
+            )?,
+            None => write!(f, "{} (movie)", self.title)?,
+        }
+
+        if self.position > 0.0 {
+            write!(f, " at {}", format_position(self.position))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs a `lobster-rs history` subcommand.
+pub fn manage_history(command: &HistoryCommand) -> anyhow::Result<()> {
+    let mut store = Store::load_for_update()?;
+
+    match command {
+        HistoryCommand::List { query } => {
+            let query = query.as_deref().unwrap_or_default().to_lowercase();
+
+            for entry in store.entries() {
+                if entry.title.to_lowercase().contains(&query) {
+                    println!("{}", entry);
+                }
+            }
+
+            return Ok(());
+        }
+        HistoryCommand::Migrate => {
+            info!(
+                "History is at version {} with {} entries",
+                HISTORY_VERSION,
+                store.entries().len()
+            );
+
+            return Ok(());
+        }
+        HistoryCommand::Rm { title } => {
+            let matches: Vec<String> = store
+                .entries()
+                .iter()
+                .filter(|entry| entry.title.eq_ignore_ascii_case(title))
+                .map(|entry| entry.media_id.clone())
+                .collect();
+
+            if matches.is_empty() {
+                return Err(anyhow!("No history entry titled {}", title));
+            }
+
+            for media_id in matches {
+                store.remove(&media_id);
+            }
+
+            info!("Removed {} from the history", title);
+        }
+        HistoryCommand::Clear { watched: false } => {
+            // Clearing takes the lock itself
+            drop(store);
+            Store::clear()?;
+            info!("History cleared");
+            return Ok(());
+        }
+        HistoryCommand::Clear { watched: true } => {
+            let watched: Vec<String> = store
+                .entries()
+                .iter()
+                .filter(|entry| entry.watched)
+                .map(|entry| entry.media_id.clone())
+                .collect();
+
+            for media_id in &watched {
+                store.remove(media_id);
+            }
+
+            info!("Removed {} watched entries from the history", watched.len());
+        }
+        HistoryCommand::Export { format, file } => {
+            let export = store.export(*format)?;
+
+            match file {
+                Some(file) => {
+                    std::fs::write(file, export)?;
+                    info!("Exported {} entries to {:?}", store.entries().len(), file);
+                }
+                None => print!("{}", export),
+            }
+
+            return Ok(());
+        }
+        HistoryCommand::Import { format, file } => {
+            let format = format.unwrap_or(
+                match file.extension().and_then(|extension| extension.to_str()) {
+                    Some(extension) if extension.eq_ignore_ascii_case("csv") => HistoryFormat::Csv,
+                    _ => HistoryFormat::Json,
+                },
+            );
+
+            let entries = Store::parse_export(&std::fs::read_to_string(file)?, format)
+                .with_context(|| format!("Failed to import {:?} as {}", file, format))?;
+            let count = entries.len();
+
+            for entry in entries {
+                store.upsert(entry);
+            }
+
+            info!("Imported {} entries from {:?}", count, file);
+        }
+    }
+
+    store.save()
+}
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Applies `history_max_entries` and `history_max_age_days` from the config.
+pub fn prune_history(max_entries: Option<usize>, max_age_days: Option<u64>) -> anyhow::Result<()> {
+    if max_entries.is_none() && max_age_days.is_none() {
+        return Ok(());
+    }
+
+    let mut store = Store::load_for_update()?;
+
+    if store.entries().is_empty() {
+        return Ok(());
+    }
+
+    let removed = store.prune(max_entries, max_age_days);
+
+    if removed > 0 {
+        info!("Removed {} old entries from the history", removed);
+    }
+
+    store.save()
+}
+
+fn watchlater_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join("lobster-rs/watchlater")
+}
+
+/// Empties the directory mpv writes its watch-later file to and returns its path, so the
+/// position read afterwards is from this playback.
+pub fn reset_watchlater_dir() -> String {
+    let watchlater_dir = watchlater_dir();
+
+    if watchlater_dir.exists() {
+        std::fs::remove_dir_all(&watchlater_dir).expect("Failed to remove watchlater directory!");
+    }
+
+    std::fs::create_dir_all(&watchlater_dir).expect("Failed to create watchlater directory!");
+
+    watchlater_dir.display().to_string()
+}
+
+pub async fn save_progress(url: String) -> anyhow::Result<(String, f32)> {
+    let watchlater_dir = watchlater_dir();
 
     let client = Client::builder()
         .danger_accept_invalid_certs(true)
@@ -20,22 +815,23 @@ pub async fn save_progress(url: String) -> anyhow::Result<(String, f32)> {
 
     let response = client.get(url).send().await?.text().await?;
 
-    for capture in re.captures_iter(&response) {
-        if let Some(duration) = capture.get(1) {
-            durations.push(duration.as_str().parse::<f32>().unwrap());
-        }
-    }
-
     let entries: Vec<_> = std::fs::read_dir(watchlater_dir)?
         .filter_map(|entry| entry.ok())
         .filter(|entry| entry.path().is_file())
         .collect();
 
-    let file_path = entries[0].path();
+    let file_path = entries
+        .first()
+        .ok_or_else(|| anyhow!("The player didn't save its position"))?
+        .path();
 
     let watchlater_contents = std::fs::read_to_string(&file_path)?;
 
-    let start_pos = watchlater_contents.split("start=").collect::<Vec<&str>>()[1].trim();
+    let start_pos = watchlater_contents
+        .split_once("start=")
+        .ok_or_else(|| anyhow!("No position in {:?}", file_path))?
+        .1
+        .trim();
 
     let position = start_pos
         .chars()
@@ -43,146 +839,203 @@ pub async fn save_progress(url: String) -> anyhow::Result<(String, f32)> {
         .map(|n| &start_pos[..n])
         .unwrap_or_else(|| start_pos);
 
-    let position = position.parse::<f32>().unwrap();
+    let position = position.parse::<f32>()?;
 
-    let total_duration: f32 = durations.iter().sum();
+    let total_duration = playlist::total_duration(&response);
 
     let progress = (position * 100.0) / total_duration;
 
-    let new_position = format!(
-        "{:.2}:{:.2}:{:.2}",
-        (position / 3600.0),
-        (position / 60.0 % 60.0),
-        (position % 60.0)
-    );
+    Ok((format_position(position), progress))
+}
 
-    Ok((new_position, progress))
+pub fn format_position(seconds: f32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        (seconds / 3600.0) as u32,
+        (seconds / 60.0 % 60.0) as u32,
+        (seconds % 60.0) as u32
+    )
 }
 
-fn write_to_history(info: String) -> anyhow::Result<()> {
-    let history_file_dir = dirs::data_local_dir()
-        .expect("Failed to find local dir")
-        .join("lobster-rs");
+/// Parses a position saved in the history back into seconds. Older entries stored every
+/// field with decimals (e.g. `0.51:30.75:45.12`), so hours and minutes are rounded down.
+fn position_seconds(position: &str) -> Option<f32> {
+    let fields = position
+        .split(':')
+        .map(|field| field.parse::<f32>().ok())
+        .collect::<Option<Vec<f32>>>()?;
 
-    if !history_file_dir.exists() {
-        std::fs::create_dir_all(&history_file_dir)?;
+    match fields.as_slice() {
+        [hours, minutes, seconds] => {
+            Some(hours.floor() * 3600.0 + minutes.floor() * 60.0 + seconds)
+        }
+        _ => None,
     }
+}
 
-    let history_file = history_file_dir.join("lobster_history.txt");
+/// How far `position` (as reported by mpv) is into the video at `url`, in percent.
+pub async fn playback_progress(url: &str, position: &str) -> Option<f32> {
+    let seconds = position_seconds(position)?;
+    let playlist = CLIENT.get(url).send().await.ok()?.text().await.ok()?;
+    let total_duration = playlist::total_duration(&playlist);
 
-    if !history_file.exists() {
-        std::fs::File::create(&history_file)?;
-    }
+    (total_duration > 0.0).then(|| seconds * 100.0 / total_duration)
+}
 
-    let mut file = OpenOptions::new().append(true).open(history_file).unwrap();
-    if let Err(e) = writeln!(file, "{}", info) {
-        eprintln!("Couldn't write to file: {}", e);
+/// The position in seconds saved in the history for a movie, or for one episode of a show
+/// when `episode_id` is given. Returns `None` when nothing (or 00:00:00) was saved.
+pub fn saved_position(media_id: &str, episode_id: Option<&str>) -> Option<f32> {
+    let store = Store::load().ok()?;
+    let entry = store.get(media_id)?;
+
+    if let Some(episode_id) = episode_id {
+        if entry.episode.as_ref()?.id != episode_id {
+            return None;
+        }
     }
 
-    Ok(())
+    (entry.position >= 1.0).then_some(entry.position)
 }
 
-fn remove_from_history(media_id: String) -> anyhow::Result<()> {
-    let history_file_dir = dirs::data_local_dir()
-        .expect("Failed to find local dir")
-        .join("lobster-rs");
+/// Parses the episode number out of an episode title like "Eps 5: Pilot".
+pub fn episode_number(episode_title: &str) -> Option<usize> {
+    episode_title
+        .replace(":", "")
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
 
-    if !history_file_dir.exists() {
-        std::fs::create_dir_all(&history_file_dir)?;
-    }
+/// Where each show in the history left off, as season number and episode title keyed by media id.
+/// Labels for the episodes of a season in the episode picker. The episodes before the one
+/// saved in the history get a `✓`, and the saved one how much of it was watched.
+pub fn mark_watched(media_id: &str, season: usize, titles: &[String]) -> Vec<String> {
+    let Some(entry) = Store::load()
+        .ok()
+        .and_then(|store| store.get(media_id).cloned())
+    else {
+        return titles.to_vec();
+    };
 
-    let history_file = history_file_dir.join("lobster_history.txt");
+    let Some(episode) = &entry.episode else {
+        return titles.to_vec();
+    };
 
-    if !history_file.exists() {
-        return Err(anyhow!("History file does not exist!"));
-    }
+    let saved = match season.cmp(&episode.season) {
+        std::cmp::Ordering::Less => titles.len(),
+        std::cmp::Ordering::Equal => titles
+            .iter()
+            .position(|title| *title == episode.title)
+            .unwrap_or(0),
+        std::cmp::Ordering::Greater => 0,
+    };
 
-    let mut history_file_temp = std::fs::read_to_string(&history_file)?
-        .lines()
-        .map(String::from)
-        .collect::<Vec<String>>();
+    titles
+        .iter()
+        .enumerate()
+        .map(|(index, title)| {
+            if index < saved {
+                format!("✓ {}", title)
+            } else if season == episode.season && *title == episode.title {
+                match entry.progress {
+                    Some(progress) if progress >= 1.0 => {
+                        format!("{} ({:.0}%)", title, progress)
+                    }
+                    _ => title.clone(),
+                }
+            } else {
+                title.clone()
+            }
+        })
+        .collect()
+}
 
-    if let Some(pos) = history_file_temp.iter().position(|x| x.contains(&media_id)) {
-        let _ = history_file_temp.remove(pos);
-    } else {
-        return Err(anyhow!("Episode does not exist in history file yet!"));
-    }
+pub fn show_progress() -> HashMap<String, (usize, String)> {
+    let Ok(store) = Store::load() else {
+        return HashMap::new();
+    };
 
-    std::fs::write(history_file, history_file_temp.join("\n"))?;
+    store
+        .entries()
+        .iter()
+        .filter_map(|entry| {
+            let episode = entry.episode.as_ref()?;
 
-    Ok(())
+            Some((
+                entry.media_id.clone(),
+                (episode.season, episode.title.clone()),
+            ))
+        })
+        .collect()
 }
 
 pub async fn save_history(
     media_info: (Option<String>, String, String, String, String),
-    episode_info: Option<(usize, usize, Vec<Vec<FlixHQEpisode>>)>,
+    episode_info: Option<(usize, usize, FlixHQSeason)>,
     position: String,
-    progress: f32,
+    progress: Option<f32>,
+    finished: bool,
 ) -> anyhow::Result<()> {
-    let media_type = media_info.2.split('/').collect::<Vec<&str>>()[0];
-
-    match media_type {
-        "movie" => {
-            if progress > 90.0 {
-                if remove_from_history(media_info.2.clone()).is_ok() {
-                } else {
-                    write_to_history(format!(
-                        "{}\t{}\t{}\t{}",
-                        media_info.3, position, media_info.2, media_info.4
-                    ))?;
-                }
+    let media_id: MediaId = media_info.2.parse()?;
 
-                return Ok(());
-            }
+    let mut store = Store::load_for_update()?;
+    store.add_session(&media_info.2, &media_info.3);
 
-            write_to_history(format!(
-                "{}\t{}\t{}\t{}",
-                media_info.3, position, media_info.2, media_info.4
-            ))?;
+    let mut entry = HistoryEntry {
+        title: media_info.3,
+        media_id: media_info.2,
+        image: media_info.4,
+        position: position_seconds(&position).unwrap_or(0.0),
+        progress: progress.filter(|_| !finished),
+        episode: None,
+        watched: finished,
+        updated_at: unix_time(),
+    };
+
+    match media_id.media_type() {
+        MediaType::Movie => {
+            if finished {
+                store.remove_finished(&entry.media_id);
+            } else {
+                store.upsert(entry);
+            }
         }
-        "tv" => {
-            if let Some((mut season_number, mut episode_number, episodes)) = episode_info {
-                if progress > 90.0 {
-                    episode_number += 1;
+        MediaType::Tv => {
+            let Some((mut season_number, mut episode_number, seasons)) = episode_info else {
+                return store.save();
+            };
 
-                    if episode_number >= episodes[season_number - 1].len() {
-                        if season_number < episodes.len() {
-                            season_number += 1;
-                            episode_number = 0;
-                        }
-                    }
+            let flixhq = FlixHQ::new(&*SCRAPER_CLIENT);
+
+            if finished {
+                // Continue with the next episode, or forget the show after its last one
+                episode_number += 1;
+                entry.position = 0.0;
+                entry.progress = None;
 
-                    if remove_from_history(media_info.2.clone()).is_ok() {
+                if episode_number >= seasons.episodes(&flixhq, season_number).await?.len() {
+                    if season_number < seasons.total_seasons {
+                        season_number += 1;
+                        episode_number = 0;
                     } else {
-                        write_to_history(format!(
-                            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
-                            media_info.3,
-                            position,
-                            media_info.2,
-                            media_info.1,
-                            season_number,
-                            episodes[season_number - 1][episode_number].title,
-                            media_info.4
-                        ))?;
+                        store.remove_finished(&entry.media_id);
+                        return store.save();
                     }
-
-                    return Ok(());
                 }
-
-                write_to_history(format!(
-                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
-                    media_info.3,
-                    position,
-                    media_info.2,
-                    media_info.1,
-                    season_number,
-                    episodes[season_number - 1][episode_number].title,
-                    media_info.4
-                ))?;
             }
+
+            let episode = &seasons.episodes(&flixhq, season_number).await?[episode_number];
+
+            entry.episode = Some(HistoryEpisode {
+                id: episode.id.clone(),
+                season: season_number,
+                title: episode.title.clone(),
+            });
+
+            store.upsert(entry);
         }
-        _ => return Err(anyhow!("Unknown media type!")),
     }
 
-    Ok(())
+    store.save()
 }
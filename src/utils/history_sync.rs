@@ -0,0 +1,180 @@
+use crate::{
+    utils::{
+        config::HistorySync,
+        history::{migrate, Store},
+    },
+    HistoryFormat, CLIENT,
+};
+use anyhow::anyhow;
+use log::{debug, info, warn};
+use reqwest::StatusCode;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Exit codes of rclone for a missing directory or file.
+const RCLONE_NOT_FOUND: [i32; 2] = [3, 4];
+
+/// Where the shared history is kept, from `remote` in `[history_sync]`.
+enum Remote<'a> {
+    WebDav {
+        url: &'a str,
+        username: Option<&'a str>,
+        password: Option<&'a str>,
+    },
+    /// `remote:path` as rclone takes it
+    Rclone(&'a str),
+}
+
+impl<'a> Remote<'a> {
+    fn new(history_sync: &'a HistorySync) -> anyhow::Result<Self> {
+        if let Some(path) = history_sync.remote.strip_prefix("rclone:") {
+            return Ok(Remote::Rclone(path));
+        }
+
+        if !history_sync.remote.starts_with("http://")
+            && !history_sync.remote.starts_with("https://")
+        {
+            return Err(anyhow!(
+                "`remote` has to be a WebDAV address or rclone:<remote>:<path>, got {:?}",
+                history_sync.remote
+            ));
+        }
+
+        Ok(Remote::WebDav {
+            url: &history_sync.remote,
+            username: history_sync.username.as_deref(),
+            password: history_sync.password.as_deref(),
+        })
+    }
+
+    /// The history on the remote, `None` until it was uploaded the first time.
+    async fn download(&self) -> anyhow::Result<Option<String>> {
+        match self {
+            Remote::WebDav {
+                url,
+                username,
+                password,
+            } => {
+                let mut request = CLIENT.get(*url);
+
+                if let Some(username) = username {
+                    request = request.basic_auth(username, *password);
+                }
+
+                let response = request.send().await?;
+
+                if response.status() == StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+
+                Ok(Some(response.error_for_status()?.text().await?))
+            }
+            Remote::Rclone(path) => {
+                let output = Command::new("rclone").args(["cat", path]).output()?;
+
+                if output.status.success() {
+                    return Ok(Some(String::from_utf8(output.stdout)?));
+                }
+
+                if output
+                    .status
+                    .code()
+                    .is_some_and(|code| RCLONE_NOT_FOUND.contains(&code))
+                {
+                    return Ok(None);
+                }
+
+                Err(anyhow!(
+                    "rclone cat failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+            }
+        }
+    }
+
+    async fn upload(&self, contents: String) -> anyhow::Result<()> {
+        match self {
+            Remote::WebDav {
+                url,
+                username,
+                password,
+            } => {
+                let mut request = CLIENT
+                    .put(*url)
+                    .header("Content-Type", "application/json")
+                    .body(contents);
+
+                if let Some(username) = username {
+                    request = request.basic_auth(username, *password);
+                }
+
+                request.send().await?.error_for_status()?;
+            }
+            Remote::Rclone(path) => {
+                let mut child = Command::new("rclone")
+                    .args(["rcat", path])
+                    .stdin(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(contents.as_bytes())?;
+                }
+
+                let output = child.wait_with_output()?;
+
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "rclone rcat failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn sync(history_sync: &HistorySync) -> anyhow::Result<()> {
+    let remote = Remote::new(history_sync)?;
+
+    let remote_history = match remote.download().await? {
+        Some(contents) => Some(migrate(serde_json::from_str(&contents)?)?.0),
+        None => {
+            debug!("No history on the remote yet");
+            None
+        }
+    };
+
+    // The lock is let go before uploading, the copy on the remote isn't guarded by it
+    let contents = {
+        let mut store = Store::load_for_update()?;
+
+        if let Some(remote_history) = remote_history {
+            store.merge(remote_history);
+        }
+
+        store.save()?;
+        store.export(HistoryFormat::Json)?
+    };
+
+    remote.upload(contents).await?;
+    info!("Synced the history with {}", history_sync.remote);
+
+    Ok(())
+}
+
+/// Merges the history on the `[history_sync]` remote into the local one and uploads the
+/// result. Failures are only logged, the local history works fine on its own.
+pub async fn sync_history(history_sync: &Option<HistorySync>) {
+    let Some(history_sync) = history_sync else {
+        return;
+    };
+
+    if let Err(e) = sync(history_sync).await {
+        warn!("Failed to sync the history: {}", e);
+    }
+}
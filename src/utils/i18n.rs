@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::OnceLock};
+
+static LANGUAGE: OnceLock<UiLanguage> = OnceLock::new();
+
+/// The language of the prompts and messages that have been translated, set with
+/// `ui_language`. Everything else stays in English.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UiLanguage {
+    #[default]
+    English,
+    Spanish,
+    German,
+    French,
+}
+
+/// Picks the language of [`Message`]s for the rest of the run.
+pub fn set_ui_language(language: UiLanguage) {
+    let _ = LANGUAGE.set(language);
+}
+
+/// A translated prompt or message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    Yes,
+    No,
+    PlayWithSubtitles,
+    ContinuingWithoutSubtitles,
+    NoSubtitlesAvailable,
+}
+
+impl Message {
+    pub fn text(self) -> &'static str {
+        let language = LANGUAGE.get().copied().unwrap_or_default();
+
+        match (self, language) {
+            (Message::Yes, UiLanguage::English) => "Yes",
+            (Message::Yes, UiLanguage::Spanish) => "Sí",
+            (Message::Yes, UiLanguage::German) => "Ja",
+            (Message::Yes, UiLanguage::French) => "Oui",
+            (Message::No, UiLanguage::English) => "No",
+            (Message::No, UiLanguage::Spanish) => "No",
+            (Message::No, UiLanguage::German) => "Nein",
+            (Message::No, UiLanguage::French) => "Non",
+            (Message::PlayWithSubtitles, UiLanguage::English) => "Play with subtitles?",
+            (Message::PlayWithSubtitles, UiLanguage::Spanish) => "¿Reproducir con subtítulos?",
+            (Message::PlayWithSubtitles, UiLanguage::German) => "Mit Untertiteln abspielen?",
+            (Message::PlayWithSubtitles, UiLanguage::French) => "Lire avec les sous-titres ?",
+            (Message::ContinuingWithoutSubtitles, UiLanguage::English) => {
+                "Continuing without subtitles"
+            }
+            (Message::ContinuingWithoutSubtitles, UiLanguage::Spanish) => {
+                "Continuando sin subtítulos"
+            }
+            (Message::ContinuingWithoutSubtitles, UiLanguage::German) => "Weiter ohne Untertitel",
+            (Message::ContinuingWithoutSubtitles, UiLanguage::French) => "Lecture sans sous-titres",
+            (Message::NoSubtitlesAvailable, UiLanguage::English) => "No subtitles available!",
+            (Message::NoSubtitlesAvailable, UiLanguage::Spanish) => {
+                "¡No hay subtítulos disponibles!"
+            }
+            (Message::NoSubtitlesAvailable, UiLanguage::German) => "Keine Untertitel verfügbar!",
+            (Message::NoSubtitlesAvailable, UiLanguage::French) => "Aucun sous-titre disponible !",
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
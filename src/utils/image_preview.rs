@@ -1,5 +1,97 @@
 use crate::CLIENT;
 use log::{debug, error};
+use std::path::PathBuf;
+
+/// Where the downloaded posters are kept while a menu is open.
+pub fn images_dir() -> PathBuf {
+    if cfg!(windows) {
+        std::env::temp_dir().join("lobster-rs-images")
+    } else {
+        PathBuf::from("/tmp/images")
+    }
+}
+
+/// Parses the version of the Windows Terminal package, e.g. `1.22.10352.0`, into its
+/// major and minor parts.
+fn windows_terminal_version() -> Option<(u32, u32)> {
+    let output = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "(Get-AppxPackage -Name Microsoft.WindowsTerminal*).Version",
+        ])
+        .output()
+        .ok()?;
+
+    let version = String::from_utf8_lossy(&output.stdout);
+    let mut parts = version.lines().next()?.trim().split('.');
+
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// The chafa output format for the preview. Windows Terminal only draws sixels since
+/// 1.22, older versions and conhost get block symbols instead.
+pub fn chafa_format() -> &'static str {
+    if !cfg!(windows) {
+        return "sixels";
+    }
+
+    if std::env::var_os("WT_SESSION").is_none() {
+        debug!("Not running in Windows Terminal, previewing images with symbols");
+        return "symbols";
+    }
+
+    match windows_terminal_version() {
+        Some(version) if version >= (1, 22) => "sixels",
+        version => {
+            debug!(
+                "Windows Terminal {:?} can't draw sixels, previewing images with symbols",
+                version
+            );
+            "symbols"
+        }
+    }
+}
+
+/// The shell fzf should run the preview command with, when its default one can't.
+pub fn fzf_preview_shell() -> Option<String> {
+    cfg!(windows).then(|| "powershell -NoProfile -NonInteractive -Command".to_string())
+}
+
+/// Builds the fzf preview command that draws the poster of the selected entry with chafa,
+/// after `title_preview` (which may be empty).
+pub fn fzf_preview(title_preview: &str) -> String {
+    let images_dir = images_dir();
+
+    if cfg!(windows) {
+        let mut commands = vec![
+            "$selected = {2} -replace '/','-'".to_string(),
+            format!(
+                "chafa -f {} -s 80x40 \"{}\\$selected.jpg\"",
+                chafa_format(),
+                images_dir.display()
+            ),
+        ];
+
+        if !title_preview.is_empty() {
+            commands.insert(0, title_preview.to_string());
+        }
+
+        commands.join("; ")
+    } else {
+        format!(
+            r#"
+    {}
+    set -l selected (echo {{}} | cut -f2 | sed 's/\//-/g')
+    chafa -f {} -s 80x40 "{}/$selected.jpg"
+    "#,
+            title_preview,
+            chafa_format(),
+            images_dir.display()
+        )
+    }
+}
 
 pub fn generate_desktop(
     media_title: String,
@@ -59,11 +151,16 @@ pub fn remove_desktop_and_tmp(media_id: String) -> anyhow::Result<()> {
         debug!("Desktop file does not exist: {:?}", desktop_file);
     }
 
-    if std::fs::metadata("/tmp/images").is_ok() {
-        debug!("Removing temporary images directory: /tmp/images");
-        std::fs::remove_dir_all("/tmp/images")?;
+    let images_dir = images_dir();
+
+    if images_dir.exists() {
+        debug!("Removing temporary images directory: {:?}", images_dir);
+        std::fs::remove_dir_all(&images_dir)?;
     } else {
-        debug!("Temporary images directory does not exist: /tmp/images");
+        debug!(
+            "Temporary images directory does not exist: {:?}",
+            images_dir
+        );
     }
 
     debug!(
@@ -82,13 +179,18 @@ pub async fn image_preview(
         images.len()
     );
 
-    if std::fs::metadata("/tmp/images").is_ok() {
-        debug!("Removing existing temporary images directory: /tmp/images");
-        std::fs::remove_dir_all("/tmp/images")?;
+    let images_dir = images_dir();
+
+    if images_dir.exists() {
+        debug!(
+            "Removing existing temporary images directory: {:?}",
+            images_dir
+        );
+        std::fs::remove_dir_all(&images_dir)?;
     }
 
-    debug!("Creating temporary images directory: /tmp/images");
-    std::fs::create_dir_all("/tmp/images").expect("Failed to create image cache directory");
+    debug!("Creating temporary images directory: {:?}", images_dir);
+    std::fs::create_dir_all(&images_dir).expect("Failed to create image cache directory");
 
     let mut temp_images: Vec<(String, String, String)> = vec![];
 
@@ -105,7 +207,10 @@ pub async fn image_preview(
             .bytes()
             .await?;
 
-        let output_path = format!("/tmp/images/{}.jpg", media_id.replace("/", "-"));
+        let output_path = images_dir
+            .join(format!("{}.jpg", media_id.replace("/", "-")))
+            .display()
+            .to_string();
         debug!("Saving image to: {}", output_path);
 
         match image::load_from_memory(&image_bytes) {
@@ -0,0 +1,62 @@
+use crate::Provider;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The movie or episode that was picked last, with the server and quality it was played
+/// with, for `--last` and the "Repeat last" entry of the search menu.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LastSelection {
+    pub media_id: String,
+    pub title: String,
+    pub image: String,
+    /// The season and the index of the episode in it, shows only
+    pub episode: Option<(usize, usize)>,
+    pub provider: Provider,
+    /// The quality given with `-q` or capped to, the best one otherwise
+    pub quality: Option<u32>,
+}
+
+impl LastSelection {
+    /// How it's shown in the search menu, e.g. `Repeat last: The Office S02E01`.
+    pub fn label(&self) -> String {
+        match self.episode {
+            Some((season, episode)) => format!(
+                "Repeat last: {} S{:02}E{:02}",
+                self.title,
+                season,
+                episode + 1
+            ),
+            None => format!("Repeat last: {}", self.title),
+        }
+    }
+}
+
+fn last_selection_file() -> PathBuf {
+    dirs::data_local_dir()
+        .expect("Failed to find local dir")
+        .join("lobster-rs/last_selection.json")
+}
+
+pub fn last_selection() -> Option<LastSelection> {
+    let content = std::fs::read_to_string(last_selection_file()).ok()?;
+    let last = serde_json::from_str(&content).ok();
+
+    debug!("Last selection: {:?}", last);
+
+    last
+}
+
+pub fn save_last_selection(last: &LastSelection) -> anyhow::Result<()> {
+    let last_selection_file = last_selection_file();
+
+    if let Some(parent) = last_selection_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(last_selection_file, serde_json::to_string(last)?)?;
+
+    debug!("Saved last selection: {:?}", last);
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use crate::utils::{config::Limits, menu::menu_backend, rofi::RofiArgs};
+use anyhow::{anyhow, Context};
+use chrono::{Local, NaiveDate, NaiveTime};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    terminal,
+};
+use log::{debug, info, warn};
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+/// Minutes left in the daily allowance below which a warning is shown.
+const WARN_MINUTES: f32 = 15.0;
+
+/// Set once the PIN was entered, the limits are lifted for the rest of the session.
+static UNLOCKED: AtomicBool = AtomicBool::new(false);
+
+fn screen_time_file() -> PathBuf {
+    dirs::data_local_dir()
+        .expect("Failed to find local dir")
+        .join("lobster-rs/screen_time.txt")
+}
+
+/// Seconds spent watching today. The file only holds the date and the total of the last
+/// day something was played.
+fn watched_today(today: NaiveDate) -> u64 {
+    let Ok(contents) = std::fs::read_to_string(screen_time_file()) else {
+        return 0;
+    };
+
+    contents
+        .trim()
+        .split_once('\t')
+        .filter(|(date, _)| date.parse::<NaiveDate>() == Ok(today))
+        .and_then(|(_, seconds)| seconds.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Adds the time since `started` to today's watch time, when there are limits to enforce.
+pub fn record_playback(limits: &Option<Limits>, started: Instant) -> anyhow::Result<()> {
+    if limits.is_none() {
+        return Ok(());
+    }
+
+    let today = Local::now().date_naive();
+    let total = watched_today(today) + started.elapsed().as_secs();
+
+    let screen_time_file = screen_time_file();
+
+    if let Some(parent) = screen_time_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    debug!("Watched {} seconds today", total);
+    std::fs::write(screen_time_file, format!("{}\t{}\n", today, total))?;
+
+    Ok(())
+}
+
+/// Parses an `allowed_hours` window like `08:00-20:00`. The end may be before the start for
+/// a window that goes past midnight.
+fn parse_window(window: &str) -> anyhow::Result<(NaiveTime, NaiveTime)> {
+    let (start, end) = window
+        .split_once('-')
+        .ok_or_else(|| anyhow!("expected a window like 08:00-20:00, got {}", window))?;
+
+    Ok((
+        NaiveTime::parse_from_str(start.trim(), "%H:%M")
+            .with_context(|| format!("invalid start time {}", start))?,
+        NaiveTime::parse_from_str(end.trim(), "%H:%M")
+            .with_context(|| format!("invalid end time {}", end))?,
+    ))
+}
+
+fn in_window(now: NaiveTime, (start, end): (NaiveTime, NaiveTime)) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// Reads the PIN without echoing it. The external menu hides it where it has a password
+/// mode.
+fn read_pin(rofi: bool) -> anyhow::Result<String> {
+    if rofi {
+        let output = menu_backend().spawn(&mut RofiArgs {
+            dmenu: true,
+            password: true,
+            entry_prompt: Some("PIN".to_string()),
+            mesg: Some("Enter the PIN to keep watching".to_string()),
+            ..Default::default()
+        })?;
+
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    print!("Enter the PIN to keep watching: ");
+    std::io::stdout().flush()?;
+
+    terminal::enable_raw_mode()?;
+
+    let read = || -> anyhow::Result<String> {
+        let mut pin = String::new();
+
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                match key.code {
+                    KeyCode::Enter => return Ok(pin),
+                    KeyCode::Esc => return Ok(String::new()),
+                    KeyCode::Backspace => {
+                        pin.pop();
+                    }
+                    KeyCode::Char(c) => pin.push(c),
+                    _ => {}
+                }
+            }
+        }
+    };
+
+    let pin = read();
+
+    terminal::disable_raw_mode()?;
+    println!();
+
+    pin
+}
+
+/// Checks the `[limits]` from the config before playback starts. Outside the allowed hours
+/// or past the daily allowance playback is refused, unless the PIN is entered.
+pub fn check_allowance(limits: &Option<Limits>, rofi: bool) -> anyhow::Result<()> {
+    let Some(limits) = limits else {
+        return Ok(());
+    };
+
+    if UNLOCKED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let now = Local::now();
+    let mut refusal = None;
+
+    if let Some(window) = &limits.allowed_hours {
+        let window = parse_window(window).context("Invalid `allowed_hours` in [limits]")?;
+
+        if !in_window(now.time(), window) {
+            refusal = Some(format!(
+                "Watching is only allowed between {} and {}",
+                window.0.format("%H:%M"),
+                window.1.format("%H:%M")
+            ));
+        }
+    }
+
+    if let Some(max_hours) = limits.max_hours_per_day {
+        let minutes_left = max_hours * 60.0 - watched_today(now.date_naive()) as f32 / 60.0;
+
+        if minutes_left <= 0.0 {
+            refusal.get_or_insert(format!("The daily limit of {} hours is used up", max_hours));
+        } else if minutes_left < WARN_MINUTES {
+            warn!(
+                "{:.0} minutes of watch time left today",
+                minutes_left.ceil()
+            );
+        }
+    }
+
+    let Some(refusal) = refusal else {
+        return Ok(());
+    };
+
+    warn!("{}", refusal);
+
+    match &limits.pin {
+        Some(pin) if read_pin(rofi)? == *pin => {
+            info!("PIN accepted, the limits are lifted for this session.");
+            UNLOCKED.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        Some(_) => Err(anyhow!("Wrong PIN, not starting playback")),
+        None => Err(anyhow!(refusal)),
+    }
+}
@@ -0,0 +1,239 @@
+use crate::utils::{
+    rofi::{Rofi, RofiArgs, RofiSpawn},
+    template::expand_command,
+    SpawnError,
+};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Error, ErrorKind, Write},
+    process::{Command, Output, Stdio},
+    sync::OnceLock,
+};
+
+/// The external menu used with `use_external_menu` or `--rofi`, from `menu` in the config.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Menu {
+    #[default]
+    Rofi,
+    Dmenu,
+    Wofi,
+    Fuzzel,
+    Tofi,
+}
+
+impl Menu {
+    pub fn executable(&self) -> &'static str {
+        match self {
+            Menu::Rofi => "rofi",
+            Menu::Dmenu => "dmenu",
+            Menu::Wofi => "wofi",
+            Menu::Fuzzel => "fuzzel",
+            Menu::Tofi => "tofi",
+        }
+    }
+}
+
+static MENU: OnceLock<Menu> = OnceLock::new();
+static MENU_COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Uses `menu` from the config for the external menu instead of rofi.
+pub fn set_menu(menu: Menu) {
+    let _ = MENU.set(menu);
+}
+
+/// Uses `external_menu_command` from the config for the external menu, which takes over
+/// from `menu`.
+pub fn set_menu_command(command: &str) {
+    let _ = MENU_COMMAND.set(command.to_string());
+}
+
+fn menu() -> Menu {
+    MENU.get().copied().unwrap_or_default()
+}
+
+/// The program run for the external menu, for the logs.
+pub fn menu_name() -> String {
+    match MENU_COMMAND.get() {
+        Some(command) => expand_command(command, &[])
+            .into_iter()
+            .next()
+            .unwrap_or_default(),
+        None => menu().executable().to_string(),
+    }
+}
+
+/// Only rofi can show the image previews, through its drun mode.
+pub fn supports_images() -> bool {
+    MENU_COMMAND.get().is_none() && menu() == Menu::Rofi
+}
+
+/// rofi shows both the message and the prompt, the other menus only have room for one.
+fn prompt(args: &RofiArgs) -> Option<String> {
+    args.mesg
+        .clone()
+        .or_else(|| args.entry_prompt.clone())
+        .filter(|prompt| !prompt.is_empty())
+}
+
+/// Runs a menu that works like dmenu: the lines are written to its stdin, and the picked
+/// one is read from its stdout. Without any lines it works as a plain input box, which is
+/// how the search is asked for.
+fn run_menu(
+    program: &str,
+    menu_args: &[String],
+    lines: Option<&str>,
+) -> Result<Output, SpawnError> {
+    let mut command = Command::new(program);
+    command
+        .args(menu_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    debug!("Constructed command: {:?}", command);
+
+    let mut child = command.spawn().map_err(|e| {
+        error!("Failed to spawn {}: {}", program, e);
+        SpawnError::IOError(e)
+    })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Some(lines) = lines {
+            writeln!(stdin, "{}", lines).map_err(SpawnError::IOError)?;
+        }
+    }
+
+    child.wait_with_output().map_err(|e| {
+        error!("Failed to wait for {}: {}", program, e);
+        SpawnError::IOError(e)
+    })
+}
+
+/// A menu lines are picked from. They all take the arguments rofi does and leave out what
+/// they can't do.
+pub trait MenuBackend {
+    fn spawn(&mut self, args: &mut RofiArgs) -> Result<Output, SpawnError>;
+}
+
+impl MenuBackend for Rofi {
+    fn spawn(&mut self, args: &mut RofiArgs) -> Result<Output, SpawnError> {
+        RofiSpawn::spawn(self, args)
+    }
+}
+
+/// One of the menus that work like dmenu, reading the lines on stdin and printing the
+/// picked one.
+pub struct Dmenu {
+    menu: Menu,
+}
+
+impl Dmenu {
+    fn args(&self, args: &RofiArgs) -> Vec<String> {
+        let prompt = prompt(args);
+
+        let mut menu_args = vec![];
+
+        match self.menu {
+            Menu::Dmenu => {
+                menu_args.extend(["-l".to_string(), "20".to_string()]);
+
+                if args.case_sensitive {
+                    menu_args.push("-i".to_string());
+                }
+
+                if let Some(prompt) = prompt {
+                    menu_args.extend(["-p".to_string(), prompt]);
+                }
+            }
+            Menu::Wofi => {
+                menu_args.push("--dmenu".to_string());
+
+                if args.case_sensitive {
+                    menu_args.push("--insensitive".to_string());
+                }
+
+                if let Some(prompt) = prompt {
+                    menu_args.extend(["--prompt".to_string(), prompt]);
+                }
+
+                if args.password {
+                    menu_args.push("--password".to_string());
+                }
+            }
+            Menu::Fuzzel => {
+                menu_args.push("--dmenu".to_string());
+
+                if let Some(prompt) = prompt {
+                    menu_args.extend(["--prompt".to_string(), prompt]);
+                }
+
+                if args.password {
+                    menu_args.push("--password".to_string());
+                }
+            }
+            Menu::Tofi => {
+                if let Some(prompt) = prompt {
+                    menu_args.push(format!("--prompt-text={}", prompt));
+                }
+
+                if args.password {
+                    menu_args.push("--hide-input=true".to_string());
+                }
+            }
+            Menu::Rofi => unreachable!("rofi isn't spawned as a dmenu-like menu"),
+        }
+
+        menu_args
+    }
+}
+
+impl MenuBackend for Dmenu {
+    fn spawn(&mut self, args: &mut RofiArgs) -> Result<Output, SpawnError> {
+        run_menu(
+            self.menu.executable(),
+            &self.args(args),
+            args.process_stdin.as_deref(),
+        )
+    }
+}
+
+/// Any command that speaks the dmenu protocol, from `external_menu_command`, e.g.
+/// `walker --dmenu`. `{prompt}` in it is replaced by the prompt.
+pub struct CommandMenu {
+    command: String,
+}
+
+impl MenuBackend for CommandMenu {
+    fn spawn(&mut self, args: &mut RofiArgs) -> Result<Output, SpawnError> {
+        let command = expand_command(
+            &self.command,
+            &[("prompt", prompt(args).into_iter().collect())],
+        );
+
+        let Some((program, menu_args)) = command.split_first() else {
+            error!("`external_menu_command` is empty");
+            return Err(SpawnError::IOError(Error::new(
+                ErrorKind::InvalidInput,
+                "empty menu command",
+            )));
+        };
+
+        run_menu(program, menu_args, args.process_stdin.as_deref())
+    }
+}
+
+/// The menu from `external_menu_command` or `menu` in the config.
+pub fn menu_backend() -> Box<dyn MenuBackend> {
+    if let Some(command) = MENU_COMMAND.get() {
+        return Box::new(CommandMenu {
+            command: command.clone(),
+        });
+    }
+
+    match menu() {
+        Menu::Rofi => Box::new(Rofi::new()),
+        menu => Box::new(Dmenu { menu }),
+    }
+}
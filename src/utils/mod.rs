@@ -1,11 +1,37 @@
+pub mod anime_sync;
+pub mod batch;
 pub mod config;
+pub mod config_check;
+pub mod details;
+pub mod downloads;
 pub mod ffmpeg;
 pub mod fzf;
 pub mod history;
+pub mod history_sync;
+pub mod i18n;
 pub mod image_preview;
+pub mod last_selection;
+pub mod limits;
+pub mod menu;
+pub mod network;
+pub mod nfo;
+pub mod organize;
+pub mod picker;
 pub mod players;
+pub mod preferences;
 pub mod rofi;
+pub mod self_test;
+pub mod shuffle;
+pub mod sleep_timer;
+pub mod stream_cache;
+pub mod subtitles;
+pub mod template;
+pub mod title;
+pub mod whisper;
 pub mod presence;
+pub mod report;
+pub mod search_filters;
+pub mod stats;
 
 #[derive(thiserror::Error, Debug)]
 pub enum SpawnError {
@@ -0,0 +1,89 @@
+use lobster_core::http::HttpClient;
+use log::debug;
+use reqwest::Client;
+use std::{process::Command, sync::OnceLock, time::Duration};
+use tokio::{
+    sync::{Mutex, Semaphore},
+    time::Instant,
+};
+
+/// Asks NetworkManager whether any device is on a metered connection.
+/// Returns `false` when `nmcli` isn't available.
+pub fn is_metered_connection() -> bool {
+    let output = match Command::new("nmcli")
+        .args(["-t", "-f", "GENERAL.METERED", "device", "show"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            debug!("nmcli is unavailable, assuming an unmetered connection.");
+            return false;
+        }
+    };
+
+    let metered = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|line| line.trim_start_matches("GENERAL.METERED:").starts_with("yes"));
+
+    debug!("NetworkManager reports metered connection: {}", metered);
+    metered
+}
+
+struct RequestLimits {
+    permits: Semaphore,
+    delay: Duration,
+}
+
+/// The client the scrapers go through. Once [`limit`](Self::limit) is called, at most
+/// `max_concurrent_requests` requests are in flight and each one starts at least
+/// `request_delay` after the previous one, so heavy use doesn't get an IP banned.
+pub struct LimitedClient {
+    client: Client,
+    limits: OnceLock<RequestLimits>,
+    next_request: Mutex<Option<Instant>>,
+}
+
+impl LimitedClient {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            limits: OnceLock::new(),
+            next_request: Mutex::new(None),
+        }
+    }
+
+    /// Sets the limits from the config. Only the first call has an effect.
+    pub fn limit(&self, max_concurrent_requests: usize, request_delay: Duration) {
+        debug!(
+            "Limiting requests to {} at a time, {:?} apart.",
+            max_concurrent_requests, request_delay
+        );
+
+        let _ = self.limits.set(RequestLimits {
+            permits: Semaphore::new(max_concurrent_requests.max(1)),
+            delay: request_delay,
+        });
+    }
+}
+
+impl HttpClient for LimitedClient {
+    async fn get(&self, url: &str) -> anyhow::Result<String> {
+        let Some(limits) = self.limits.get() else {
+            return HttpClient::get(&self.client, url).await;
+        };
+
+        let _permit = limits.permits.acquire().await?;
+
+        if !limits.delay.is_zero() {
+            let mut next_request = self.next_request.lock().await;
+
+            if let Some(next_request) = *next_request {
+                tokio::time::sleep_until(next_request).await;
+            }
+
+            *next_request = Some(Instant::now() + limits.delay);
+        }
+
+        HttpClient::get(&self.client, url).await
+    }
+}
@@ -0,0 +1,116 @@
+use crate::utils::details::{load_details, Details};
+use log::debug;
+use std::{fmt::Write, path::Path};
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The tags a movie and a show have in common.
+fn tags(details: &Details) -> String {
+    let mut tags = String::new();
+    let mut tag = |name: &str, value: &str| {
+        if !value.is_empty() {
+            let _ = writeln!(tags, "  <{0}>{1}</{0}>", name, escape(value));
+        }
+    };
+
+    tag("title", &details.flixhq.title);
+    tag("plot", details.description());
+    tag("premiered", &details.flixhq.released);
+
+    if let Some(year) = details.year() {
+        tag("year", &year.to_string());
+    }
+
+    if let Some(rating) = &details.flixhq.rating {
+        tag("rating", rating);
+    }
+
+    for genre in details.genres() {
+        tag("genre", genre);
+    }
+
+    for country in &details.flixhq.country {
+        tag("country", country);
+    }
+
+    for actor in &details.flixhq.casts {
+        let _ = writeln!(tags, "  <actor><name>{}</name></actor>", escape(actor));
+    }
+
+    if let Some(poster) = details.poster() {
+        let _ = writeln!(
+            tags,
+            r#"  <thumb aspect="poster">{}</thumb>"#,
+            escape(poster)
+        );
+    }
+
+    if let Some(tmdb) = &details.tmdb {
+        let _ = writeln!(
+            tags,
+            r#"  <uniqueid type="tmdb" default="true">{}</uniqueid>"#,
+            tmdb.id
+        );
+    }
+
+    tags
+}
+
+fn nfo(root: &str, tags: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<{0}>\n{1}</{0}>\n",
+        root, tags
+    )
+}
+
+/// Writes the `.nfo` files Kodi, Jellyfin and Plex read next to a download in the
+/// `organize_downloads` layout: `<name>.nfo` for a movie, and `<name>.nfo` for an episode
+/// with a `tvshow.nfo` in the folder of the show. `episode` is the season, the episode
+/// number and its title.
+pub async fn write_nfo(
+    download_dir: &str,
+    name: &str,
+    media_id: &str,
+    episode: Option<(usize, usize, &str)>,
+    tmdb_api_key: Option<&str>,
+) -> anyhow::Result<()> {
+    let details = load_details(media_id, tmdb_api_key).await?;
+    let download_dir = Path::new(download_dir);
+
+    std::fs::create_dir_all(download_dir)?;
+
+    let Some((season, episode, episode_title)) = episode else {
+        let nfo_file = download_dir.join(format!("{}.nfo", name));
+        debug!("Writing {}", nfo_file.display());
+
+        return Ok(std::fs::write(nfo_file, nfo("movie", &tags(&details)))?);
+    };
+
+    // `Show/Season 02/`, the show's own file goes into `Show/`
+    if let Some(show_dir) = download_dir.parent() {
+        let nfo_file = show_dir.join("tvshow.nfo");
+        debug!("Writing {}", nfo_file.display());
+
+        std::fs::write(nfo_file, nfo("tvshow", &tags(&details)))?;
+    }
+
+    let episode_tags = format!(
+        "  <title>{}</title>\n  <showtitle>{}</showtitle>\n  <season>{}</season>\n  <episode>{}</episode>\n",
+        escape(episode_title),
+        escape(&details.flixhq.title),
+        season,
+        episode
+    );
+
+    let nfo_file = download_dir.join(format!("{}.nfo", name));
+    debug!("Writing {}", nfo_file.display());
+
+    std::fs::write(nfo_file, nfo("episodedetails", &episode_tags))?;
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+const CHECKSUMS_FILE: &str = "sha256sums.txt";
+
+/// Makes a title safe to use as a file or folder name.
+fn path_safe(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Directory and file name (without extension) for a download. With `organize` episodes go
+/// into `Show/Season 02/Show - S02E05`, otherwise everything lands in `download_dir` as is.
+pub fn download_target(
+    download_dir: &str,
+    media_title: &str,
+    season_and_episode: Option<(usize, usize)>,
+    organize: bool,
+) -> (String, String) {
+    match season_and_episode {
+        Some((season, episode)) if organize => {
+            let show = path_safe(media_title);
+
+            (
+                format!("{}/{}/Season {:02}", download_dir, show, season),
+                format!("{} - S{:02}E{:02}", show, season, episode),
+            )
+        }
+        _ => (download_dir.to_string(), media_title.to_string()),
+    }
+}
+
+fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Adds (or refreshes) the entries for `files` in the `sha256sums.txt` of `dir`, in the
+/// format `sha256sum -c` understands.
+pub fn update_checksums(dir: &Path, files: &[String]) -> anyhow::Result<()> {
+    let checksums_file = dir.join(CHECKSUMS_FILE);
+
+    let mut checksums: Vec<(String, String)> = std::fs::read_to_string(&checksums_file)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .filter(|(_, file)| !files.iter().any(|updated| updated == file))
+        .map(|(hash, file)| (hash.to_string(), file.to_string()))
+        .collect();
+
+    for file in files {
+        debug!("Hashing {}", file);
+        checksums.push((sha256_file(&dir.join(file))?, file.clone()));
+    }
+
+    checksums.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let manifest: String = checksums
+        .iter()
+        .map(|(hash, file)| format!("{}  {}\n", hash, file))
+        .collect();
+
+    std::fs::write(checksums_file, manifest)?;
+
+    Ok(())
+}
+
+/// Writes a fresh checksum manifest for `dir` and packs it into a single `dir.zip` next to it.
+pub fn pack(dir: &Path) -> anyhow::Result<PathBuf> {
+    let dir_name = dir
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Can't pack {}", dir.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let mut files: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|file| file != CHECKSUMS_FILE)
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("{} has no files to pack", dir.display()));
+    }
+
+    std::fs::write(dir.join(CHECKSUMS_FILE), "")?;
+    update_checksums(dir, &files)?;
+    files.push(CHECKSUMS_FILE.to_string());
+
+    let archive_path = dir.with_file_name(format!("{}.zip", dir_name));
+    let mut archive = ZipWriter::new(File::create(&archive_path)?);
+
+    for file in &files {
+        let path = dir.join(file);
+        debug!("Adding {} to {}", path.display(), archive_path.display());
+
+        // Videos are already compressed, storing them as is keeps packing fast
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(path.metadata()?.len() >= u32::MAX as u64);
+
+        archive.start_file(format!("{}/{}", dir_name, file), options)?;
+        std::io::copy(&mut File::open(&path)?, &mut archive)?;
+    }
+
+    archive.finish()?;
+
+    Ok(archive_path)
+}
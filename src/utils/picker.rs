@@ -0,0 +1,352 @@
+use crate::utils::fzf::FzfArgs;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use log::debug;
+use std::io::{Stderr, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// The fields of `line` that fzf's `--with-nth` picks, e.g. `4` or `2..`.
+fn with_nth(line: &str, with_nth: &str, delimiter: &str) -> String {
+    let fields: Vec<&str> = line.split(delimiter).collect();
+
+    with_nth
+        .split(',')
+        .filter_map(|range| {
+            let (start, end) = match range.split_once("..") {
+                Some((start, end)) => (
+                    start.parse().unwrap_or(1),
+                    end.parse().unwrap_or(fields.len()),
+                ),
+                None => {
+                    let field = range.parse().ok()?;
+                    (field, field)
+                }
+            };
+
+            fields
+                .get(start.checked_sub(1)?..end.min(fields.len()))
+                .map(|fields| fields.join(delimiter))
+        })
+        .collect::<Vec<_>>()
+        .join(delimiter)
+}
+
+/// Scores how well `candidate` matches a single search term. Like fzf, the characters of the
+/// term have to appear in order, and runs of them or ones at the start of a word count more.
+/// Lowercase terms match any case.
+fn term_score(candidate: &str, term: &str) -> Option<i64> {
+    let ignore_case = !term.chars().any(char::is_uppercase);
+    let mut term = term.chars().peekable();
+
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut run = 0;
+
+    for c in candidate.chars() {
+        let Some(&wanted) = term.peek() else {
+            break;
+        };
+
+        let matches = if ignore_case {
+            c.to_lowercase().eq(wanted.to_lowercase())
+        } else {
+            c == wanted
+        };
+
+        if matches {
+            term.next();
+            run += 1;
+            score += 1 + run * 2;
+
+            if previous.is_none_or(|previous| !previous.is_alphanumeric()) {
+                score += 4;
+            }
+        } else {
+            run = 0;
+        }
+
+        previous = Some(c);
+    }
+
+    term.peek().is_none().then_some(score)
+}
+
+/// Every whitespace separated term of the query has to match, as in fzf.
+fn score(candidate: &str, query: &str) -> Option<i64> {
+    query
+        .split_whitespace()
+        .map(|term| term_score(candidate, term))
+        .sum()
+}
+
+/// Cuts `text` to `width` columns.
+fn fit(text: &str, width: usize) -> String {
+    let mut used = 0;
+
+    text.chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
+
+struct Picker<'a> {
+    /// The lines fzf would have been given, with what is shown of each
+    items: Vec<(&'a str, String)>,
+    query: String,
+    /// Indices into `items` of the ones matching the query, best first
+    matches: Vec<usize>,
+    /// Indices into `items` of the ones marked with Tab, in the order they were marked
+    marked: Vec<usize>,
+    selected: usize,
+    offset: usize,
+}
+
+impl<'a> Picker<'a> {
+    fn filter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, shown))| Some((score(shown, &self.query)?, i)))
+            .collect();
+
+        // Stable, so equally good matches keep their order
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.offset = 0;
+    }
+
+    fn draw(&mut self, stderr: &mut Stderr, args: &FzfArgs) -> std::io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+
+        queue!(stderr, MoveTo(0, 0), Clear(ClearType::All))?;
+
+        let prompt = args.prompt.as_deref().unwrap_or("> ");
+        queue!(
+            stderr,
+            Print(fit(&format!("{}{}", prompt, self.query), width))
+        )?;
+
+        let mut lines = vec![format!("  {}/{}", self.matches.len(), self.items.len())];
+        lines.extend(
+            args.header
+                .iter()
+                .flat_map(|header| header.lines())
+                .map(String::from),
+        );
+
+        for (row, line) in lines.iter().enumerate() {
+            queue!(
+                stderr,
+                MoveTo(0, row as u16 + 1),
+                SetAttribute(Attribute::Dim),
+                Print(fit(line, width)),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+
+        let first_row = lines.len() + 1;
+        let rows = (height as usize).saturating_sub(first_row).max(1);
+
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + rows {
+            self.offset = self.selected + 1 - rows;
+        }
+
+        for (row, &item) in self.matches.iter().skip(self.offset).take(rows).enumerate() {
+            let mark = if !args.multi {
+                ""
+            } else if self.marked.contains(&item) {
+                "+"
+            } else {
+                " "
+            };
+
+            let shown = fit(&self.items[item].1, width.saturating_sub(2 + mark.len()));
+
+            queue!(stderr, MoveTo(0, (first_row + row) as u16))?;
+
+            if self.offset + row == self.selected {
+                queue!(
+                    stderr,
+                    SetAttribute(Attribute::Reverse),
+                    Print(format!(">{} {}", mark, shown)),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(stderr, Print(format!(" {} {}", mark, shown)))?;
+            }
+        }
+
+        let cursor = (prompt.chars().count() + self.query.chars().count()).min(width);
+        queue!(stderr, MoveTo(cursor as u16, 0), Show)?;
+
+        stderr.flush()
+    }
+
+    fn move_selection(&mut self, step: isize, cycle: bool) {
+        let len = self.matches.len() as isize;
+
+        if len == 0 {
+            return;
+        }
+
+        let selected = self.selected as isize + step;
+
+        self.selected = if cycle {
+            selected.rem_euclid(len)
+        } else {
+            selected.clamp(0, len - 1)
+        } as usize;
+    }
+
+    /// The marked lines, or the highlighted one when none are, as fzf prints them.
+    fn picked(&self) -> String {
+        if !self.marked.is_empty() {
+            return self
+                .marked
+                .iter()
+                .map(|&item| self.items[item].0)
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        self.matches
+            .get(self.selected)
+            .map(|&item| self.items[item].0.to_string())
+            .unwrap_or_default()
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(&item) = self.matches.get(self.selected) else {
+            return;
+        };
+
+        match self.marked.iter().position(|&marked| marked == item) {
+            Some(i) => {
+                self.marked.remove(i);
+            }
+            None => self.marked.push(item),
+        }
+
+        self.move_selection(1, false);
+    }
+
+    /// Handles a key, returning the picked line once Enter is pressed, or an empty one when
+    /// picking was cancelled, which is what fzf prints then.
+    fn handle_key(&mut self, key: KeyEvent, args: &FzfArgs) -> Option<String> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        // Like fzf, the keys from `expect` pick the line too and are printed before it
+        if let (true, KeyCode::Char(c), Some(expect)) = (ctrl, key.code, &args.expect) {
+            let name = format!("ctrl-{}", c);
+
+            if expect.split(',').any(|key| key == name) {
+                return Some(format!("{}\n{}", name, self.picked()));
+            }
+        }
+
+        match key.code {
+            KeyCode::Enter => return Some(self.picked()),
+            KeyCode::Esc => return Some(String::new()),
+            KeyCode::Char('c' | 'g' | 'q') if ctrl => return Some(String::new()),
+            KeyCode::Char('j' | 'n') if ctrl => self.move_selection(1, args.cycle),
+            KeyCode::Char('k' | 'p') if ctrl => self.move_selection(-1, args.cycle),
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.filter();
+            }
+            KeyCode::Tab if args.multi => self.toggle_mark(),
+            KeyCode::Down => self.move_selection(1, args.cycle),
+            KeyCode::Up => self.move_selection(-1, args.cycle),
+            KeyCode::PageDown => self.move_selection(10, false),
+            KeyCode::PageUp => self.move_selection(-10, false),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+/// A minimal fuzzy picker taking the same arguments as fzf, used when fzf isn't installed.
+/// It draws on stderr like fzf does on the terminal, and returns the picked line as fzf
+/// would print it. Previews aren't supported.
+pub fn pick(args: &FzfArgs) -> anyhow::Result<String> {
+    let input = args.process_stdin.as_deref().unwrap_or_default();
+    let delimiter = args.delimiter.as_deref().unwrap_or(" ");
+
+    let items = input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let shown = match &args.with_nth {
+                Some(fields) => with_nth(line, fields, delimiter),
+                None => line.to_string(),
+            };
+
+            (line, shown)
+        })
+        .collect();
+
+    let mut picker = Picker {
+        items,
+        query: args.query.clone().unwrap_or_default(),
+        matches: vec![],
+        marked: vec![],
+        selected: 0,
+        offset: 0,
+    };
+
+    picker.filter();
+
+    debug!(
+        "Picking from {} lines with the built-in picker",
+        picker.items.len()
+    );
+
+    let mut stderr = std::io::stderr();
+
+    terminal::enable_raw_mode()?;
+    queue!(stderr, EnterAlternateScreen, Hide)?;
+
+    let picked = (|| -> anyhow::Result<String> {
+        loop {
+            picker.draw(&mut stderr, args)?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                if let Some(picked) = picker.handle_key(key, args) {
+                    return Ok(picked);
+                }
+            }
+        }
+    })();
+
+    queue!(stderr, Show, LeaveAlternateScreen)?;
+    stderr.flush()?;
+    terminal::disable_raw_mode()?;
+
+    picked
+}
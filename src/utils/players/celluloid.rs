@@ -1,4 +1,8 @@
-use crate::utils::SpawnError;
+use crate::utils::{
+    players::{dry_run, installed::find_player},
+    SpawnError,
+};
+use lobster_core::redact::redact;
 use log::{debug, error};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -11,10 +15,10 @@ pub struct Celluloid {
 impl Celluloid {
     pub fn new() -> Self {
         debug!("Initializing new celluloid instance.");
-        Self {
-            executable: "celluloid".to_string(),
-            args: vec![],
-        }
+        let (executable, args) =
+            find_player("celluloid").unwrap_or_else(|| (String::from("celluloid"), vec![]));
+
+        Self { executable, args }
     }
 }
 
@@ -23,6 +27,15 @@ pub struct CelluloidArgs {
     pub url: String,
     pub mpv_sub_files: Option<Vec<String>>,
     pub mpv_force_media_title: Option<String>,
+    pub mpv_start: Option<String>,
+    pub mpv_sub_delay: Option<f32>,
+    pub mpv_sub_font_size: Option<u32>,
+    pub mpv_sub_scale: Option<f32>,
+    pub mpv_fullscreen: bool,
+    pub mpv_loop_file: bool,
+    pub mpv_save_position_on_quit: bool,
+    pub mpv_watch_later_dir: Option<String>,
+    pub extra_args: Vec<String>,
 }
 
 pub trait CelluloidPlay {
@@ -31,7 +44,7 @@ pub trait CelluloidPlay {
 
 impl CelluloidPlay for Celluloid {
     fn play(&self, args: CelluloidArgs) -> Result<(), SpawnError> {
-        debug!("Preparing to play video with URL: {:?}", args.url);
+        debug!("Preparing to play video with URL: {}", redact(&args.url));
 
         let mut temp_args = self.args.clone();
         temp_args.push(args.url.clone());
@@ -50,6 +63,43 @@ impl CelluloidPlay for Celluloid {
             temp_args.push(format!("--mpv-force-media-title={}", mpv_force_media_title));
         }
 
+        if let Some(mpv_start) = args.mpv_start {
+            temp_args.push(format!("--mpv-start={}", mpv_start));
+        }
+
+        if let Some(mpv_sub_delay) = args.mpv_sub_delay {
+            temp_args.push(format!("--mpv-sub-delay={}", mpv_sub_delay));
+        }
+
+        if let Some(mpv_sub_font_size) = args.mpv_sub_font_size {
+            temp_args.push(format!("--mpv-sub-font-size={}", mpv_sub_font_size));
+        }
+
+        if let Some(mpv_sub_scale) = args.mpv_sub_scale {
+            temp_args.push(format!("--mpv-sub-scale={}", mpv_sub_scale));
+        }
+
+        if args.mpv_fullscreen {
+            temp_args.push("--mpv-fullscreen".to_string());
+        }
+
+        if args.mpv_loop_file {
+            temp_args.push("--mpv-loop-file=inf".to_string());
+        }
+
+        if args.mpv_save_position_on_quit {
+            temp_args.push("--mpv-save-position-on-quit".to_string());
+        }
+
+        if let Some(mpv_watch_later_dir) = args.mpv_watch_later_dir {
+            temp_args.push(format!("--mpv-watch-later-dir={}", mpv_watch_later_dir));
+        }
+
+        if !args.extra_args.is_empty() {
+            debug!("Adding extra arguments: {:?}", args.extra_args);
+            temp_args.extend(args.extra_args);
+        }
+
         let running = Arc::new(AtomicBool::new(true));
         let r = running.clone();
 
@@ -60,13 +110,17 @@ impl CelluloidPlay for Celluloid {
             Err(_) => {}
         }
 
-        std::process::Command::new(&self.executable)
-            .args(temp_args)
-            .status()
-            .map_err(|e| {
-                error!("Failed to spawn iina process: {}", e);
-                SpawnError::IOError(e)
-            })?;
+        let mut command = std::process::Command::new(&self.executable);
+        command.args(temp_args);
+
+        if dry_run(&command) {
+            return Ok(());
+        }
+
+        command.status().map_err(|e| {
+            error!("Failed to spawn iina process: {}", e);
+            SpawnError::IOError(e)
+        })?;
 
         Ok(())
     }
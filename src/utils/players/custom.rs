@@ -0,0 +1,80 @@
+use crate::utils::{players::dry_run, template::expand_command, SpawnError};
+use lobster_core::redact::redact;
+use log::{debug, error};
+use std::io::{Error, ErrorKind};
+
+pub struct Custom {
+    pub command: String,
+}
+
+impl Custom {
+    pub fn new(command: &str) -> Self {
+        debug!("Initializing new custom player instance: {}", command);
+        Self {
+            command: command.to_string(),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct CustomArgs {
+    pub url: String,
+    pub title: String,
+    pub sub_files: Option<Vec<String>>,
+    pub extra_args: Vec<String>,
+}
+
+pub trait CustomPlay {
+    fn play(&self, args: CustomArgs) -> Result<(), SpawnError>;
+}
+
+impl CustomPlay for Custom {
+    fn play(&self, args: CustomArgs) -> Result<(), SpawnError> {
+        debug!("Preparing to play video with URL: {}", redact(&args.url));
+
+        let sub_files = args.sub_files.unwrap_or_default();
+
+        let mut command = expand_command(
+            &self.command,
+            &[
+                ("url", vec![args.url]),
+                ("title", vec![args.title]),
+                ("subtitle", sub_files.first().cloned().into_iter().collect()),
+                ("subtitles", sub_files),
+            ],
+        );
+
+        if !args.extra_args.is_empty() {
+            debug!("Adding extra arguments: {:?}", args.extra_args);
+            command.extend(args.extra_args);
+        }
+
+        let Some((executable, temp_args)) = command.split_first() else {
+            error!("`player_command` is empty");
+            return Err(SpawnError::IOError(Error::new(
+                ErrorKind::InvalidInput,
+                "empty player command",
+            )));
+        };
+
+        debug!(
+            "Executing custom player command: {} {}",
+            executable,
+            redact(&format!("{:?}", temp_args))
+        );
+
+        let mut command = std::process::Command::new(executable);
+        command.args(temp_args);
+
+        if dry_run(&command) {
+            return Ok(());
+        }
+
+        command.status().map_err(|e| {
+            error!("Failed to spawn {}: {}", executable, e);
+            SpawnError::IOError(e)
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{utils::nfo::escape, CLIENT};
+use anyhow::{anyhow, Context};
+use lobster_core::redact::redact;
+use log::debug;
+use regex::Regex;
+use reqwest::Url;
+use std::{
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+
+const AV_TRANSPORT: &str = "urn:schemas-upnp-org:service:AVTransport:1";
+
+/// How long renderers get to answer the search
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often a renderer is asked whether it's still playing
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A TV or other DLNA renderer on the network that can be told to play a URL.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub name: String,
+    /// Where the AVTransport actions are posted to
+    pub control_url: String,
+}
+
+/// The `LOCATION` headers of the SSDP answers, each pointing at a renderer's description.
+fn search_locations() -> anyhow::Result<Vec<String>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").context("Failed to open a UDP socket")?;
+    socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_ADDRESS, AV_TRANSPORT
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDRESS)?;
+
+    let started = Instant::now();
+    let mut locations = vec![];
+    let mut buffer = [0; 2048];
+
+    while started.elapsed() < DISCOVERY_TIMEOUT {
+        let Ok((length, from)) = socket.recv_from(&mut buffer) else {
+            continue;
+        };
+
+        let response = String::from_utf8_lossy(&buffer[..length]);
+
+        let location = response.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("location")
+                .then(|| value.trim().to_string())
+        });
+
+        if let Some(location) = location.filter(|location| !locations.contains(location)) {
+            debug!("DLNA renderer at {} describes itself at {}", from, location);
+            locations.push(location);
+        }
+    }
+
+    Ok(locations)
+}
+
+/// The renderer described at `location`, if it has an AVTransport service.
+async fn describe(location: &str) -> anyhow::Result<Option<Renderer>> {
+    let description = CLIENT.get(location).send().await?.text().await?;
+
+    let tag = |name: &str, text: &str| {
+        Regex::new(&format!(r"(?s)<{0}>\s*(.*?)\s*</{0}>", name))
+            .unwrap()
+            .captures(text)
+            .map(|captures| captures[1].to_string())
+    };
+
+    let Some(control_url) = Regex::new(r"(?s)<service>(.*?)</service>")
+        .unwrap()
+        .captures_iter(&description)
+        .map(|captures| captures[1].to_string())
+        .find(|service| service.contains(AV_TRANSPORT))
+        .and_then(|service| tag("controlURL", &service))
+    else {
+        return Ok(None);
+    };
+
+    let base = tag("URLBase", &description).unwrap_or_else(|| location.to_string());
+    let control_url = Url::parse(&base)?.join(&control_url)?.to_string();
+
+    Ok(Some(Renderer {
+        name: tag("friendlyName", &description).unwrap_or_else(|| location.to_string()),
+        control_url,
+    }))
+}
+
+/// Looks for DLNA renderers on the local network for a few seconds.
+pub async fn discover() -> anyhow::Result<Vec<Renderer>> {
+    let locations = tokio::task::spawn_blocking(search_locations).await??;
+
+    let mut renderers = vec![];
+
+    for location in locations {
+        match describe(&location).await {
+            Ok(Some(renderer)) => renderers.push(renderer),
+            Ok(None) => debug!("{} has no AVTransport service", location),
+            Err(e) => debug!("Failed to read the description at {}: {}", location, e),
+        }
+    }
+
+    Ok(renderers)
+}
+
+impl Renderer {
+    async fn action(&self, action: &str, arguments: &str) -> anyhow::Result<String> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body><u:{0} xmlns:u="{1}"><InstanceID>0</InstanceID>{2}</u:{0}></s:Body></s:Envelope>"#,
+            action, AV_TRANSPORT, arguments
+        );
+
+        debug!("Sending {} to {}", action, self.name);
+
+        let response = CLIENT
+            .post(&self.control_url)
+            .header("Content-Type", r#"text/xml; charset="utf-8""#)
+            .header("SOAPAction", format!(r#""{}#{}""#, AV_TRANSPORT, action))
+            .body(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "{} refused {} ({})",
+                self.name,
+                action,
+                response.status()
+            ));
+        }
+
+        Ok(response.text().await?)
+    }
+
+    /// Has the renderer load `url` and start playing it, with `title` shown where the TV
+    /// shows one.
+    pub async fn play(&self, url: &str, title: &str) -> anyhow::Result<()> {
+        debug!("Casting {} to {}", redact(url), self.name);
+
+        let metadata = format!(
+            r#"<DIDL-Lite xmlns="urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:upnp="urn:schemas-upnp-org:metadata-1-0/upnp/"><item id="0" parentID="-1" restricted="1"><dc:title>{}</dc:title><upnp:class>object.item.videoItem</upnp:class><res protocolInfo="http-get:*:application/vnd.apple.mpegurl:*">{}</res></item></DIDL-Lite>"#,
+            escape(title),
+            escape(url)
+        );
+
+        self.action(
+            "SetAVTransportURI",
+            &format!(
+                "<CurrentURI>{}</CurrentURI><CurrentURIMetaData>{}</CurrentURIMetaData>",
+                escape(url),
+                escape(&metadata)
+            ),
+        )
+        .await?;
+
+        self.action("Play", "<Speed>1</Speed>").await?;
+
+        Ok(())
+    }
+
+    /// Waits until the renderer stops playing, or can't be reached anymore.
+    pub async fn wait(&self) {
+        let state_re = Regex::new(r"<CurrentTransportState>\s*(\w+)").unwrap();
+
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let state = match self.action("GetTransportInfo", "").await {
+                Ok(response) => state_re
+                    .captures(&response)
+                    .map(|captures| captures[1].to_string())
+                    .unwrap_or_default(),
+                Err(e) => {
+                    debug!("Lost {}: {}", self.name, e);
+                    return;
+                }
+            };
+
+            if !["PLAYING", "PAUSED_PLAYBACK", "TRANSITIONING"].contains(&state.as_str()) {
+                debug!("{} is {}", self.name, state);
+                return;
+            }
+        }
+    }
+}
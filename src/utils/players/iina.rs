@@ -1,6 +1,7 @@
-use crate::utils::SpawnError;
+use crate::utils::{players::dry_run, SpawnError};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use lobster_core::redact::redact;
 use log::{debug, error};
 
 pub struct Iina {
@@ -25,6 +26,15 @@ pub struct IinaArgs {
     pub keep_running: bool,
     pub mpv_sub_files: Option<Vec<String>>,
     pub mpv_force_media_title: Option<String>,
+    pub mpv_start: Option<String>,
+    pub mpv_sub_delay: Option<f32>,
+    pub mpv_sub_font_size: Option<u32>,
+    pub mpv_sub_scale: Option<f32>,
+    pub mpv_fullscreen: bool,
+    pub mpv_loop_file: bool,
+    pub mpv_save_position_on_quit: bool,
+    pub mpv_watch_later_dir: Option<String>,
+    pub extra_args: Vec<String>,
 }
 
 pub trait IinaPlay {
@@ -33,7 +43,7 @@ pub trait IinaPlay {
 
 impl IinaPlay for Iina {
     fn play(&self, args: IinaArgs) -> Result<(), SpawnError> {
-        debug!("Preparing to play video with URL: {:?}", args.url);
+        debug!("Preparing to play video with URL: {}", redact(&args.url));
 
         let mut temp_args = self.args.clone();
         temp_args.push(args.url.clone());
@@ -60,6 +70,43 @@ impl IinaPlay for Iina {
             temp_args.push(format!("--mpv-force-media-title={}", mpv_force_media_title));
         }
 
+        if let Some(mpv_start) = args.mpv_start {
+            temp_args.push(format!("--mpv-start={}", mpv_start));
+        }
+
+        if let Some(mpv_sub_delay) = args.mpv_sub_delay {
+            temp_args.push(format!("--mpv-sub-delay={}", mpv_sub_delay));
+        }
+
+        if let Some(mpv_sub_font_size) = args.mpv_sub_font_size {
+            temp_args.push(format!("--mpv-sub-font-size={}", mpv_sub_font_size));
+        }
+
+        if let Some(mpv_sub_scale) = args.mpv_sub_scale {
+            temp_args.push(format!("--mpv-sub-scale={}", mpv_sub_scale));
+        }
+
+        if args.mpv_fullscreen {
+            temp_args.push("--mpv-fullscreen".to_string());
+        }
+
+        if args.mpv_loop_file {
+            temp_args.push("--mpv-loop-file=inf".to_string());
+        }
+
+        if args.mpv_save_position_on_quit {
+            temp_args.push("--mpv-save-position-on-quit".to_string());
+        }
+
+        if let Some(mpv_watch_later_dir) = args.mpv_watch_later_dir {
+            temp_args.push(format!("--mpv-watch-later-dir={}", mpv_watch_later_dir));
+        }
+
+        if !args.extra_args.is_empty() {
+            debug!("Adding extra arguments: {:?}", args.extra_args);
+            temp_args.extend(args.extra_args);
+        }
+
         let running = Arc::new(AtomicBool::new(true));
         let r = running.clone();
 
@@ -70,13 +117,17 @@ impl IinaPlay for Iina {
             Err(_) => {}
         }
 
-        std::process::Command::new(&self.executable)
-            .args(temp_args)
-            .status()
-            .map_err(|e| {
-                error!("Failed to spawn iina process: {}", e);
-                SpawnError::IOError(e)
-            })?;
+        let mut command = std::process::Command::new(&self.executable);
+        command.args(temp_args);
+
+        if dry_run(&command) {
+            return Ok(());
+        }
+
+        command.status().map_err(|e| {
+            error!("Failed to spawn iina process: {}", e);
+            SpawnError::IOError(e)
+        })?;
 
         Ok(())
     }
@@ -0,0 +1,75 @@
+use log::debug;
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Flathub ids of the players that are commonly installed as Flatpaks.
+fn flatpak_ids(command: &str) -> &'static [&'static str] {
+    match command {
+        "mpv" => &["io.mpv.Mpv"],
+        "celluloid" => &["io.github.celluloid_player.Celluloid", "org.gnome.Celluloid"],
+        "vlc" => &["org.videolan.VLC"],
+        _ => &[],
+    }
+}
+
+fn on_path(command: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            dir.join(command).is_file() || dir.join(format!("{}.exe", command)).is_file()
+        })
+    })
+}
+
+fn flatpak_installed(app_id: &str) -> bool {
+    Command::new("flatpak")
+        .args(["info", app_id])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Finds how to launch `command`: from PATH, as a Flatpak app or as a Snap whose
+/// `/snap/bin` isn't on PATH. Returns the executable and the arguments that have to go
+/// before the player's own, or `None` when the player isn't installed at all.
+pub fn find_player(command: &str) -> Option<(String, Vec<String>)> {
+    if on_path(command) {
+        return Some((command.to_string(), vec![]));
+    }
+
+    if on_path("flatpak") {
+        if let Some(app_id) = flatpak_ids(command)
+            .iter()
+            .find(|app_id| flatpak_installed(app_id))
+        {
+            debug!("Found {} as Flatpak {}", command, app_id);
+
+            // Flatpaks get a /tmp of their own, so the subtitles, watch-later files and IPC
+            // socket lobster keeps in its temp directory have to be shared with the player
+            let shared_dir = std::env::temp_dir().join("lobster-rs");
+            let _ = std::fs::create_dir_all(&shared_dir);
+
+            return Some((
+                String::from("flatpak"),
+                vec![
+                    String::from("run"),
+                    format!("--filesystem={}", shared_dir.display()),
+                    app_id.to_string(),
+                ],
+            ));
+        }
+    }
+
+    if Path::new("/snap/bin").join(command).exists() {
+        debug!("Found {} as a Snap", command);
+        return Some((
+            String::from("snap"),
+            vec![String::from("run"), command.to_string()],
+        ));
+    }
+
+    debug!("{} isn't installed", command);
+    None
+}
@@ -2,3 +2,47 @@ pub mod mpv;
 pub mod vlc;
 pub mod iina;
 pub mod celluloid;
+pub mod custom;
+pub mod dlna;
+pub mod installed;
+
+use std::{process::Command, sync::OnceLock};
+
+static DRY_RUN: OnceLock<bool> = OnceLock::new();
+
+/// With `--dry-run` the players print the command they'd run instead of running it.
+pub fn set_dry_run(dry_run: bool) {
+    let _ = DRY_RUN.set(dry_run);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.get().copied().unwrap_or_default()
+}
+
+/// Quotes `arg` for a POSIX shell, leaving it alone when there's nothing to quote.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c);
+
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Prints `command` so it can be pasted into a shell when this is a dry run, returning
+/// whether it was, in which case it mustn't be run.
+pub fn dry_run(command: &Command) -> bool {
+    if !is_dry_run() {
+        return false;
+    }
+
+    let words: Vec<String> = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|word| shell_quote(&word.to_string_lossy()))
+        .collect();
+
+    println!("{}", words.join(" "));
+
+    true
+}
@@ -1,6 +1,12 @@
-use crate::utils::SpawnError;
+use crate::utils::{
+    players::{dry_run, installed::find_player, is_dry_run},
+    SpawnError,
+};
 use crossterm::style::Stylize;
+use lobster_core::redact::redact;
 use log::{debug, error};
+use regex::Regex;
+use std::io::Read;
 use std::process::{Child, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -13,10 +19,10 @@ pub struct Mpv {
 impl Mpv {
     pub fn new() -> Self {
         debug!("Initializing new mpv instance.");
-        Self {
-            executable: "mpv".to_string(),
-            args: vec![],
-        }
+        let (executable, args) =
+            find_player("mpv").unwrap_or_else(|| (String::from("mpv"), vec![]));
+
+        Self { executable, args }
     }
 }
 
@@ -32,19 +38,73 @@ pub struct MpvArgs {
     pub write_filename_in_watch_later_config: bool,
     pub watch_later_dir: Option<String>,
     pub input_ipc_server: Option<String>,
+    pub start: Option<String>,
+    pub sub_delay: Option<f32>,
+    pub sub_font_size: Option<u32>,
+    pub sub_scale: Option<f32>,
+    pub fullscreen: bool,
+    pub loop_file: bool,
+    pub subtitle_fallback: Option<String>,
+    pub profile: Option<String>,
+    pub config_dir: Option<String>,
+    pub extra_args: Vec<String>,
+}
+
+const STATUS_PATTERN: &str = r#"AV:\s([0-9:]*) / ([0-9:]*)"#;
+
+/// Reads mpv's status line until it exits, returning the last playback position it reported.
+pub fn track_position(child: &mut Child) -> Result<Option<String>, SpawnError> {
+    let re = Regex::new(STATUS_PATTERN).unwrap();
+    let mut position = None;
+
+    if let Some(mut stdout) = child.stdout.take() {
+        let mut buffer = [0; 256];
+
+        loop {
+            let bytes_read = stdout.read(&mut buffer).map_err(|e| {
+                error!("Failed to read mpv output: {}", e);
+                SpawnError::IOError(e)
+            })?;
+
+            if bytes_read == 0 {
+                break;
+            }
+
+            let content = String::from_utf8_lossy(&buffer[..bytes_read]);
+
+            if let Some(captures) = re.captures_iter(&content).last() {
+                position = Some(captures[1].to_string());
+            }
+        }
+    }
+
+    debug!("Last reported mpv position: {:?}", position);
+
+    Ok(position)
 }
 
 pub trait MpvPlay {
-    fn play(&self, args: MpvArgs) -> Result<Child, SpawnError>;
+    /// Starts mpv, or only prints how it would have been started on a dry run.
+    fn play(&self, args: MpvArgs) -> Result<Option<Child>, SpawnError>;
 }
 
 impl MpvPlay for Mpv {
-    fn play(&self, args: MpvArgs) -> Result<Child, SpawnError> {
-        debug!("Preparing to play video with URL: {:?}", args.url);
+    fn play(&self, args: MpvArgs) -> Result<Option<Child>, SpawnError> {
+        debug!("Preparing to play video with URL: {}", redact(&args.url));
 
         let mut temp_args = self.args.clone();
         temp_args.push(args.url.clone());
 
+        if let Some(config_dir) = args.config_dir {
+            debug!("Setting config directory: {}", config_dir);
+            temp_args.push(format!("--config-dir={}", config_dir));
+        }
+
+        if let Some(profile) = args.profile {
+            debug!("Setting profile: {}", profile);
+            temp_args.push(format!("--profile={}", profile));
+        }
+
         if args.quiet {
             debug!("Adding quiet flag");
             temp_args.push(String::from("--quiet"));
@@ -62,7 +122,7 @@ impl MpvPlay for Mpv {
                 .collect::<Vec<_>>()
                 .join(":");
 
-            debug!("Adding subtitle files: {}", temp_sub_files);
+            debug!("Adding subtitle files: {}", redact(&temp_sub_files));
             temp_args.push(format!("--sub-files={}", temp_sub_files));
         }
 
@@ -90,21 +150,69 @@ impl MpvPlay for Mpv {
             temp_args.push(format!("--input-ipc-server={}", input_ipc_server));
         }
 
+        if let Some(start) = args.start {
+            debug!("Setting start position: {}", start);
+            temp_args.push(format!("--start={}", start));
+        }
+
+        if let Some(sub_delay) = args.sub_delay {
+            debug!("Setting subtitle delay: {}", sub_delay);
+            temp_args.push(format!("--sub-delay={}", sub_delay));
+        }
+
+        if let Some(sub_font_size) = args.sub_font_size {
+            debug!("Setting subtitle font size: {}", sub_font_size);
+            temp_args.push(format!("--sub-font-size={}", sub_font_size));
+        }
+
+        if let Some(sub_scale) = args.sub_scale {
+            debug!("Setting subtitle scale: {}", sub_scale);
+            temp_args.push(format!("--sub-scale={}", sub_scale));
+        }
+
+        if args.fullscreen {
+            debug!("Adding fullscreen flag");
+            temp_args.push(String::from("--fullscreen"));
+        }
+
+        if args.loop_file {
+            debug!("Looping the video");
+            temp_args.push(String::from("--loop-file=inf"));
+        }
+
         if let Some(sub_file) = args.sub_file {
-            debug!("Adding subtitle file: {}", sub_file);
+            debug!("Adding subtitle file: {}", redact(&sub_file));
             temp_args.push(format!("--sub-file={sub_file}"));
         }
 
         if let Some(force_media_title) = args.force_media_title {
             debug!("Forcing media title: {}", force_media_title);
-            println!(
-                "{}",
-                format!(r#"Now playing "{}""#, force_media_title).blue()
-            );
+
+            let now_playing = match &args.subtitle_fallback {
+                Some(language) => format!(
+                    r#"Now playing "{}" ({} subtitles as a fallback)"#,
+                    force_media_title, language
+                ),
+                None => format!(r#"Now playing "{}""#, force_media_title),
+            };
+
+            // A dry run only prints the command, so it can be used in scripts
+            if !is_dry_run() {
+                println!("{}", now_playing.blue());
+            }
             temp_args.push(format!("--force-media-title={}", force_media_title));
         }
 
-        debug!("Executing mpv command: {} {:?}", self.executable, temp_args);
+        if !args.extra_args.is_empty() {
+            debug!("Adding extra arguments: {:?}", args.extra_args);
+            temp_args.extend(args.extra_args);
+        }
+
+        debug!(
+            "Executing mpv command: {} {}",
+            self.executable,
+            redact(&format!("{:?}", temp_args))
+        );
 
         let running = Arc::new(AtomicBool::new(true));
         let r = running.clone();
@@ -116,10 +224,17 @@ impl MpvPlay for Mpv {
             Err(_) => {}
         }
 
-        std::process::Command::new(&self.executable)
+        let mut command = std::process::Command::new(&self.executable);
+        command.args(temp_args);
+
+        if dry_run(&command) {
+            return Ok(None);
+        }
+
+        command
             .stdout(Stdio::piped())
-            .args(temp_args)
             .spawn()
+            .map(Some)
             .map_err(|e| {
                 error!("Failed to spawn MPV process: {}", e);
                 SpawnError::IOError(e)
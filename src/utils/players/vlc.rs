@@ -1,6 +1,14 @@
-use crate::utils::SpawnError;
+use crate::{
+    utils::{
+        players::{dry_run, installed::find_player},
+        SpawnError,
+    },
+    CLIENT,
+};
 use ctrlc;
+use lobster_core::redact::redact;
 use log::{debug, error};
+use serde::Deserialize;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
@@ -14,10 +22,10 @@ pub struct Vlc {
 impl Vlc {
     pub fn new() -> Self {
         debug!("Initializing new vlc instance.");
-        Self {
-            executable: "vlc".to_string(),
-            args: vec![],
-        }
+        let (executable, args) =
+            find_player("vlc").unwrap_or_else(|| (String::from("vlc"), vec![]));
+
+        Self { executable, args }
     }
 }
 
@@ -26,6 +34,12 @@ pub struct VlcArgs {
     pub url: String,
     pub input_slave: Option<Vec<String>>,
     pub meta_title: Option<String>,
+    pub start_time: Option<f32>,
+    pub fullscreen: bool,
+    pub repeat: bool,
+    pub http_port: Option<u16>,
+    pub http_password: Option<String>,
+    pub extra_args: Vec<String>,
 }
 
 pub trait VlcPlay {
@@ -34,7 +48,7 @@ pub trait VlcPlay {
 
 impl VlcPlay for Vlc {
     fn play(&self, args: VlcArgs) -> Result<(), SpawnError> {
-        debug!("Preparing to play video with URL: {:?}", args.url);
+        debug!("Preparing to play video with URL: {}", redact(&args.url));
 
         let mut temp_args = self.args.clone();
         temp_args.push(args.url.clone());
@@ -42,7 +56,7 @@ impl VlcPlay for Vlc {
         if let Some(input_slave) = &args.input_slave {
             let input_slave_arg = format!(r#"--input-slave="{}""#, input_slave.join("#"));
             temp_args.push(input_slave_arg.clone());
-            debug!("Added input-slave argument: {}", input_slave_arg);
+            debug!("Added input-slave argument: {}", redact(&input_slave_arg));
         }
 
         if let Some(meta_title) = &args.meta_title {
@@ -51,13 +65,42 @@ impl VlcPlay for Vlc {
             debug!("Added meta-title argument: {}", meta_title_arg);
         }
 
+        if let Some(start_time) = args.start_time {
+            debug!("Starting at {} seconds", start_time);
+            temp_args.push(format!("--start-time={}", start_time));
+        }
+
+        if args.fullscreen {
+            debug!("Added fullscreen argument");
+            temp_args.push("--fullscreen".to_string());
+        }
+
+        if args.repeat {
+            debug!("Added repeat argument");
+            temp_args.push("--repeat".to_string());
+        }
+
+        if let (Some(http_port), Some(http_password)) = (args.http_port, &args.http_password) {
+            debug!("Enabling the HTTP interface on port {}", http_port);
+            temp_args.extend([
+                "--extraintf=http".to_string(),
+                "--http-host=127.0.0.1".to_string(),
+                format!("--http-port={}", http_port),
+                format!("--http-password={}", http_password),
+            ]);
+        }
+
+        if !args.extra_args.is_empty() {
+            debug!("Adding extra arguments: {:?}", args.extra_args);
+            temp_args.extend(args.extra_args);
+        }
+
         debug!(
-            "Executing VLC command: {} with args: {:?}",
-            self.executable, temp_args
+            "Executing VLC command: {} with args: {}",
+            self.executable,
+            redact(&format!("{:?}", temp_args))
         );
 
-        debug!("Executing mpv command: {} {:?}", self.executable, temp_args);
-
         let running = Arc::new(AtomicBool::new(true));
         let r = running.clone();
 
@@ -68,14 +111,43 @@ impl VlcPlay for Vlc {
             Err(_) => {}
         }
 
-        std::process::Command::new(&self.executable)
-            .args(temp_args)
-            .status()
-            .map_err(|e| {
-                error!("Failed to spawn VLC process: {}", e);
-                SpawnError::IOError(e)
-            })?;
+        let mut command = std::process::Command::new(&self.executable);
+        command.args(temp_args);
+
+        if dry_run(&command) {
+            return Ok(());
+        }
+
+        command.status().map_err(|e| {
+            error!("Failed to spawn VLC process: {}", e);
+            SpawnError::IOError(e)
+        })?;
 
         Ok(())
     }
 }
+
+#[derive(Deserialize)]
+struct VlcStatus {
+    time: f32,
+    length: f32,
+}
+
+/// Asks VLC's HTTP interface for the playback position and the length of the video, in
+/// seconds. Returns `None` while nothing is playing.
+pub async fn playback_status(http_port: u16, http_password: &str) -> Option<(f32, f32)> {
+    let status: VlcStatus = CLIENT
+        .get(format!(
+            "http://127.0.0.1:{}/requests/status.json",
+            http_port
+        ))
+        .basic_auth("", Some(http_password))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+
+    (status.length > 0.0).then_some((status.time, status.length))
+}
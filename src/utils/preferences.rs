@@ -0,0 +1,78 @@
+use crate::utils::subtitles::LanguageTag;
+use log::debug;
+use std::{path::PathBuf, str::FromStr};
+
+/// The subtitles picked for a show, reused for its next episodes. A `language` of `None`
+/// means subtitles were turned off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitlePreference {
+    pub language: Option<LanguageTag>,
+    pub label: Option<String>,
+}
+
+fn preferences_file() -> PathBuf {
+    dirs::data_local_dir()
+        .expect("Failed to find local dir")
+        .join("lobster-rs/lobster_preferences.txt")
+}
+
+fn parse_preference(line: &str) -> Option<(&str, SubtitlePreference)> {
+    let mut fields = line.splitn(3, '\t');
+    let media_id = fields.next()?;
+    let language = fields.next()?;
+    let label = fields.next()?;
+
+    Some((
+        media_id,
+        SubtitlePreference {
+            language: LanguageTag::from_str(language).ok(),
+            label: (!label.is_empty()).then(|| label.to_string()),
+        },
+    ))
+}
+
+pub fn subtitle_preference(media_id: &str) -> Option<SubtitlePreference> {
+    let preferences = std::fs::read_to_string(preferences_file()).ok()?;
+
+    let preference = preferences
+        .lines()
+        .filter_map(parse_preference)
+        .find(|(id, _)| *id == media_id)
+        .map(|(_, preference)| preference);
+
+    debug!("Subtitle preference for {}: {:?}", media_id, preference);
+
+    preference
+}
+
+pub fn save_subtitle_preference(
+    media_id: &str,
+    preference: &SubtitlePreference,
+) -> anyhow::Result<()> {
+    let preferences_file = preferences_file();
+
+    if let Some(parent) = preferences_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let existing = std::fs::read_to_string(&preferences_file).unwrap_or_default();
+
+    let mut preferences: Vec<String> = existing
+        .lines()
+        .filter(|line| line.split('\t').next() != Some(media_id))
+        .map(String::from)
+        .collect();
+
+    preferences.push(format!(
+        "{}\t{}\t{}",
+        media_id,
+        preference.language.map_or("", |language| language.code),
+        preference.label.as_deref().unwrap_or("")
+    ));
+
+    std::fs::write(preferences_file, preferences.join("\n") + "\n")?;
+
+    debug!("Saved subtitle preference for {}: {:?}", media_id, preference);
+
+    Ok(())
+}
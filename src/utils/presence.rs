@@ -3,7 +3,7 @@ use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::{
-    io::{Cursor, Read},
+    io::{BufRead, BufReader, Cursor, Read},
     process::Child,
 };
 use log::{info, error, warn};
@@ -18,97 +18,115 @@ lazy_static! {
 
 const PATTERN: &str = r#"(\(Paused\)\s)?AV:\s([0-9:]*) / ([0-9:]*) \(([0-9]*)%\)"#;
 
-pub async fn discord_presence(
-    title: &str,
-    season_and_episode_num: Option<(usize, usize)>,
-    mut mpv_child: Child,
-    large_image: &str,
-) -> anyhow::Result<()> {
-    let client_id = "1340948447305535592";
-    let mut client = DiscordIpcClient::new(client_id)
-        .map_err(|_| anyhow!("Failed to create discord IPC client!"))?;
+/// What is currently happening with a title, as shown in the presence.
+pub enum Progress {
+    Playing { position: String, duration: String },
+    Downloading { percentage: Option<u32> },
+}
 
-    match client.connect() {
-        Ok(_) => info!("Client connected to Discord successfully."),
-        Err(_) => warn!("Client failed to connect to Discord, will retry automatically."),
-    };
+/// What to show while a download is running with `--rpc`.
+#[derive(Debug, Clone)]
+pub struct DownloadPresence {
+    pub title: String,
+    pub season_and_episode_num: Option<(usize, usize)>,
+    pub large_image: String,
+}
 
-    let details = match season_and_episode_num {
-        Some((season_num, episode_num)) => format!(
-            "{} - Season {} Episode {}",
-            title,
-            season_num,
-            episode_num + 1
-        ),
-        None => title.to_string(),
-    };
+struct Presence<'a> {
+    client: DiscordIpcClient,
+    connected: bool,
+    title: &'a str,
+    details: String,
+    large_image: &'a str,
+}
 
-    let re: regex::Regex = Regex::new(PATTERN).unwrap();
-    let mut output = mpv_child.stdout.take().unwrap();
-    let buffer = vec![0; 256];
-    let mut cursor = Cursor::new(buffer);
+impl<'a> Presence<'a> {
+    fn connect(
+        title: &'a str,
+        season_and_episode_num: Option<(usize, usize)>,
+        large_image: &'a str,
+    ) -> anyhow::Result<Self> {
+        let client_id = "1340948447305535592";
+        let mut client = DiscordIpcClient::new(client_id)
+            .map_err(|_| anyhow!("Failed to create discord IPC client!"))?;
+
+        match client.connect() {
+            Ok(_) => info!("Client connected to Discord successfully."),
+            Err(_) => warn!("Client failed to connect to Discord, will retry automatically."),
+        };
 
-    // Track connection status
-    let mut connected = true;
+        let details = match season_and_episode_num {
+            Some((season_num, episode_num)) => format!(
+                "{} - Season {} Episode {}",
+                title,
+                season_num,
+                episode_num + 1
+            ),
+            None => title.to_string(),
+        };
 
-    while mpv_child.try_wait()?.is_none() {
-        cursor.set_position(0);
-        let offset = cursor.position();
-        let bread = output.read(&mut cursor.get_mut()[offset as usize..])?;
-        cursor.set_position(offset + bread as u64);
-        let read_data = &cursor.get_ref()[..cursor.position() as usize];
-        let content = String::from_utf8_lossy(&read_data);
-        let captures = re
-            .captures_iter(&content)
-            .last()
-            .ok_or("Could not match the regex pattern.");
-        let position = match captures {
-            Ok(captures) => {
-                let (_paused, av_first, av_second, _percentage) = (
-                    captures.get(1).map_or("", |m| m.as_str()),
-                    captures.get(2).map_or("", |m| m.as_str()),
-                    captures.get(3).map_or("", |m| m.as_str()),
-                    captures.get(4).map_or("", |m| m.as_str()),
-                );
-                format!("{}/{}", av_first, av_second)
+        Ok(Self {
+            client,
+            connected: true,
+            title,
+            details,
+            large_image,
+        })
+    }
+
+    fn update(&mut self, progress: &Progress) {
+        let (details, state) = match progress {
+            Progress::Playing { position, .. } if position.is_empty() => {
+                (self.details.clone(), String::new())
+            }
+            Progress::Playing { position, duration } => {
+                (self.details.clone(), format!("{}/{}", position, duration))
+            }
+            Progress::Downloading {
+                percentage: Some(percentage),
+            } => (
+                format!("Downloading {}", self.details),
+                format!("{}%", percentage),
+            ),
+            Progress::Downloading { percentage: None } => {
+                (format!("Downloading {}", self.details), String::new())
             }
-            Err(_) => String::from(""),
         };
 
         let activity = activity::Activity::new()
             .details(details.as_str())
-            .state(position.as_str())
+            .state(state.as_str())
             .assets(
                 activity::Assets::new()
-                    .large_image(large_image)
-                    .large_text(&title),
+                    .large_image(self.large_image)
+                    .large_text(self.title),
             )
             .buttons(vec![
                 activity::Button::new("Github", "https://github.com/eatmynerds/lobster-rs"),
                 activity::Button::new("Discord", "https://discord.gg/4P2DaJFxbm"),
             ]);
 
-        let result = client.set_activity(activity.clone());
+        let result = self.client.set_activity(activity.clone());
 
         match result {
             Ok(_) => {
-                if !connected {
+                if !self.connected {
                     info!("Reconnected to Discord successfully.");
-                    connected = true;
+                    self.connected = true;
                 }
             }
             Err(_) => {
-                if connected {
+                if self.connected {
                     warn!("Discord connection lost, attempting to reconnect...");
-                    connected = false;
+                    self.connected = false;
                 }
 
-                match client.connect() {
+                match self.client.connect() {
                     Ok(_) => {
                         info!("Reconnected to Discord successfully.");
-                        connected = true;
+                        self.connected = true;
 
-                        if let Err(_) = client.set_activity(activity) {
+                        if let Err(_) = self.client.set_activity(activity) {
                             warn!("Failed to set activity after reconnection.");
                         }
                     }
@@ -120,13 +138,110 @@ pub async fn discord_presence(
         }
     }
 
-    // Try to close connection gracefully
-    if let Err(_) = client.close() {
-        error!("Failed to close Discord connection gracefully.");
+    fn close(mut self) {
+        // Try to close connection gracefully
+        if let Err(_) = self.client.close() {
+            error!("Failed to close Discord connection gracefully.");
+        }
     }
+}
 
-    Ok(())
+pub async fn discord_presence(
+    title: &str,
+    season_and_episode_num: Option<(usize, usize)>,
+    mpv_child: &mut Child,
+    large_image: &str,
+) -> anyhow::Result<Option<String>> {
+    let mut presence = Presence::connect(title, season_and_episode_num, large_image)?;
+
+    let re: regex::Regex = Regex::new(PATTERN).unwrap();
+    let mut output = mpv_child.stdout.take().unwrap();
+    let buffer = vec![0; 256];
+    let mut cursor = Cursor::new(buffer);
+
+    let mut last_position: Option<String> = None;
+
+    while mpv_child.try_wait()?.is_none() {
+        cursor.set_position(0);
+        let offset = cursor.position();
+        let bread = output.read(&mut cursor.get_mut()[offset as usize..])?;
+        cursor.set_position(offset + bread as u64);
+        let read_data = &cursor.get_ref()[..cursor.position() as usize];
+        let content = String::from_utf8_lossy(&read_data);
+        let captures = re
+            .captures_iter(&content)
+            .last()
+            .ok_or("Could not match the regex pattern.");
+        let progress = match captures {
+            Ok(captures) => {
+                let (_paused, av_first, av_second, _percentage) = (
+                    captures.get(1).map_or("", |m| m.as_str()),
+                    captures.get(2).map_or("", |m| m.as_str()),
+                    captures.get(3).map_or("", |m| m.as_str()),
+                    captures.get(4).map_or("", |m| m.as_str()),
+                );
+                last_position = Some(av_first.to_string());
+                Progress::Playing {
+                    position: av_first.to_string(),
+                    duration: av_second.to_string(),
+                }
+            }
+            Err(_) => Progress::Playing {
+                position: String::new(),
+                duration: String::new(),
+            },
+        };
+
+        presence.update(&progress);
+    }
+
+    presence.close();
+
+    Ok(last_position)
 }
 
+/// Shows the progress of an ffmpeg download spawned with `-progress pipe:1`, for a video
+/// `duration` seconds long (0 when unknown). Returns once ffmpeg closes its output.
+pub async fn download_presence(
+    info: &DownloadPresence,
+    ffmpeg_child: &mut Child,
+    duration: f32,
+) -> anyhow::Result<()> {
+    let mut presence = Presence::connect(
+        &info.title,
+        info.season_and_episode_num,
+        &info.large_image,
+    )?;
+
+    presence.update(&Progress::Downloading { percentage: None });
+
+    let output = ffmpeg_child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("ffmpeg output isn't piped"))?;
+
+    let mut percentage = None;
+
+    for line in BufReader::new(output).lines() {
+        let line = line?;
+
+        if let Some(out_time_us) = line.strip_prefix("out_time_us=") {
+            percentage = out_time_us
+                .parse::<f64>()
+                .ok()
+                .filter(|_| duration > 0.0)
+                .map(|out_time_us| {
+                    (out_time_us / 1e6 / duration as f64 * 100.0).clamp(0.0, 100.0) as u32
+                });
+        }
+
+        // ffmpeg ends every progress report with a `progress=` line
+        if line.starts_with("progress=") {
+            presence.update(&Progress::Downloading { percentage });
+        }
+    }
 
+    presence.close();
 
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::utils::config::Config;
+use lobster_core::redact::redact_always;
+use log::debug;
+use regex::Regex;
+use self_update::cargo_crate_version;
+
+const LOG_TAIL_LINES: usize = 200;
+
+/// Keys whose values are left out of the report, wherever they appear, so a `[profile.*]`
+/// table can't leak them either.
+const SECRET_KEYS: [&str; 4] = ["opensubtitles_api_key", "tmdb_api_key", "password", "pin"];
+
+fn redact_secrets(value: &mut toml::Value) {
+    if let toml::Value::Table(table) = value {
+        for (key, value) in table.iter_mut() {
+            if SECRET_KEYS.contains(&key.as_str()) && !value.is_table() {
+                *value = toml::Value::String(String::from("<redacted>"));
+            } else {
+                redact_secrets(value);
+            }
+        }
+    }
+}
+
+fn sanitized_config(config: &Config) -> String {
+    toml::Value::try_from(config)
+        .and_then(|mut config| {
+            redact_secrets(&mut config);
+            toml::to_string(&config)
+        })
+        .unwrap_or_else(|e| format!("Failed to serialize config: {}", e))
+}
+
+/// Builds a paste-ready report for bug reports and saves a copy to the temp directory.
+pub fn generate_report(config: &Config) -> anyhow::Result<String> {
+    let log_file = std::env::temp_dir().join("lobster.log");
+    debug!("Reading debug log from {:?}", log_file);
+
+    let log = std::fs::read_to_string(&log_file)
+        .map(|log| redact_always(&log))
+        .ok();
+
+    let url_re = Regex::new(r#"https?://[^\s"']+"#).unwrap();
+
+    let mut report = String::new();
+
+    report.push_str("### lobster-rs report\n\n");
+    report.push_str(&format!("- Version: {}\n", cargo_crate_version!()));
+    report.push_str(&format!("- Target: {}\n", self_update::get_target()));
+    report.push_str(&format!("- OS: {}\n\n", std::env::consts::OS));
+
+    report.push_str("#### Config\n\n```toml\n");
+    report.push_str(&sanitized_config(config));
+    report.push_str("```\n\n");
+
+    match &log {
+        Some(log) => {
+            let urls: Vec<&str> = log
+                .lines()
+                .rev()
+                .filter_map(|line| url_re.find(line).map(|url| url.as_str()))
+                .take(10)
+                .collect();
+
+            report.push_str("#### Last requested URLs\n\n```\n");
+            for url in urls.iter().rev() {
+                report.push_str(url);
+                report.push('\n');
+            }
+            report.push_str("```\n\n");
+
+            report.push_str("#### Warnings\n\n```\n");
+            for line in log.lines().filter(|line| line.contains("WARN")) {
+                report.push_str(line);
+                report.push('\n');
+            }
+            report.push_str("```\n\n");
+
+            let lines: Vec<&str> = log.lines().collect();
+            let tail = &lines[lines.len().saturating_sub(LOG_TAIL_LINES)..];
+
+            report.push_str("#### Debug log (tail)\n\n```\n");
+            report.push_str(&tail.join("\n"));
+            report.push_str("\n```\n");
+        }
+        None => {
+            report.push_str(&format!(
+                "No debug log found at {}. Run lobster-rs with --debug to reproduce the issue first.\n",
+                log_file.display()
+            ));
+        }
+    }
+
+    let report_file = std::env::temp_dir().join("lobster-report.md");
+    std::fs::write(&report_file, &report)?;
+    debug!("Saved report to {:?}", report_file);
+
+    Ok(report)
+}
@@ -33,6 +33,7 @@ pub struct RofiArgs {
     pub left_display_prompt: Option<String>,
     pub entry_prompt: Option<String>,
     pub display_columns: Option<u32>,
+    pub password: bool,
 }
 
 pub trait RofiSpawn {
@@ -112,6 +113,11 @@ impl RofiSpawn for Rofi {
             debug!("Set display columns to {}", display_columns);
         }
 
+        if args.password {
+            temp_args.push("-password".to_string());
+            debug!("Enabled password mode.");
+        }
+
         if let Some(mesg) = &args.mesg {
             temp_args.push("-mesg".to_string());
             temp_args.push(mesg.to_string());
@@ -0,0 +1,227 @@
+use crate::flixhq::flixhq::{FlixHQInfo, GENRES};
+use crate::providers::tmdb::TmdbEntry;
+use crate::MediaType;
+use std::{cmp::Reverse, str::FromStr};
+
+/// Years a movie was released in, both ends included, e.g. `2010..2020`, `2015..`, `..1999`
+/// or just `1999`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YearRange {
+    pub start: Option<u32>,
+    pub end: Option<u32>,
+}
+
+impl YearRange {
+    fn contains(&self, year: u32) -> bool {
+        self.start.is_none_or(|start| year >= start) && self.end.is_none_or(|end| year <= end)
+    }
+
+    /// The year when the range is a single one, which is all FlixHQ can list.
+    pub fn single(&self) -> Option<u32> {
+        self.start.filter(|start| Some(*start) == self.end)
+    }
+}
+
+impl FromStr for YearRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |year: &str| -> Result<Option<u32>, String> {
+            match year.trim() {
+                "" => Ok(None),
+                year => year
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("{:?} isn't a year", year)),
+            }
+        };
+
+        let range = match s.split_once("..") {
+            Some((start, end)) => YearRange {
+                start: parse(start)?,
+                end: parse(end)?,
+            },
+            None => {
+                let year = parse(s)?;
+                YearRange {
+                    start: year,
+                    end: year,
+                }
+            }
+        };
+
+        match range {
+            YearRange {
+                start: None,
+                end: None,
+            } => Err("expected a year or a range like 2010..2020".to_string()),
+            YearRange {
+                start: Some(start),
+                end: Some(end),
+            } if start > end => Err(format!("{} comes after {}", start, end)),
+            range => Ok(range),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum SearchSort {
+    /// The order FlixHQ returns them in
+    #[default]
+    Relevance,
+    /// Newest first
+    Year,
+    Title,
+}
+
+/// How many search results are fetched, and how they are narrowed down and ordered before
+/// they are shown. Without a query, `--country`, `--actor` and a single `--year` list what
+/// FlixHQ has from there, with them and from then instead.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// Fetch this many pages of search results instead of only the first
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub pages: Option<u32>,
+
+    /// Only show movies or only TV shows
+    #[clap(long = "type", value_enum, value_name = "TYPE")]
+    pub media_type: Option<MediaType>,
+
+    /// Only show movies released in these years, e.g. 2010..2020, 2015.. or 1999 (shows are kept, search results don't have their year). Without a query, lists everything from a single year
+    #[clap(long, value_name = "YEARS")]
+    pub year: Option<YearRange>,
+
+    /// List the movies and TV shows from a country, by its two letter code, e.g. KR
+    #[clap(long, value_name = "CODE", value_parser = country_code, conflicts_with_all = ["query", "recent", "trending", "genre"])]
+    pub country: Option<String>,
+
+    /// List the movies and TV shows an actor played in, e.g. "Tom Hanks"
+    #[clap(long, value_name = "NAME", conflicts_with_all = ["query", "recent", "trending", "genre", "country"])]
+    pub actor: Option<String>,
+
+    /// Sort the results
+    #[clap(long, value_enum, value_name = "ORDER")]
+    pub sort: Option<SearchSort>,
+
+    /// Go straight to the movie or show with this IMDb id, e.g. tt0386676 (needs `tmdb_api_key`)
+    #[clap(long, value_name = "ID", value_parser = imdb_id, conflicts_with_all = ["query", "recent", "trending", "genre", "country", "actor"])]
+    pub imdb: Option<String>,
+
+    /// Go straight to the movie with this TMDB id, or the show with --type tv (needs `tmdb_api_key`)
+    #[clap(long, value_name = "ID", conflicts_with_all = ["imdb", "query", "recent", "trending", "genre", "country", "actor"])]
+    pub tmdb: Option<u64>,
+}
+
+/// The slug of a genre given by its name or slug, e.g. `Sci-Fi & Fantasy` or `scifi-fantasy`.
+pub fn parse_genre(genre: &str) -> Result<String, String> {
+    GENRES
+        .iter()
+        .find(|(name, slug)| {
+            plain_title(name) == plain_title(genre) || plain_title(slug) == plain_title(genre)
+        })
+        .map(|(_, slug)| slug.to_string())
+        .ok_or_else(|| {
+            let slugs: Vec<&str> = GENRES.iter().map(|(_, slug)| *slug).collect();
+            format!("expected one of {}", slugs.join(", "))
+        })
+}
+
+fn country_code(code: &str) -> Result<String, String> {
+    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err("expected a two letter country code like KR or US".to_string());
+    }
+
+    Ok(code.to_uppercase())
+}
+
+fn imdb_id(id: &str) -> Result<String, String> {
+    let digits = id.strip_prefix("tt").unwrap_or(id);
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err("expected an IMDb id like tt0386676".to_string());
+    }
+
+    Ok(format!("tt{}", digits))
+}
+
+/// The release year of a result, only known for movies.
+fn year(result: &FlixHQInfo) -> Option<u32> {
+    match result {
+        FlixHQInfo::Movie(movie) => movie.year.get(..4)?.parse().ok(),
+        FlixHQInfo::Tv(_) => None,
+    }
+}
+
+fn title(result: &FlixHQInfo) -> String {
+    match result {
+        FlixHQInfo::Movie(movie) => movie.title.to_lowercase(),
+        FlixHQInfo::Tv(tv) => tv.title.to_lowercase(),
+    }
+}
+
+/// A title without case, punctuation and spacing, so `Spider-Man: No Way Home` and
+/// `Spider Man No Way Home` are the same.
+fn plain_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The search result that is the movie or show TMDB knows by `entry`: the same kind with
+/// the same title, and for movies from the same year, give or take one.
+pub fn matching_result(results: Vec<FlixHQInfo>, entry: &TmdbEntry) -> Option<FlixHQInfo> {
+    let wanted = plain_title(&entry.title);
+    let wanted_year: Option<u32> = entry
+        .release_date
+        .get(..4)
+        .and_then(|year| year.parse().ok());
+
+    results.into_iter().find(|result| {
+        let same_kind = matches!(
+            (&entry.media_type, result),
+            (MediaType::Movie, FlixHQInfo::Movie(_)) | (MediaType::Tv, FlixHQInfo::Tv(_))
+        );
+
+        let same_year = match (wanted_year, year(result)) {
+            (Some(wanted), Some(year)) => wanted.abs_diff(year) <= 1,
+            _ => true,
+        };
+
+        same_kind && same_year && plain_title(&title(result)) == wanted
+    })
+}
+
+impl SearchFilters {
+    /// Whether `--imdb` or `--tmdb` picks the result.
+    pub fn has_id(&self) -> bool {
+        self.imdb.is_some() || self.tmdb.is_some()
+    }
+
+    pub fn apply(&self, results: Vec<FlixHQInfo>) -> Vec<FlixHQInfo> {
+        let mut results: Vec<FlixHQInfo> = results
+            .into_iter()
+            .filter(|result| {
+                !matches!(
+                    (&self.media_type, result),
+                    (Some(MediaType::Movie), FlixHQInfo::Tv(_))
+                        | (Some(MediaType::Tv), FlixHQInfo::Movie(_))
+                )
+            })
+            .filter(|result| match (self.year, year(result)) {
+                (Some(range), Some(year)) => range.contains(year),
+                _ => true,
+            })
+            .collect();
+
+        // Both sorts are stable, so equal ones keep FlixHQ's order
+        match self.sort.unwrap_or_default() {
+            SearchSort::Relevance => {}
+            SearchSort::Year => results.sort_by_key(|result| Reverse(year(result))),
+            SearchSort::Title => results.sort_by_key(title),
+        }
+
+        results
+    }
+}
@@ -0,0 +1,183 @@
+use crate::flixhq::flixhq::{FlixHQInfo, FlixHQMovie};
+use crate::utils::{
+    config::{Config, SubtitlesMode},
+    config_check::check_config,
+    search_filters::{SearchFilters, SearchSort, YearRange},
+    title::{parse_episode, split_episode, EpisodeNumber},
+};
+use crate::MediaType;
+use anyhow::ensure;
+use log::debug;
+
+/// Parses the file contents directly, as loading a file would put a profile or `LOBSTER_*`
+/// variables from the environment over them.
+fn config_round_trip() -> anyhow::Result<()> {
+    let written = toml::to_string(&Config::new())?;
+    let read_back = toml::to_string(&toml::from_str::<Config>(&written)?)?;
+    ensure!(written == read_back, "config changed after a round-trip");
+
+    Ok(())
+}
+
+fn legacy_config() -> anyhow::Result<()> {
+    let config: Config = toml::from_str("subs_language = \"spanish\"\nno_subs = true\n")?;
+    ensure!(
+        config.subs_language.len() == 1 && config.subs_language[0].code == "es",
+        "single subtitle language not read"
+    );
+    ensure!(config.no_subs, "legacy no_subs not read");
+    ensure!(
+        config.subtitles == SubtitlesMode::Always,
+        "missing keys didn't fall back to their defaults"
+    );
+
+    Ok(())
+}
+
+fn config_problems() -> anyhow::Result<()> {
+    let problems = check_config(
+        "plyer = \"vlc\"\nprovider = \"vidcloud\"\nsubtitles = \"allways\"\n\n[limits]\npin = 1234\n",
+    );
+
+    let expected = [
+        "Unknown key `plyer`, did you mean `player`?",
+        "`provider` can't be \"vidcloud\", it's one of Vidcloud, Upcloud, did you mean `Vidcloud`?",
+        "`subtitles` can't be \"allways\", it's one of always, never, ask, did you mean `always`?",
+    ];
+
+    for problem in expected {
+        ensure!(
+            problems.iter().any(|found| found == problem),
+            "missing {:?} in {:?}",
+            problem,
+            problems
+        );
+    }
+
+    ensure!(
+        problems
+            .iter()
+            .any(|found| found.starts_with("`limits.pin` can't be 1234")),
+        "wrong type in a table not reported"
+    );
+    ensure!(
+        check_config(&toml::to_string(&Config::new())?).is_empty(),
+        "problems found in the default config"
+    );
+
+    Ok(())
+}
+
+fn episode_notation() -> anyhow::Result<()> {
+    let s03e07 = EpisodeNumber {
+        season: 3,
+        episode: 7,
+    };
+
+    let (query, episode) = split_episode("Breaking Bad s03e07");
+    ensure!(
+        query == "Breaking Bad" && episode == Some(s03e07),
+        "episode not taken off the query"
+    );
+
+    let (query, episode) = split_episode("Ocean's Eleven");
+    ensure!(
+        query == "Ocean's Eleven" && episode.is_none(),
+        "query without an episode changed"
+    );
+
+    ensure!(parse_episode("S3E7") == Ok(s03e07), "--episode not parsed");
+    ensure!(parse_episode("S00E01").is_err(), "episode 0 accepted");
+
+    Ok(())
+}
+
+fn search_filters() -> anyhow::Result<()> {
+    ensure!(
+        "2010..2020".parse::<YearRange>()
+            == Ok(YearRange {
+                start: Some(2010),
+                end: Some(2020)
+            }),
+        "year range not parsed"
+    );
+    ensure!(
+        "1999".parse::<YearRange>()
+            == Ok(YearRange {
+                start: Some(1999),
+                end: Some(1999)
+            }),
+        "single year not parsed"
+    );
+    ensure!(
+        "2020..2010".parse::<YearRange>().is_err(),
+        "backwards range accepted"
+    );
+    ensure!("..".parse::<YearRange>().is_err(), "empty range accepted");
+
+    let movie = |title: &str, year: &str| {
+        FlixHQInfo::Movie(FlixHQMovie {
+            title: title.to_string(),
+            year: year.to_string(),
+            media_type: MediaType::Movie,
+            duration: "N/A".to_string(),
+            image: String::new(),
+            id: String::new(),
+        })
+    };
+
+    let filters = SearchFilters {
+        year: Some("2000..".parse().map_err(anyhow::Error::msg)?),
+        sort: Some(SearchSort::Year),
+        ..Default::default()
+    };
+
+    let titles: Vec<String> = filters
+        .apply(vec![
+            movie("Heat", "1995"),
+            movie("Collateral", "2004"),
+            movie("Tenet", "2020"),
+        ])
+        .into_iter()
+        .filter_map(|result| match result {
+            FlixHQInfo::Movie(movie) => Some(movie.title),
+            FlixHQInfo::Tv(_) => None,
+        })
+        .collect();
+
+    ensure!(
+        titles == ["Tenet", "Collateral"],
+        "wrong filtered results {:?}",
+        titles
+    );
+
+    Ok(())
+}
+
+/// Runs the offline checks of `--self-test`, printing one line per check. Returns whether
+/// all of them passed.
+pub async fn self_test() -> bool {
+    let mut checks = vec![
+        ("config round-trip", config_round_trip()),
+        ("legacy config keys", legacy_config()),
+        ("config problems", config_problems()),
+        ("episode notation", episode_notation()),
+        ("search filters", search_filters()),
+    ];
+    checks.extend(lobster_core::self_test::run().await);
+
+    let mut passed = true;
+
+    for (name, result) in checks {
+        match result {
+            Ok(()) => println!("ok      {}", name),
+            Err(e) => {
+                debug!("Self-test check {} failed: {:?}", name, e);
+                println!("FAILED  {}: {}", name, e);
+                passed = false;
+            }
+        }
+    }
+
+    passed
+}
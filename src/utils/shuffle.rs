@@ -0,0 +1,32 @@
+use crate::flixhq::flixhq::{FlixHQ, FlixHQSeason};
+use anyhow::anyhow;
+use lobster_core::http::HttpClient;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// A random number below `len`, which can't be 0.
+pub fn random_index(len: usize) -> usize {
+    RandomState::new().build_hasher().finish() as usize % len
+}
+
+/// A random episode of a show, as the season number and the index of the episode in it.
+/// The season is picked first, so only its episodes have to be fetched.
+pub async fn random_episode<C: HttpClient>(
+    flixhq: &FlixHQ<C>,
+    seasons: &FlixHQSeason,
+) -> anyhow::Result<(usize, usize)> {
+    if seasons.total_seasons == 0 {
+        return Err(anyhow!("The show has no seasons"));
+    }
+
+    let season_number = random_index(seasons.total_seasons) + 1;
+    let episodes = seasons.episodes(flixhq, season_number).await?;
+
+    if episodes.is_empty() {
+        return Err(anyhow!("Season {} has no episodes", season_number));
+    }
+
+    Ok((season_number, random_index(episodes.len())))
+}
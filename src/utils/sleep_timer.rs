@@ -0,0 +1,119 @@
+use log::{debug, error, info};
+use std::{
+    io::{ErrorKind, Write},
+    sync::{Once, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// When `--sleep` stops playback. It covers the whole session, so episodes started by
+/// autoplay don't reset it.
+static DEADLINE: OnceLock<Instant> = OnceLock::new();
+
+static WATCH: Once = Once::new();
+
+/// Parses a duration like `45m`, `1h30m` or `90s`. A plain number is taken as minutes.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let mut input = input.trim().to_string();
+
+    if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+        input.push('m');
+    }
+
+    let mut seconds = 0;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("invalid unit `{}`, use h, m or s", c)),
+        };
+
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("missing a number before `{}`", c))?;
+        seconds += value * unit;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(format!("missing a unit after `{}`", number));
+    }
+
+    if seconds == 0 {
+        return Err(String::from("the duration has to be longer than 0"));
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+pub fn start(duration: Duration) {
+    debug!("Sleep timer set to {:?}", duration);
+    let _ = DEADLINE.set(Instant::now() + duration);
+}
+
+/// Whether the sleep timer has gone off.
+pub fn expired() -> bool {
+    DEADLINE
+        .get()
+        .is_some_and(|deadline| Instant::now() >= *deadline)
+}
+
+/// The IPC socket mpv listens on, so the timer can tell it to quit.
+pub fn ipc_path() -> String {
+    if cfg!(windows) {
+        format!(r"\\.\pipe\lobster-rs-mpv-{}", std::process::id())
+    } else {
+        std::env::temp_dir()
+            .join(format!("lobster-rs/mpv-{}.sock", std::process::id()))
+            .display()
+            .to_string()
+    }
+}
+
+#[cfg(unix)]
+fn connect(ipc_path: &str) -> std::io::Result<impl Write> {
+    std::os::unix::net::UnixStream::connect(ipc_path)
+}
+
+#[cfg(not(unix))]
+fn connect(ipc_path: &str) -> std::io::Result<impl Write> {
+    std::fs::OpenOptions::new().write(true).open(ipc_path)
+}
+
+/// Quits whichever mpv is listening on [`ipc_path`] once the sleep timer goes off. mpv
+/// saves its position on quit, so the progress still ends up in the history. Only the
+/// first call starts the timer.
+pub fn watch() {
+    let Some(deadline) = DEADLINE.get().copied() else {
+        return;
+    };
+
+    if Instant::now() >= deadline {
+        return;
+    }
+
+    WATCH.call_once(|| {
+        std::thread::spawn(move || {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+
+            match connect(&ipc_path())
+                .and_then(|mut socket| socket.write_all(b"{\"command\": [\"quit\"]}\n"))
+            {
+                Ok(()) => info!("Sleep timer is up, stopping playback."),
+                Err(e)
+                    if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) =>
+                {
+                    debug!("Sleep timer is up, but mpv isn't running")
+                }
+                Err(e) => error!("Failed to stop mpv for the sleep timer: {}", e),
+            }
+        });
+    });
+}
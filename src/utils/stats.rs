@@ -0,0 +1,176 @@
+use crate::utils::history::Store;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use std::collections::{HashMap, HashSet};
+
+const HEATMAP_WEEKS: i64 = 53;
+const HEATMAP_LEVELS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const WEEKDAY_LABELS: [&str; 7] = ["   ", "Mon", "   ", "Wed", "   ", "Fri", "   "];
+
+struct WatchEntry {
+    date: NaiveDate,
+    media_id: String,
+    title: String,
+}
+
+/// The sessions saved in the history, with the day each one was on.
+fn read_sessions() -> anyhow::Result<Vec<WatchEntry>> {
+    let entries = Store::load()?
+        .sessions()
+        .iter()
+        .filter_map(|session| {
+            let date = DateTime::from_timestamp(session.watched_at as i64, 0)?
+                .with_timezone(&Local)
+                .date_naive();
+
+            Some(WatchEntry {
+                date,
+                media_id: session.media_id.clone(),
+                title: session.title.clone(),
+            })
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+fn heatmap_level(count: usize, max: usize) -> char {
+    if count == 0 || max == 0 {
+        return HEATMAP_LEVELS[0];
+    }
+
+    let level = (count * (HEATMAP_LEVELS.len() - 1)).div_ceil(max);
+
+    HEATMAP_LEVELS[level.clamp(1, HEATMAP_LEVELS.len() - 1)]
+}
+
+/// Renders a weekly heatmap of the past year, one column per week and one row per weekday.
+fn render_heatmap(per_day: &HashMap<NaiveDate, usize>, today: NaiveDate) -> String {
+    let first_day = today
+        - Duration::weeks(HEATMAP_WEEKS - 1)
+        - Duration::days(today.weekday().num_days_from_sunday() as i64);
+
+    let max = per_day
+        .iter()
+        .filter(|(date, _)| **date >= first_day)
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0);
+
+    let mut month_labels = String::new();
+    let mut last_month = None;
+
+    for week in 0..HEATMAP_WEEKS {
+        let week_start = first_day + Duration::weeks(week);
+
+        if last_month != Some(week_start.month()) {
+            last_month = Some(week_start.month());
+
+            // Keep at least one column between labels, skipping months that would collide
+            if month_labels.is_empty() || week as usize > month_labels.len() {
+                let padding = week as usize - month_labels.len();
+                month_labels.push_str(&" ".repeat(padding));
+                month_labels.push_str(&week_start.format("%b").to_string());
+            }
+        }
+    }
+
+    let mut heatmap = format!("    {}\n", month_labels);
+
+    for (weekday, label) in WEEKDAY_LABELS.iter().enumerate() {
+        heatmap.push_str(label);
+        heatmap.push(' ');
+
+        for week in 0..HEATMAP_WEEKS {
+            let date = first_day + Duration::weeks(week) + Duration::days(weekday as i64);
+
+            if date > today {
+                heatmap.push(' ');
+                continue;
+            }
+
+            let count = per_day.get(&date).copied().unwrap_or(0);
+            heatmap.push(heatmap_level(count, max));
+        }
+
+        heatmap.push('\n');
+    }
+
+    heatmap.push_str(&format!(
+        "\n    Less {} More\n",
+        HEATMAP_LEVELS.iter().collect::<String>()
+    ));
+
+    heatmap
+}
+
+fn longest_streak(per_day: &HashMap<NaiveDate, usize>) -> usize {
+    let mut days: Vec<&NaiveDate> = per_day.keys().collect();
+    days.sort();
+
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for day in days {
+        current = match previous {
+            Some(previous) if *day - previous == Duration::days(1) => current + 1,
+            _ => 1,
+        };
+
+        longest = longest.max(current);
+        previous = Some(*day);
+    }
+
+    longest
+}
+
+/// Summarizes the sessions in the history with totals and a heatmap of the past year.
+pub fn watch_stats() -> anyhow::Result<String> {
+    let entries = read_sessions()?;
+
+    if entries.is_empty() {
+        return Ok(String::from(
+            "No watch activity recorded yet, start watching something first!\n",
+        ));
+    }
+
+    let today = Local::now().date_naive();
+    let year_ago = today - Duration::days(365);
+
+    let mut per_day: HashMap<NaiveDate, usize> = HashMap::new();
+    let mut per_title: HashMap<&str, usize> = HashMap::new();
+    let mut media_ids: HashSet<&str> = HashSet::new();
+
+    for entry in &entries {
+        *per_day.entry(entry.date).or_default() += 1;
+        *per_title.entry(entry.title.as_str()).or_default() += 1;
+        media_ids.insert(entry.media_id.as_str());
+    }
+
+    let past_year = entries
+        .iter()
+        .filter(|entry| entry.date > year_ago)
+        .count();
+
+    let mut stats = String::new();
+
+    stats.push_str(&format!(
+        "{} sessions across {} titles ({} in the past year)\n",
+        entries.len(),
+        media_ids.len(),
+        past_year
+    ));
+
+    if let Some((title, count)) = per_title.iter().max_by_key(|(_, count)| **count) {
+        stats.push_str(&format!("Most watched: {} ({} sessions)\n", title, count));
+    }
+
+    stats.push_str(&format!(
+        "Longest streak: {} days\n\n",
+        longest_streak(&per_day)
+    ));
+
+    stats.push_str(&render_heatmap(&per_day, today));
+
+    Ok(stats)
+}
@@ -0,0 +1,54 @@
+use crate::CLIENT;
+use lazy_static::lazy_static;
+use log::debug;
+use reqwest::StatusCode;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Source links are signed and stop working after a while, so older ones aren't reused
+const MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+lazy_static! {
+    static ref RESOLVED_AT: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+/// Remembers when a stream URL was resolved, so it can be reused for this session.
+pub fn remember_stream(url: &str) {
+    RESOLVED_AT
+        .lock()
+        .unwrap()
+        .insert(url.to_string(), Instant::now());
+}
+
+/// Whether a stream URL resolved earlier in this session can still be played: it is younger
+/// than [`MAX_AGE`] and the server doesn't refuse it.
+pub async fn is_fresh(url: &str) -> bool {
+    let resolved_at = RESOLVED_AT.lock().unwrap().get(url).copied();
+
+    match resolved_at {
+        Some(resolved_at) if resolved_at.elapsed() < MAX_AGE => {}
+        _ => {
+            debug!("Stream link is too old to reuse");
+            return false;
+        }
+    }
+
+    match CLIENT.get(url).send().await {
+        Ok(response) => {
+            let status = response.status();
+            debug!("Probed stream link: {}", status);
+
+            !matches!(
+                status,
+                StatusCode::FORBIDDEN | StatusCode::NOT_FOUND | StatusCode::GONE
+            )
+        }
+        Err(e) => {
+            debug!("Failed to probe stream link: {}", e);
+            false
+        }
+    }
+}
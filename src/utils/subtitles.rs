@@ -0,0 +1,619 @@
+use crate::{providers::vidcloud::Track, Languages, CLIENT};
+use anyhow::anyhow;
+use chardetng::EncodingDetector;
+use clap::builder::{PossibleValue, StringValueParser, TypedValueParser};
+use encoding_rs::{Encoding, UTF_8};
+use lobster_core::redact::redact;
+use log::{debug, warn};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::hash_map::DefaultHasher,
+    ffi::OsStr,
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+/// Name, ISO 639-1 and ISO 639-2/B code of every ISO 639-1 language.
+const LANGUAGE_TABLE: &[(&str, &str, &str)] = &[
+    ("Abkhazian", "ab", "abk"),
+    ("Afar", "aa", "aar"),
+    ("Afrikaans", "af", "afr"),
+    ("Akan", "ak", "aka"),
+    ("Albanian", "sq", "alb"),
+    ("Amharic", "am", "amh"),
+    ("Arabic", "ar", "ara"),
+    ("Aragonese", "an", "arg"),
+    ("Armenian", "hy", "arm"),
+    ("Assamese", "as", "asm"),
+    ("Avaric", "av", "ava"),
+    ("Avestan", "ae", "ave"),
+    ("Aymara", "ay", "aym"),
+    ("Azerbaijani", "az", "aze"),
+    ("Bambara", "bm", "bam"),
+    ("Bashkir", "ba", "bak"),
+    ("Basque", "eu", "baq"),
+    ("Belarusian", "be", "bel"),
+    ("Bengali", "bn", "ben"),
+    ("Bislama", "bi", "bis"),
+    ("Bosnian", "bs", "bos"),
+    ("Breton", "br", "bre"),
+    ("Bulgarian", "bg", "bul"),
+    ("Burmese", "my", "bur"),
+    ("Catalan", "ca", "cat"),
+    ("Chamorro", "ch", "cha"),
+    ("Chechen", "ce", "che"),
+    ("Chichewa", "ny", "nya"),
+    ("Chinese", "zh", "chi"),
+    ("Church Slavic", "cu", "chu"),
+    ("Chuvash", "cv", "chv"),
+    ("Cornish", "kw", "cor"),
+    ("Corsican", "co", "cos"),
+    ("Cree", "cr", "cre"),
+    ("Croatian", "hr", "hrv"),
+    ("Czech", "cs", "cze"),
+    ("Danish", "da", "dan"),
+    ("Divehi", "dv", "div"),
+    ("Dutch", "nl", "dut"),
+    ("Dzongkha", "dz", "dzo"),
+    ("English", "en", "eng"),
+    ("Esperanto", "eo", "epo"),
+    ("Estonian", "et", "est"),
+    ("Ewe", "ee", "ewe"),
+    ("Faroese", "fo", "fao"),
+    ("Fijian", "fj", "fij"),
+    ("Finnish", "fi", "fin"),
+    ("French", "fr", "fre"),
+    ("Fulah", "ff", "ful"),
+    ("Galician", "gl", "glg"),
+    ("Ganda", "lg", "lug"),
+    ("Georgian", "ka", "geo"),
+    ("German", "de", "ger"),
+    ("Greek", "el", "gre"),
+    ("Guarani", "gn", "grn"),
+    ("Gujarati", "gu", "guj"),
+    ("Haitian", "ht", "hat"),
+    ("Hausa", "ha", "hau"),
+    ("Hebrew", "he", "heb"),
+    ("Herero", "hz", "her"),
+    ("Hindi", "hi", "hin"),
+    ("Hiri Motu", "ho", "hmo"),
+    ("Hungarian", "hu", "hun"),
+    ("Icelandic", "is", "ice"),
+    ("Ido", "io", "ido"),
+    ("Igbo", "ig", "ibo"),
+    ("Indonesian", "id", "ind"),
+    ("Interlingua", "ia", "ina"),
+    ("Interlingue", "ie", "ile"),
+    ("Inuktitut", "iu", "iku"),
+    ("Inupiaq", "ik", "ipk"),
+    ("Irish", "ga", "gle"),
+    ("Italian", "it", "ita"),
+    ("Japanese", "ja", "jpn"),
+    ("Javanese", "jv", "jav"),
+    ("Kalaallisut", "kl", "kal"),
+    ("Kannada", "kn", "kan"),
+    ("Kanuri", "kr", "kau"),
+    ("Kashmiri", "ks", "kas"),
+    ("Kazakh", "kk", "kaz"),
+    ("Khmer", "km", "khm"),
+    ("Kikuyu", "ki", "kik"),
+    ("Kinyarwanda", "rw", "kin"),
+    ("Komi", "kv", "kom"),
+    ("Kongo", "kg", "kon"),
+    ("Korean", "ko", "kor"),
+    ("Kuanyama", "kj", "kua"),
+    ("Kurdish", "ku", "kur"),
+    ("Kyrgyz", "ky", "kir"),
+    ("Lao", "lo", "lao"),
+    ("Latin", "la", "lat"),
+    ("Latvian", "lv", "lav"),
+    ("Limburgish", "li", "lim"),
+    ("Lingala", "ln", "lin"),
+    ("Lithuanian", "lt", "lit"),
+    ("Luba-Katanga", "lu", "lub"),
+    ("Luxembourgish", "lb", "ltz"),
+    ("Macedonian", "mk", "mac"),
+    ("Malagasy", "mg", "mlg"),
+    ("Malay", "ms", "may"),
+    ("Malayalam", "ml", "mal"),
+    ("Maltese", "mt", "mlt"),
+    ("Manx", "gv", "glv"),
+    ("Maori", "mi", "mao"),
+    ("Marathi", "mr", "mar"),
+    ("Marshallese", "mh", "mah"),
+    ("Mongolian", "mn", "mon"),
+    ("Nauru", "na", "nau"),
+    ("Navajo", "nv", "nav"),
+    ("Ndonga", "ng", "ndo"),
+    ("Nepali", "ne", "nep"),
+    ("North Ndebele", "nd", "nde"),
+    ("Northern Sami", "se", "sme"),
+    ("Norwegian", "no", "nor"),
+    ("Norwegian Bokmål", "nb", "nob"),
+    ("Norwegian Nynorsk", "nn", "nno"),
+    ("Occitan", "oc", "oci"),
+    ("Ojibwa", "oj", "oji"),
+    ("Oriya", "or", "ori"),
+    ("Oromo", "om", "orm"),
+    ("Ossetian", "os", "oss"),
+    ("Pali", "pi", "pli"),
+    ("Pashto", "ps", "pus"),
+    ("Persian", "fa", "per"),
+    ("Polish", "pl", "pol"),
+    ("Portuguese", "pt", "por"),
+    ("Punjabi", "pa", "pan"),
+    ("Quechua", "qu", "que"),
+    ("Romanian", "ro", "rum"),
+    ("Romansh", "rm", "roh"),
+    ("Rundi", "rn", "run"),
+    ("Russian", "ru", "rus"),
+    ("Samoan", "sm", "smo"),
+    ("Sango", "sg", "sag"),
+    ("Sanskrit", "sa", "san"),
+    ("Sardinian", "sc", "srd"),
+    ("Scottish Gaelic", "gd", "gla"),
+    ("Serbian", "sr", "srp"),
+    ("Shona", "sn", "sna"),
+    ("Sichuan Yi", "ii", "iii"),
+    ("Sindhi", "sd", "snd"),
+    ("Sinhala", "si", "sin"),
+    ("Slovak", "sk", "slo"),
+    ("Slovenian", "sl", "slv"),
+    ("Somali", "so", "som"),
+    ("South Ndebele", "nr", "nbl"),
+    ("Southern Sotho", "st", "sot"),
+    ("Spanish", "es", "spa"),
+    ("Sundanese", "su", "sun"),
+    ("Swahili", "sw", "swa"),
+    ("Swati", "ss", "ssw"),
+    ("Swedish", "sv", "swe"),
+    ("Tagalog", "tl", "tgl"),
+    ("Tahitian", "ty", "tah"),
+    ("Tajik", "tg", "tgk"),
+    ("Tamil", "ta", "tam"),
+    ("Tatar", "tt", "tat"),
+    ("Telugu", "te", "tel"),
+    ("Thai", "th", "tha"),
+    ("Tibetan", "bo", "tib"),
+    ("Tigrinya", "ti", "tir"),
+    ("Tonga", "to", "ton"),
+    ("Tsonga", "ts", "tso"),
+    ("Tswana", "tn", "tsn"),
+    ("Turkish", "tr", "tur"),
+    ("Turkmen", "tk", "tuk"),
+    ("Twi", "tw", "twi"),
+    ("Ukrainian", "uk", "ukr"),
+    ("Urdu", "ur", "urd"),
+    ("Uyghur", "ug", "uig"),
+    ("Uzbek", "uz", "uzb"),
+    ("Venda", "ve", "ven"),
+    ("Vietnamese", "vi", "vie"),
+    ("Volapük", "vo", "vol"),
+    ("Walloon", "wa", "wln"),
+    ("Welsh", "cy", "wel"),
+    ("Western Frisian", "fy", "fry"),
+    ("Wolof", "wo", "wol"),
+    ("Xhosa", "xh", "xho"),
+    ("Yiddish", "yi", "yid"),
+    ("Yoruba", "yo", "yor"),
+    ("Zhuang", "za", "zha"),
+    ("Zulu", "zu", "zul"),
+];
+
+/// Other names track labels and users commonly use, mapped to their ISO 639-1 code.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("Brazilian", "pt"),
+    ("Cantonese", "zh"),
+    ("Castilian", "es"),
+    ("Farsi", "fa"),
+    ("Filipino", "tl"),
+    ("Flemish", "nl"),
+    ("Mandarin", "zh"),
+    ("Moldavian", "ro"),
+    ("Panjabi", "pa"),
+    ("Sinhalese", "si"),
+];
+
+/// ISO 639-2/T codes that differ from the /B code in [`LANGUAGE_TABLE`], mapped to it.
+const TERMINOLOGY_CODES: &[(&str, &str)] = &[
+    ("sqi", "alb"),
+    ("hye", "arm"),
+    ("eus", "baq"),
+    ("mya", "bur"),
+    ("zho", "chi"),
+    ("ces", "cze"),
+    ("nld", "dut"),
+    ("fra", "fre"),
+    ("kat", "geo"),
+    ("deu", "ger"),
+    ("ell", "gre"),
+    ("isl", "ice"),
+    ("mkd", "mac"),
+    ("mri", "mao"),
+    ("msa", "may"),
+    ("fas", "per"),
+    ("ron", "rum"),
+    ("slk", "slo"),
+    ("bod", "tib"),
+    ("cym", "wel"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageTag {
+    pub name: &'static str,
+    pub code: &'static str,
+    pub alpha3: &'static str,
+}
+
+/// Finds `word` in `text` (both lowercase) without matching part of a longer word, so
+/// "malay" doesn't match "malayalam". With `at_start` it has to be the first word.
+fn contains_word(text: &str, word: &str, at_start: bool) -> bool {
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphabetic());
+
+    text.match_indices(word).any(|(i, _)| {
+        (!at_start || i == 0)
+            && is_boundary(text[..i].chars().next_back())
+            && is_boundary(text[i + word.len()..].chars().next())
+    })
+}
+
+impl LanguageTag {
+    pub const UNDETERMINED: Self = Self {
+        name: "Unknown",
+        code: "und",
+        alpha3: "und",
+    };
+
+    fn from_entry(&(name, code, alpha3): &(&'static str, &'static str, &'static str)) -> Self {
+        Self { name, code, alpha3 }
+    }
+
+    fn names() -> impl Iterator<Item = (&'static str, &'static str)> {
+        LANGUAGE_TABLE
+            .iter()
+            .map(|(name, code, _)| (*name, *code))
+            .chain(LANGUAGE_ALIASES.iter().copied())
+    }
+
+    /// Detects the language of a track label such as "English - SDH" or "Portuguese (Brazil)".
+    pub fn from_label(label: &str) -> Self {
+        let label = label.to_lowercase();
+
+        // The longest name wins so "Norwegian Bokmål" isn't read as plain Norwegian
+        let find = |at_start: bool| {
+            Self::names()
+                .filter(|(name, _)| contains_word(&label, &name.to_lowercase(), at_start))
+                .max_by_key(|(name, _)| name.len())
+        };
+
+        find(true)
+            .or_else(|| find(false))
+            .and_then(|(_, code)| Self::from_code(code))
+            .unwrap_or(Self::UNDETERMINED)
+    }
+
+    /// Looks a language up by name, alias, ISO 639-1 or ISO 639-2 (/B or /T) code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.trim();
+
+        let iso_code = LANGUAGE_ALIASES
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(code))
+            .map_or(code, |(_, iso_code)| iso_code);
+
+        let iso_code = TERMINOLOGY_CODES
+            .iter()
+            .find(|(terminology, _)| terminology.eq_ignore_ascii_case(iso_code))
+            .map_or(iso_code, |(_, bibliographic)| bibliographic);
+
+        LANGUAGE_TABLE
+            .iter()
+            .find(|(name, iso, alpha3)| {
+                name.eq_ignore_ascii_case(iso_code)
+                    || iso.eq_ignore_ascii_case(iso_code)
+                    || alpha3.eq_ignore_ascii_case(iso_code)
+            })
+            .map(Self::from_entry)
+    }
+}
+
+impl FromStr for LanguageTag {
+    type Err = anyhow::Error;
+
+    fn from_str(language: &str) -> Result<Self, Self::Err> {
+        Self::from_code(language).ok_or_else(|| {
+            anyhow!(
+                "Unknown language: {} (use a language name or an ISO 639 code like \"pt\" or \"jpn\")",
+                language
+            )
+        })
+    }
+}
+
+/// Parses `--language`, listing the language names as possible values so shell completions
+/// can offer them. Codes and aliases are accepted as well.
+#[derive(Clone)]
+pub struct LanguageTagParser;
+
+impl TypedValueParser for LanguageTagParser {
+    type Value = LanguageTag;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        StringValueParser::new()
+            .try_map(|language| language.parse::<LanguageTag>())
+            .parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            LANGUAGE_TABLE
+                .iter()
+                .map(|(name, _, _)| PossibleValue::new(*name)),
+        ))
+    }
+}
+
+impl Serialize for LanguageTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for LanguageTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<Languages> for LanguageTag {
+    fn from(language: Languages) -> Self {
+        Self::from_code(language.iso_code()).unwrap_or(Self::UNDETERMINED)
+    }
+}
+
+impl Display for LanguageTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Subtitle {
+    pub url: String,
+    pub label: String,
+    pub language: LanguageTag,
+    pub kind: String,
+    pub default: bool,
+    pub sdh: bool,
+    pub forced: bool,
+}
+
+/// Guesses from a label like "English - SDH" or "English [CC]" whether a track is
+/// meant for the deaf and hard of hearing.
+fn is_sdh_label(label: &str) -> bool {
+    let label = label.to_lowercase();
+
+    label.contains("sdh")
+        || label.contains("hearing impaired")
+        || label.contains("[cc]")
+        || label.contains("(cc)")
+        || label.ends_with(" cc")
+        || label.contains("[hi]")
+        || label.contains("(hi)")
+}
+
+fn is_forced_label(label: &str) -> bool {
+    label.to_lowercase().contains("forced")
+}
+
+impl From<Track> for Subtitle {
+    fn from(track: Track) -> Self {
+        Self {
+            language: LanguageTag::from_label(&track.label),
+            sdh: track.kind == "captions" || is_sdh_label(&track.label),
+            forced: is_forced_label(&track.label),
+            url: track.file,
+            label: track.label,
+            kind: track.kind,
+            default: track.default.unwrap_or(false),
+        }
+    }
+}
+
+fn apply_preference(
+    subtitles: Vec<&Subtitle>,
+    wanted: Option<bool>,
+    flag: fn(&Subtitle) -> bool,
+) -> Vec<&Subtitle> {
+    match wanted {
+        Some(true) => {
+            let preferred: Vec<&Subtitle> = subtitles.iter().copied().filter(|s| flag(s)).collect();
+
+            if preferred.is_empty() {
+                subtitles
+            } else {
+                preferred
+            }
+        }
+        Some(false) => subtitles.into_iter().filter(|s| !flag(s)).collect(),
+        None => subtitles,
+    }
+}
+
+/// Narrows subtitles down by their SDH and forced flags. `Some(true)` prefers matching
+/// tracks if there are any, `Some(false)` drops them and `None` keeps everything.
+pub fn filter_subtitles(
+    subtitles: Vec<&Subtitle>,
+    sdh: Option<bool>,
+    forced: Option<bool>,
+) -> Vec<&Subtitle> {
+    let subtitles = apply_preference(subtitles, sdh, |subtitle| subtitle.sdh);
+    apply_preference(subtitles, forced, |subtitle| subtitle.forced)
+}
+
+fn convert_timestamp(timestamp: &str) -> String {
+    let timestamp = timestamp.trim().replace('.', ",");
+
+    // WebVTT allows dropping the hours, SRT doesn't.
+    if timestamp.matches(':').count() == 1 {
+        format!("00:{}", timestamp)
+    } else {
+        timestamp
+    }
+}
+
+/// Converts WebVTT cues into SRT, dropping the header, NOTE/STYLE blocks, cue
+/// settings and WebVTT-only tags.
+pub fn vtt_to_srt(vtt: &str) -> String {
+    let tag_re = Regex::new(r"</?(c|v|lang|ruby|rt)([.\s][^>]*)?>").unwrap();
+
+    let vtt = vtt.replace("\r\n", "\n");
+
+    let mut srt = String::new();
+    let mut cue_number = 0;
+
+    for block in vtt.split("\n\n") {
+        let lines: Vec<&str> = block
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        let Some(timing_index) = lines.iter().position(|line| line.contains("-->")) else {
+            continue;
+        };
+
+        let mut timing = lines[timing_index].split("-->");
+        let start = convert_timestamp(timing.next().unwrap_or_default());
+        let end = convert_timestamp(
+            timing
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .next()
+                .unwrap_or_default(),
+        );
+
+        let text: Vec<String> = lines[timing_index + 1..]
+            .iter()
+            .map(|line| {
+                tag_re
+                    .replace_all(line, "")
+                    .replace("&nbsp;", " ")
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&amp;", "&")
+            })
+            .collect();
+
+        if text.is_empty() {
+            continue;
+        }
+
+        cue_number += 1;
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            cue_number,
+            start,
+            end,
+            text.join("\n")
+        ));
+    }
+
+    srt
+}
+
+/// Decodes subtitle bytes into UTF-8, guessing the encoding when the file is
+/// not already valid UTF-8 (e.g. windows-1251 or windows-1256 tracks).
+pub fn decode_subtitle(bytes: &[u8]) -> (String, &'static Encoding) {
+    let (bytes, encoding) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (&bytes[bom_length..], encoding),
+        None if std::str::from_utf8(bytes).is_ok() => (bytes, UTF_8),
+        None => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, true);
+            (bytes, detector.guess(None, false))
+        }
+    };
+
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+
+    (text.into_owned(), encoding)
+}
+
+async fn convert_subtitle(subtitle: &str) -> anyhow::Result<String> {
+    let bytes = if subtitle.starts_with("http") {
+        CLIENT.get(subtitle).send().await?.bytes().await?.to_vec()
+    } else {
+        std::fs::read(subtitle)?
+    };
+
+    let (text, encoding) = decode_subtitle(&bytes);
+
+    if encoding != UTF_8 {
+        debug!(
+            "Transcoded {} from {} to UTF-8",
+            redact(subtitle),
+            encoding.name()
+        );
+    }
+
+    let subtitle_dir = std::env::temp_dir().join("lobster-rs/subtitles");
+    std::fs::create_dir_all(&subtitle_dir)?;
+
+    let file_name = subtitle
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit(['/', '\\']).next())
+        .filter(|file_name| !file_name.is_empty())
+        .unwrap_or("subtitle.vtt");
+
+    // Tracks from different paths can share a file name, so the full URL picks the file
+    let mut hasher = DefaultHasher::new();
+    subtitle.hash(&mut hasher);
+    let id = format!("{:016x}", hasher.finish());
+
+    let subtitle_path = match file_name.strip_suffix(".vtt") {
+        Some(stem) => {
+            let srt_path = subtitle_dir.join(format!("{}-{}.srt", stem, id));
+            std::fs::write(&srt_path, vtt_to_srt(&text))?;
+            srt_path
+        }
+        None if encoding != UTF_8 => {
+            let utf8_path = subtitle_dir.join(format!("{}-{}", id, file_name));
+            std::fs::write(&utf8_path, text)?;
+            utf8_path
+        }
+        None => return Ok(subtitle.to_owned()),
+    };
+
+    debug!("Converted {} to {:?}", redact(subtitle), subtitle_path);
+
+    Ok(subtitle_path.display().to_string())
+}
+
+/// Converts every subtitle to a local UTF-8 file, turning VTT into SRT along
+/// the way and keeping the original subtitle if the conversion fails.
+pub async fn convert_subtitles(subtitles: Vec<String>) -> Vec<String> {
+    let mut converted = vec![];
+
+    for subtitle in subtitles {
+        match convert_subtitle(&subtitle).await {
+            Ok(subtitle_path) => converted.push(subtitle_path),
+            Err(e) => {
+                warn!("Failed to convert {}: {}", subtitle, e);
+                converted.push(subtitle);
+            }
+        }
+    }
+
+    converted
+}
@@ -0,0 +1,71 @@
+/// Splits a command line into words like a shell would, honouring single and double
+/// quotes and backslash escapes (no variable or glob expansion).
+fn split_words(command: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => word.push(c),
+            (_, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    word.push(escaped);
+                }
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Expands a command template like `haruna --title={title} {url}` into program arguments.
+///
+/// Every `{name}` is replaced by the matching value after the template has been split into
+/// words, so values containing spaces stay a single argument. A word that is exactly
+/// `{name}` is repeated once per value (so `{subtitles}` can become several arguments) and
+/// dropped when there is none. Unknown placeholders are left as they are.
+pub fn expand_command(template: &str, values: &[(&str, Vec<String>)]) -> Vec<String> {
+    let mut args = vec![];
+
+    for word in split_words(template) {
+        if let Some((_, list)) = values
+            .iter()
+            .find(|(name, _)| word == format!("{{{}}}", name))
+        {
+            args.extend(list.iter().cloned());
+            continue;
+        }
+
+        let expanded = values.iter().fold(word, |word, (name, list)| {
+            word.replace(&format!("{{{}}}", name), &list.join(","))
+        });
+
+        args.push(expanded);
+    }
+
+    args
+}
@@ -0,0 +1,104 @@
+use regex::Regex;
+use std::fmt::{self, Display, Formatter};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ELLIPSIS: char = '…';
+const ROFI_TITLE_WIDTH: usize = 80;
+
+/// Width available to titles in the menu, either `title_max_width` from the config or
+/// (when it's 0) whatever fits in the terminal next to fzf's pointer and image preview.
+pub fn title_width(title_max_width: usize, rofi: bool, image_preview: bool) -> usize {
+    if title_max_width > 0 {
+        return title_max_width;
+    }
+
+    if rofi {
+        return ROFI_TITLE_WIDTH;
+    }
+
+    let columns = crossterm::terminal::size()
+        .map(|(columns, _)| columns as usize)
+        .unwrap_or(80);
+
+    let columns = if image_preview { columns / 2 } else { columns };
+
+    columns.saturating_sub(2)
+}
+
+/// Shortens `title` with an ellipsis so `"{title} {suffix}"` fits in `max_width` columns,
+/// never cutting into the suffix holding the year, duration or season/episode counts.
+pub fn ellipsize_title(title: &str, suffix: &str, max_width: usize) -> String {
+    let full_title = format!("{} {}", title, suffix);
+
+    if full_title.width() <= max_width {
+        return full_title;
+    }
+
+    let available = max_width.saturating_sub(suffix.width() + 2);
+
+    let mut width = 0;
+    let mut shortened = String::new();
+
+    for c in title.chars() {
+        width += c.width().unwrap_or(0);
+
+        if width > available {
+            break;
+        }
+
+        shortened.push(c);
+    }
+
+    format!("{}{} {}", shortened.trim_end(), ELLIPSIS, suffix)
+}
+
+/// A season and episode in `SxxEyy` notation, both 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpisodeNumber {
+    pub season: usize,
+    pub episode: usize,
+}
+
+impl Display for EpisodeNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "S{:02}E{:02}", self.season, self.episode)
+    }
+}
+
+fn episode_regex() -> Regex {
+    Regex::new(r"(?i)\bs(\d{1,3})\s*e(\d{1,4})\b").unwrap()
+}
+
+/// Parses `--episode`, e.g. `S03E07` or `s3e7`.
+pub fn parse_episode(input: &str) -> Result<EpisodeNumber, String> {
+    let input = input.trim();
+
+    match split_episode(input) {
+        (rest, Some(episode)) if rest.is_empty() => Ok(episode),
+        _ => Err(format!("expected an episode like S03E07, got `{}`", input)),
+    }
+}
+
+/// Takes an episode in `SxxEyy` notation off a search query, so `breaking bad s03e07`
+/// searches for `breaking bad`.
+pub fn split_episode(query: &str) -> (String, Option<EpisodeNumber>) {
+    let Some(captures) = episode_regex().captures(query) else {
+        return (query.to_string(), None);
+    };
+
+    let (Ok(season), Ok(episode)) = (captures[1].parse(), captures[2].parse()) else {
+        return (query.to_string(), None);
+    };
+
+    if season == 0 || episode == 0 {
+        return (query.to_string(), None);
+    }
+
+    let found = captures.get(0).unwrap().range();
+    let rest = format!("{} {}", &query[..found.start], &query[found.end..]);
+
+    (
+        rest.split_whitespace().collect::<Vec<_>>().join(" "),
+        Some(EpisodeNumber { season, episode }),
+    )
+}
@@ -0,0 +1,79 @@
+use crate::utils::{config::expand_path, subtitles::LanguageTag};
+use anyhow::{anyhow, Context};
+use log::{debug, info};
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// The 16 kHz mono WAV whisper.cpp reads, taken from the audio of `video`.
+fn extract_audio(video: &Path, wav: &Path) -> anyhow::Result<()> {
+    debug!(
+        "Extracting the audio of {} to {}",
+        video.display(),
+        wav.display()
+    );
+
+    let status = Command::new("ffmpeg")
+        .args(["-nostdin", "-y", "-loglevel", "error", "-i"])
+        .arg(video)
+        .args(["-vn", "-ar", "16000", "-ac", "1", "-c:a", "pcm_s16le"])
+        .arg(wav)
+        .status()
+        .context("Failed to run ffmpeg")?;
+
+    if !status.success() {
+        return Err(anyhow!("ffmpeg couldn't extract the audio ({})", status));
+    }
+
+    Ok(())
+}
+
+/// Transcribes the audio of a downloaded video with whisper.cpp into `<title>.<code>.srt`
+/// next to it, like the subtitles that are saved with a download. Whisper only translates
+/// into English, so for other languages the audio has to be in that language already.
+pub fn transcribe(
+    video: &Path,
+    language: LanguageTag,
+    command: &str,
+    model: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let model = model
+        .map(expand_path)
+        .context("`--whisper` needs `whisper_model` in the config, the path to a ggml model")?;
+
+    // whisper.cpp adds the `.srt`
+    let output = video.with_extension(language.code);
+    let wav = std::env::temp_dir().join(format!("lobster-whisper-{}.wav", std::process::id()));
+
+    extract_audio(video, &wav)?;
+
+    info!("Transcribing the audio with whisper.cpp, this can take a while");
+
+    let mut whisper = Command::new(command);
+    whisper
+        .arg("-m")
+        .arg(&model)
+        .arg("-f")
+        .arg(&wav)
+        .args(["-l", "auto", "-osrt", "-of"])
+        .arg(&output)
+        .stdout(Stdio::null());
+
+    if language.code == "en" {
+        whisper.arg("--translate");
+    }
+
+    debug!("Running whisper.cpp: {:?}", whisper);
+
+    let status = whisper.status();
+    let _ = std::fs::remove_file(&wav);
+
+    let status = status.with_context(|| format!("Failed to run `{}`", command))?;
+
+    if !status.success() {
+        return Err(anyhow!("whisper.cpp failed ({})", status));
+    }
+
+    Ok(video.with_extension(format!("{}.srt", language.code)))
+}